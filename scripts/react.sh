@@ -3,9 +3,11 @@
 path="$1/api/post/react"
 
 curl --location --request POST "$path" \
---cookie "token=$3" \
+--cookie "token=$5" \
 --header 'Content-Type: application/json' \
 --header 'Content-Type: text/plain' \
 --data-raw '{
-        "post_id": '$2'
+        "target_type": "'"$2"'",
+        "target_id": '$3',
+        "kind": "'"$4"'"
 }'
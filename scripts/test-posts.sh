@@ -7,20 +7,21 @@ tok_1=$token_1
 tok_2=$(./scripts/signup.sh $ip t1 admin false)
 tok_3=$(./scripts/signup.sh $ip t2 admin false)
 ./scripts/create-post.sh $ip hello welcome yo $tok_1
-./scripts/react.sh $ip 0 $tok_1
-./scripts/react.sh $ip 0 $tok_2
-./scripts/react.sh $ip 0 $tok_3
+./scripts/react.sh $ip post 1 👍 $tok_1
+./scripts/react.sh $ip post 1 👍 $tok_2
+./scripts/react.sh $ip post 1 👍 $tok_3
+./scripts/unreact.sh $ip post 1 👍 $tok_3
 ./scripts/create-post.sh $ip hello welcome yo $tok_1
-./scripts/react.sh $ip 1 $tok_1
+./scripts/react.sh $ip post 2 👍 $tok_1
 ./scripts/create-post.sh $ip hello welcome yo $tok_1
 ./scripts/create-post.sh $ip hello welcome yo $tok_1
 ./scripts/create-post.sh $ip hello welcome yo $tok_1
-./scripts/react.sh $ip 4 $tok_3
+./scripts/react.sh $ip post 5 👍 $tok_3
 ./scripts/create-post.sh $ip hello welcome yo $tok_1
 ./scripts/create-post.sh $ip hello welcome yo $tok_1
 ./scripts/create-post.sh $ip hello welcome yo $tok_1
 ./scripts/create-post.sh $ip hello welcome yo $tok_1
-./scripts/react.sh $ip 8 $tok_2
+./scripts/react.sh $ip post 9 👍 $tok_2
 ./scripts/create-post.sh $ip hello welcome yo $tok_1
 ./scripts/create-post.sh $ip hello welcome yo $tok_1
 ./scripts/create-post.sh $ip hello welcome yo $tok_1
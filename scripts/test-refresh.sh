@@ -0,0 +1,37 @@
+#!/bin/bash
+
+# login, refresh, potem ponowne uzycie starego refresh tokena - cala sesja ma byc odwolana
+
+ip=$1
+jar=$(mktemp)
+trap 'rm -f $jar' EXIT
+
+refresh_token() {
+	grep -P '\trefresh_token\t' $jar | cut -f7
+}
+
+status() {
+	curl -s -o /dev/null -w '%{http_code}' --request POST "$ip$1" "${@:2}"
+}
+
+./scripts/signup.sh $ip refresh_test 1234 false > /dev/null
+curl -s -o /dev/null -c $jar --location --request POST "$ip/api/post/login" \
+--header 'Content-Type: application/json' \
+--data-raw '{
+	"user_name": "refresh_test",
+	"passwd": "1234",
+	"remember_password": false
+}'
+old_refresh=$(refresh_token)
+
+echo "refresh (expect 200): $(status /api/post/refresh -b $jar -c $jar)"
+new_refresh=$(refresh_token)
+if [ "$old_refresh" == "$new_refresh" ]; then
+	echo "FAIL: refresh token was not rotated"
+	exit 1
+fi
+echo "session after refresh (expect 200): $(curl -s -o /dev/null -w '%{http_code}' -b $jar "$ip/api/get/sessions")"
+
+echo "reuse of the old refresh token (expect 401): $(status /api/post/refresh --cookie "refresh_token=$old_refresh")"
+echo "new refresh token after reuse (expect 401): $(status /api/post/refresh --cookie "refresh_token=$new_refresh")"
+echo "access token after reuse (expect 401): $(curl -s -o /dev/null -w '%{http_code}' -b $jar "$ip/api/get/sessions")"
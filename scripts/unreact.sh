@@ -0,0 +1,13 @@
+#!/bin/bash
+
+path="$1/api/post/unreact"
+
+curl --location --request POST "$path" \
+--cookie "token=$5" \
+--header 'Content-Type: application/json' \
+--header 'Content-Type: text/plain' \
+--data-raw '{
+        "target_type": "'"$2"'",
+        "target_id": '$3',
+        "kind": "'"$4"'"
+}'
@@ -2,21 +2,70 @@ DROP TABLE IF EXISTS posts;
 DROP TABLE IF EXISTS users;
 DROP TABLE IF EXISTS posts_tags;
 DROP TABLE IF EXISTS tags;
-DROP TABLE IF EXISTS likes;
+DROP TABLE IF EXISTS reactions;
 DROP TABLE IF EXISTS images;
 DROP TABLE IF EXISTS posts_images;
 DROP TABLE IF EXISTS bans;
 DROP TABLE IF EXISTS uploads;
 DROP TABLE IF EXISTS comments;
+DROP TABLE IF EXISTS post_revisions;
+DROP TABLE IF EXISTS sessions;
+DROP TABLE IF EXISTS refresh_tokens;
+DROP TABLE IF EXISTS password_resets;
+DROP TABLE IF EXISTS follows;
+DROP TABLE IF EXISTS posts_fts;
+DROP TABLE IF EXISTS mentions;
+DROP TABLE IF EXISTS notifications;
+DROP TABLE IF EXISTS notification_actors;
+DROP TABLE IF EXISTS conversations;
+DROP TABLE IF EXISTS conversation_members;
+DROP TABLE IF EXISTS messages;
+DROP TABLE IF EXISTS blocks;
+DROP TABLE IF EXISTS mutes;
+DROP TABLE IF EXISTS reports;
+DROP TABLE IF EXISTS report_actions;
+DROP TABLE IF EXISTS audit_log;
+DROP TABLE IF EXISTS collections;
+DROP TABLE IF EXISTS collections_posts;
+DROP TABLE IF EXISTS reposts;
+DROP TABLE IF EXISTS polls;
+DROP TABLE IF EXISTS poll_options;
+DROP TABLE IF EXISTS poll_votes;
 
 CREATE TABLE posts(
-	post_id INTEGER PRIMARY KEY NOT NULL,
+	post_id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
 	user_id INTEGER NOT NULL,
 	date BIGINT NOT NULL,
 	body VARCHAR(2048) NOT NULL,
-	likes INTEGER NOT NULL
+	likes INTEGER NOT NULL,
+	reaction_counts VARCHAR(2048) NOT NULL DEFAULT '{}',
+	quoted_post_id INTEGER,
+	repost_count INTEGER NOT NULL DEFAULT 0,
+	quote_count INTEGER NOT NULL DEFAULT 0,
+	edited_at BIGINT
+);
+
+CREATE VIRTUAL TABLE posts_fts USING fts5(
+	body,
+	content='posts',
+	content_rowid='post_id',
+	tokenize='unicode61 remove_diacritics 2',
+	prefix='2 3'
 );
 
+CREATE TRIGGER posts_fts_insert AFTER INSERT ON posts BEGIN
+	INSERT INTO posts_fts(rowid, body) VALUES (new.post_id, new.body);
+END;
+
+CREATE TRIGGER posts_fts_delete AFTER DELETE ON posts BEGIN
+	INSERT INTO posts_fts(posts_fts, rowid, body) VALUES ('delete', old.post_id, old.body);
+END;
+
+CREATE TRIGGER posts_fts_update AFTER UPDATE OF body ON posts BEGIN
+	INSERT INTO posts_fts(posts_fts, rowid, body) VALUES ('delete', old.post_id, old.body);
+	INSERT INTO posts_fts(rowid, body) VALUES (new.post_id, new.body);
+END;
+
 CREATE TABLE posts_tags(
 	post_id INTEGER NOT NULL,
 	tag_id INTEGER NOT NULL
@@ -33,13 +82,17 @@ CREATE TABLE users(
 	display_name VARCHAR(64) NOT NULL,
 	description VARCHAR(2048) NOT NULL,
 	passwd VARCHAR(128) NOT NULL,
-	is_admin INTEGER NOT NULL,
+	role VARCHAR(16) NOT NULL DEFAULT 'user',
 	pfp_id INTEGER
 );
 
-CREATE TABLE likes(
+CREATE TABLE reactions(
 	user_id INTEGER NOT NULL,
-	post_id INTEGER NOT NULL
+	target_type VARCHAR(16) NOT NULL,
+	target_id INTEGER NOT NULL,
+	kind VARCHAR(16) NOT NULL,
+	date BIGINT NOT NULL,
+	PRIMARY KEY (user_id, target_type, target_id, kind)
 );
 
 CREATE TABLE posts_images(
@@ -53,10 +106,12 @@ CREATE TABLE images(
 );
 
 CREATE TABLE bans(
+	ban_id INTEGER PRIMARY KEY NOT NULL,
 	user_id INTEGER NOT NULL,
 	given_on INTEGER NOT NULL,
 	expires_on INTEGER NOT NULL,
 	ban_message VARCHAR(2048) NOT NULL,
+	level VARCHAR(16) NOT NULL DEFAULT 'ban',
 	is_active INTEGER NOT NULL
 );
 
@@ -68,9 +123,189 @@ CREATE TABLE uploads(
 
 CREATE TABLE comments(
 	post_id INTEGER NOT NULL,
-	comment_id INTEGER NOT NULL,
+	comment_id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
 	user_id INTEGER NOT NULL,
 	body VARCHAR(512) NOT NULL,
+	date BIGINT NOT NULL,
+	parent_comment_id INTEGER,
+	reaction_counts VARCHAR(2048) NOT NULL DEFAULT '{}',
+	edited_at BIGINT
+);
+
+CREATE TABLE sessions(
+	session_id VARCHAR(64) PRIMARY KEY NOT NULL,
+	user_id INTEGER NOT NULL,
+	created_on BIGINT NOT NULL,
+	expires_on BIGINT NOT NULL,
+	is_active INTEGER NOT NULL,
+	remember INTEGER NOT NULL
+);
+
+CREATE TABLE refresh_tokens(
+	token_hash VARCHAR(64) PRIMARY KEY NOT NULL,
+	session_id VARCHAR(64) NOT NULL,
+	user_id INTEGER NOT NULL,
+	issued_on BIGINT NOT NULL,
+	expires_on BIGINT NOT NULL,
+	is_used INTEGER NOT NULL
+);
+
+CREATE TABLE password_resets(
+	code_hash VARCHAR(64) PRIMARY KEY NOT NULL,
+	user_id INTEGER NOT NULL,
+	created_on BIGINT NOT NULL,
+	expires_on BIGINT NOT NULL,
+	is_used INTEGER NOT NULL
+);
+
+CREATE TABLE follows(
+	follower_id INTEGER NOT NULL,
+	followed_id INTEGER NOT NULL,
+	date BIGINT NOT NULL,
+	PRIMARY KEY (follower_id, followed_id)
+);
+
+CREATE TABLE mentions(
+	mention_id INTEGER PRIMARY KEY NOT NULL,
+	user_id INTEGER NOT NULL,
+	author_id INTEGER NOT NULL,
+	post_id INTEGER NOT NULL,
+	comment_id INTEGER,
+	date BIGINT NOT NULL
+);
+
+CREATE TABLE notifications(
+	notification_id INTEGER PRIMARY KEY NOT NULL,
+	user_id INTEGER NOT NULL,
+	kind VARCHAR(16) NOT NULL,
+	post_id INTEGER,
+	comment_id INTEGER,
+	date BIGINT NOT NULL,
+	is_read INTEGER NOT NULL
+);
+
+CREATE TABLE notification_actors(
+	notification_id INTEGER NOT NULL,
+	actor_id INTEGER NOT NULL,
+	date BIGINT NOT NULL
+);
+
+CREATE TABLE conversations(
+	conversation_id INTEGER PRIMARY KEY NOT NULL,
+	created_by INTEGER NOT NULL,
+	created_on BIGINT NOT NULL,
+	is_group INTEGER NOT NULL,
+	title VARCHAR(64)
+);
+
+CREATE TABLE conversation_members(
+	conversation_id INTEGER NOT NULL,
+	user_id INTEGER NOT NULL,
+	joined_on BIGINT NOT NULL,
+	last_read_message_id INTEGER NOT NULL
+);
+
+CREATE TABLE messages(
+	message_id INTEGER PRIMARY KEY NOT NULL,
+	conversation_id INTEGER NOT NULL,
+	user_id INTEGER NOT NULL,
+	body VARCHAR(2048) NOT NULL,
+	date BIGINT NOT NULL
+);
+
+CREATE TABLE blocks(
+	blocker_id INTEGER NOT NULL,
+	blocked_id INTEGER NOT NULL,
+	date BIGINT NOT NULL,
+	PRIMARY KEY (blocker_id, blocked_id)
+);
+
+CREATE TABLE mutes(
+	muter_id INTEGER NOT NULL,
+	muted_id INTEGER NOT NULL,
+	date BIGINT NOT NULL,
+	PRIMARY KEY (muter_id, muted_id)
+);
+
+CREATE TABLE reports(
+	report_id INTEGER PRIMARY KEY NOT NULL,
+	reporter_id INTEGER NOT NULL,
+	target_type VARCHAR(16) NOT NULL,
+	target_id INTEGER NOT NULL,
+	author_id INTEGER NOT NULL,
+	reason VARCHAR(32) NOT NULL,
+	details VARCHAR(2048) NOT NULL,
+	date BIGINT NOT NULL,
+	status VARCHAR(16) NOT NULL
+);
+
+CREATE TABLE report_actions(
+	action_id INTEGER PRIMARY KEY NOT NULL,
+	report_id INTEGER NOT NULL,
+	admin_id INTEGER NOT NULL,
+	action VARCHAR(16) NOT NULL,
+	note VARCHAR(2048) NOT NULL,
+	date BIGINT NOT NULL
+);
+
+CREATE TABLE audit_log(
+	log_id INTEGER PRIMARY KEY NOT NULL,
+	actor_id INTEGER NOT NULL,
+	action VARCHAR(32) NOT NULL,
+	target_type VARCHAR(16) NOT NULL,
+	target_id INTEGER NOT NULL,
+	payload VARCHAR(4096) NOT NULL,
+	date BIGINT NOT NULL
+);
+
+CREATE TABLE collections(
+	collection_id INTEGER PRIMARY KEY NOT NULL,
+	user_id INTEGER NOT NULL,
+	name VARCHAR(64) NOT NULL,
+	date BIGINT NOT NULL
+);
+
+CREATE TABLE collections_posts(
+	collection_id INTEGER NOT NULL,
+	post_id INTEGER NOT NULL,
+	date BIGINT NOT NULL,
+	PRIMARY KEY (collection_id, post_id)
+);
+
+CREATE TABLE reposts(
+	user_id INTEGER NOT NULL,
+	post_id INTEGER NOT NULL,
+	date BIGINT NOT NULL,
+	PRIMARY KEY (user_id, post_id)
+);
+
+CREATE TABLE polls(
+	post_id INTEGER PRIMARY KEY NOT NULL,
+	multiple_choice INTEGER NOT NULL DEFAULT 0,
+	closes_on BIGINT
+);
+
+CREATE TABLE poll_options(
+	option_id INTEGER PRIMARY KEY NOT NULL,
+	post_id INTEGER NOT NULL,
+	position INTEGER NOT NULL,
+	text VARCHAR(128) NOT NULL,
+	vote_count INTEGER NOT NULL DEFAULT 0
+);
+
+CREATE TABLE poll_votes(
+	post_id INTEGER NOT NULL,
+	user_id INTEGER NOT NULL,
+	option_id INTEGER NOT NULL,
+	date BIGINT NOT NULL,
+	PRIMARY KEY (post_id, user_id, option_id)
+);
+
+CREATE TABLE post_revisions(
+	revision_id INTEGER PRIMARY KEY NOT NULL,
+	post_id INTEGER NOT NULL,
+	body VARCHAR(2048) NOT NULL,
+	tags VARCHAR(2048) NOT NULL,
 	date BIGINT NOT NULL
 );
 
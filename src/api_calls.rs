@@ -2,34 +2,52 @@ use crate::database_functions::*;
 use crate::types::*;
 use crate::auth::*;
 use crate::image_processing::*;
+use crate::state::{with_state, AppState};
+use crate::config::Config;
+use crate::events::Event;
+use crate::permissions::{has_permission, is_role, role_rank, Permission};
+use crate::sanctions::{is_sanction_level, Sanction};
 use bytes::BufMut;
+use jsonwebtoken::TokenData;
 use futures::{StreamExt, TryStreamExt};
-use tracing::Instrument;
 use urlencoding::decode;
 
-use tokio_rusqlite::params;
-use tracing::{error, info};
+use tokio_rusqlite::{Connection, params};
+use tracing::{error, info, warn};
 use warp::filters::multipart::FormData;
 use warp::reject::{Reject, Rejection};
 
+use std::collections::HashMap;
 use std::time::SystemTime;
-use warp::Filter;
+use warp::{Filter, Reply};
 
-pub async fn get_posts_by_user(user_id: i64, limit: i64, offset: i64) -> Result<impl warp::Reply, warp::Rejection> {
-    let connection = tokio_rusqlite::Connection::open("projekt-db")
-        .await
-        .unwrap();
-    let query = "
-        SELECT posts.*, users.user_name, users.display_name, images.image_file, 
-        COUNT(comments.comment_id)
-        FROM posts 
-        JOIN users ON users.user_id=posts.user_id 
+pub async fn get_posts_by_user(user_id: i64, limit: i64, offset: i64, token: Option<String>, state: AppState) -> Result<impl warp::Reply, warp::Rejection> {
+    let viewer_id = viewer_id(&state, token).await;
+    let connection = state.db.get().await;
+    // wlasne posty i reposty usera, repost ma date repostowania
+    let query = format!(
+        "
+        WITH timeline AS (
+            SELECT post_id, date AS activity_date, NULL AS reposted_by FROM posts WHERE user_id = ?1
+            UNION ALL
+            SELECT post_id, date AS activity_date, user_id AS reposted_by FROM reposts WHERE user_id = ?1
+        )
+        SELECT posts.*, users.user_name, users.display_name, images.image_file,
+        timeline.activity_date, timeline.reposted_by,
+        reposters.user_name AS reposted_by_name, reposters.display_name AS reposted_by_display_name,
+        (SELECT COUNT(*) FROM comments WHERE comments.post_id=posts.post_id) AS comment_count
+        FROM timeline
+        JOIN posts ON posts.post_id=timeline.post_id
+        JOIN users ON users.user_id=posts.user_id
+        LEFT JOIN users AS reposters ON reposters.user_id=timeline.reposted_by
         LEFT JOIN images ON users.pfp_id=images.image_id
-        LEFT JOIN comments ON comments.post_id=posts.post_id
-        WHERE users.user_id = ?
-        GROUP BY posts.post_id
-        ORDER BY posts.date DESC
-        LIMIT ? OFFSET ?";
+        WHERE {}
+        AND (timeline.reposted_by IS NULL OR ({}))
+        ORDER BY timeline.activity_date DESC, posts.post_id DESC
+        LIMIT ?2 OFFSET ?3",
+        sanctioned_authors_filter("posts.user_id", viewer_id),
+        hidden_authors_filter("posts.user_id", viewer_id)
+    );
 
     if !check_user_id(&connection, user_id).await {
         let r = "User not found";
@@ -47,33 +65,29 @@ pub async fn get_posts_by_user(user_id: i64, limit: i64, offset: i64) -> Result<
         ));
     }
 
-    let post_list = connection
+    // ukarany shadow widzi swoje posty, reszta pusta liste
+    if viewer_id != user_id && get_sanction(&connection, user_id).await == Sanction::Shadow {
+        let post = PostList { post_list: Vec::new() };
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&post),
+            warp::http::StatusCode::OK,
+        ));
+    }
+
+    let mut post_list = connection
         .call(move |conn| {
-            let mut statement = conn.prepare(query).unwrap();
+            let mut statement = conn.prepare(&query).unwrap();
             let mut rows = statement.query(params![user_id, limit, offset]).unwrap();
             let mut post_vec: Vec<Post> = Vec::new();
             while let Ok(Some(row)) = rows.next() {
-                post_vec.push(Post {
-                    post_id: row.get(0).unwrap(),
-                    user_id: row.get(1).unwrap(),
-                    date: row.get(2).unwrap(),
-                    body: row.get(3).unwrap(),
-                    likes: row.get(4).unwrap(),
-                    user_name: row.get(5).unwrap(),
-                    display_name: row.get(6).unwrap(),
-                    pfp_image: {
-                        match row.get(7) {
-                            Ok(val) => val,
-                            Err(_) => "".to_string()
-                        }
-                    },
-                    like_count: row.get(8).unwrap()
-                });
+                post_vec.push(post_from_row(row));
             }
             Ok(post_vec)
         })
         .await
         .unwrap();
+    attach_quoted_posts(&connection, &mut post_list, viewer_id).await;
+    attach_polls(&connection, &mut post_list, viewer_id).await;
 
     let post = PostList { post_list };
     Ok(warp::reply::with_status(
@@ -82,65 +96,36 @@ pub async fn get_posts_by_user(user_id: i64, limit: i64, offset: i64) -> Result<
     ))
 }
 
-pub async fn get_posts_from_search(phrase: String, limit: i64, offset: i64, date_from: i64) -> Result<impl warp::Reply, warp::Rejection> {
-    let connection = tokio_rusqlite::Connection::open("projekt-db")
-        .await
-        .unwrap();
-
-    let timestamp = SystemTime::now()
-        .duration_since(SystemTime::UNIX_EPOCH)
-        .unwrap()
-        .as_secs() as i64;
+pub async fn get_posts_from_search(
+    phrase: String,
+    limit: i64,
+    offset: i64,
+    date_from: i64,
+    token: Option<String>,
+    state: AppState
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let viewer_id = viewer_id(&state, token).await;
+    let connection = state.db.get().await;
 
     let decoded_phrase = decode(&phrase).unwrap();
 
-    let phrase_cpy = "%".to_string() + &decoded_phrase + "%";
-    let query = format!(
-        "
-        SELECT posts.*, users.user_name, users.display_name, images.image_file,
-        COUNT(comments.comment_id)
-        FROM posts 
-        JOIN users
-        ON posts.user_id = users.user_id
-        LEFT JOIN images ON users.pfp_id=images.image_id
-        LEFT JOIN comments ON comments.post_id=posts.post_id
-        WHERE posts.body LIKE ?
-        AND posts.user_id NOT IN 
-        (SELECT user_id FROM bans WHERE is_active = 1 AND expires_on > {})
-        AND posts.date > ?
-        GROUP BY posts.post_id
-        ORDER BY posts.likes DESC
-        LIMIT ? OFFSET ?
-    ",
-        timestamp
-    );
-    let post_list = connection
-        .call(move |conn| {
-            let mut statement = conn.prepare(&query).unwrap();
-            let mut rows = statement.query(params![phrase_cpy, date_from, limit, offset]).unwrap();
-            let mut post_vec: Vec<Post> = Vec::new();
-            while let Ok(Some(row)) = rows.next() {
-                post_vec.push(Post {
-                    post_id: row.get(0).unwrap(),
-                    user_id: row.get(1).unwrap(),
-                    date: row.get(2).unwrap(),
-                    body: row.get(3).unwrap(),
-                    likes: row.get(4).unwrap(),
-                    user_name: row.get(5).unwrap(),
-                    display_name: row.get(6).unwrap(),
-                    pfp_image: {
-                        match row.get(7) {
-                            Ok(val) => val,
-                            Err(_) => "".to_string()
-                        }
-                    },
-                    like_count: row.get(8).unwrap()
-                });
-            }
-            Ok(post_vec)
-        })
-        .await
-        .unwrap();
+    let post_list = match fts_query(&decoded_phrase) {
+        Some(match_query) => {
+            let search = PostSearchQuery {
+                q: decoded_phrase.to_string(),
+                from: Some(date_from + 1),
+                limit: Some(limit),
+                offset: Some(offset),
+                ..Default::default()
+            };
+            search_posts_db(&connection, match_query, search, viewer_id)
+                .await
+                .into_iter()
+                .map(|result| result.post)
+                .collect()
+        }
+        None => Vec::new(),
+    };
 
     let post = PostList { post_list };
     Ok(warp::reply::with_status(
@@ -149,10 +134,33 @@ pub async fn get_posts_from_search(phrase: String, limit: i64, offset: i64, date
     ))
 }
 
-pub async fn get_users_from_search(phrase: String, limit: i64, offset: i64) -> Result<impl warp::Reply, warp::Rejection> {
-    let connection = tokio_rusqlite::Connection::open("projekt-db")
-        .await
-        .unwrap();
+pub async fn search_posts(mut search: PostSearchQuery, token: Option<String>, state: AppState) -> Result<impl warp::Reply, warp::Rejection> {
+    let viewer_id = viewer_id(&state, token).await;
+    let connection = state.db.get().await;
+
+    let match_query = match fts_query(&search.q) {
+        Some(val) => val,
+        None => {
+            let r = "Empty search query";
+            return Ok(warp::reply::with_status(
+                warp::reply::json(&r),
+                warp::http::StatusCode::BAD_REQUEST,
+            ));
+        }
+    };
+
+    search.tag = search.tag.and_then(|tag| normalize_tag(&tag));
+    let result_list = search_posts_db(&connection, match_query, search, viewer_id).await;
+
+    let results = SearchResultList { result_list };
+    Ok(warp::reply::with_status(
+        warp::reply::json(&results),
+        warp::http::StatusCode::OK,
+    ))
+}
+
+pub async fn get_users_from_search(phrase: String, limit: i64, offset: i64, state: AppState) -> Result<impl warp::Reply, warp::Rejection> {
+    let connection = state.db.get().await;
 
     let timestamp = SystemTime::now()
         .duration_since(SystemTime::UNIX_EPOCH)
@@ -165,12 +173,14 @@ pub async fn get_users_from_search(phrase: String, limit: i64, offset: i64) -> R
     let phrase_cpy = "%".to_string() + &decoded_phrase + "%";
     let query = format!(
         "
-        SELECT users.user_id, users.user_name, users.display_name, users.description, images.image_file
+        SELECT users.user_id, users.user_name, users.display_name, users.description, images.image_file,
+        (SELECT COUNT(*) FROM follows WHERE follows.followed_id=users.user_id) AS follower_count,
+        (SELECT COUNT(*) FROM follows WHERE follows.follower_id=users.user_id) AS following_count
         FROM users 
         LEFT JOIN images ON images.image_id=users.pfp_id
         WHERE users.user_name LIKE ?
         AND users.user_id NOT IN 
-        (SELECT user_id FROM bans WHERE is_active = 1 AND expires_on > {})
+        (SELECT user_id FROM bans WHERE is_active = 1 AND expires_on > {} AND level = 'ban')
         LIMIT ? OFFSET ?
     ", // tutaj tez ten left join do wywalenia
         timestamp
@@ -181,17 +191,7 @@ pub async fn get_users_from_search(phrase: String, limit: i64, offset: i64) -> R
             let mut rows = statement.query(params![phrase_cpy, limit, offset]).unwrap();
             let mut profile_vec: Vec<Profile> = Vec::new();
             while let Ok(Some(row)) = rows.next() {
-                let pfp = match row.get::<_, String>(4) {
-                    Ok(val) => format!("pfp_{}", val),
-                    Err(_) => "".to_string()
-                };
-                profile_vec.push(Profile {
-                    user_id: row.get(0).unwrap(),
-                    user_name: row.get(1).unwrap(),
-                    display_name: row.get(2).unwrap(),
-                    description: row.get(3).unwrap(),
-                    pfp_image: pfp,
-                });
+                profile_vec.push(profile_from_row(row));
             }
             Ok(profile_vec)
         })
@@ -205,58 +205,41 @@ pub async fn get_users_from_search(phrase: String, limit: i64, offset: i64) -> R
     ))
 }
 
-pub async fn get_posts(limit: i64, offset: i64) -> Result<impl warp::Reply, warp::Rejection> {
-    let connection = tokio_rusqlite::Connection::open("projekt-db")
-        .await
-        .unwrap();
-    let timestamp = SystemTime::now()
-        .duration_since(SystemTime::UNIX_EPOCH)
-        .unwrap()
-        .as_secs() as i64;
+pub async fn get_posts(limit: i64, offset: i64, token: Option<String>, state: AppState) -> Result<impl warp::Reply, warp::Rejection> {
+    let viewer_id = viewer_id(&state, token).await;
+    let connection = state.db.get().await;
     let query = format!(
         "
         SELECT posts.*, users.user_name, users.display_name, images.image_file,
-        COUNT(comments.comment_id)
+        COUNT(comments.comment_id) AS comment_count
         FROM posts
         JOIN users ON posts.user_id = users.user_id
         LEFT JOIN images ON users.pfp_id=images.image_id
         LEFT JOIN comments ON comments.post_id=posts.post_id
         WHERE 
-        posts.user_id NOT IN (SELECT user_id FROM bans WHERE is_active = 1 AND expires_on > {})
+        {}
+        AND {}
         GROUP BY posts.post_id
         ORDER BY posts.date DESC
         LIMIT ? OFFSET ?",
-        timestamp
+        sanctioned_authors_filter("posts.user_id", viewer_id),
+        hidden_authors_filter("posts.user_id", viewer_id)
     );
 
-    let post_list = connection
+    let mut post_list = connection
         .call(move |conn| {
             let mut statement = conn.prepare(&query).unwrap();
             let mut rows = statement.query(params![limit, offset]).unwrap();
             let mut post_vec: Vec<Post> = Vec::new();
             while let Ok(Some(row)) = rows.next() {
-                post_vec.push(Post {
-                    post_id: row.get(0).unwrap(),
-                    user_id: row.get(1).unwrap(),
-                    date: row.get(2).unwrap(),
-                    body: row.get(3).unwrap(),
-                    likes: row.get(4).unwrap(),
-                    user_name: row.get(5).unwrap(),
-                    display_name: row.get(6).unwrap(),
-                    pfp_image: {
-                        match row.get(7) {
-                            Ok(val) => val,
-                            Err(_) => "".to_string()
-                        }
-                    },
-                    like_count: row.get(8).unwrap()
-
-                });
+                post_vec.push(post_from_row(row));
             }
             Ok(post_vec)
         })
         .await
         .unwrap();
+    attach_quoted_posts(&connection, &mut post_list, viewer_id).await;
+    attach_polls(&connection, &mut post_list, viewer_id).await;
 
     let post = PostList { post_list };
     Ok(warp::reply::with_status(
@@ -265,58 +248,42 @@ pub async fn get_posts(limit: i64, offset: i64) -> Result<impl warp::Reply, warp
     ))
 }
 
-pub async fn get_posts_top(limit: i64, offset: i64, date_from: i64) -> Result<impl warp::Reply, warp::Rejection> {
-    let connection = tokio_rusqlite::Connection::open("projekt-db")
-        .await
-        .unwrap();
-    let timestamp = SystemTime::now()
-        .duration_since(SystemTime::UNIX_EPOCH)
-        .unwrap()
-        .as_secs() as i64;
+pub async fn get_posts_top(limit: i64, offset: i64, date_from: i64, token: Option<String>, state: AppState) -> Result<impl warp::Reply, warp::Rejection> {
+    let viewer_id = viewer_id(&state, token).await;
+    let connection = state.db.get().await;
     let query = format!(
         "
         SELECT posts.*, users.user_name, users.display_name, images.image_file,    
-        COUNT(comments.comment_id)
+        COUNT(comments.comment_id) AS comment_count
         FROM posts
         JOIN users ON posts.user_id = users.user_id
         LEFT JOIN images ON users.pfp_id=images.image_id
         LEFT JOIN comments ON comments.post_id=posts.post_id
         WHERE 
-        posts.user_id NOT IN (SELECT user_id FROM bans WHERE is_active = 1 AND expires_on > {})
+        {}
+        AND {}
         AND posts.date > ?
         GROUP BY posts.post_id
         ORDER BY posts.likes DESC
         LIMIT ? OFFSET ?",
-        timestamp
+        sanctioned_authors_filter("posts.user_id", viewer_id),
+        hidden_authors_filter("posts.user_id", viewer_id)
     );
 
-    let post_list = connection
+    let mut post_list = connection
         .call(move |conn| {
             let mut statement = conn.prepare(&query).unwrap();
             let mut rows = statement.query(params![date_from, limit, offset]).unwrap();
             let mut post_vec: Vec<Post> = Vec::new();
             while let Ok(Some(row)) = rows.next() {
-                post_vec.push(Post {
-                    post_id: row.get(0).unwrap(),
-                    user_id: row.get(1).unwrap(),
-                    date: row.get(2).unwrap(),
-                    body: row.get(3).unwrap(),
-                    likes: row.get(4).unwrap(),
-                    user_name: row.get(5).unwrap(),
-                    display_name: row.get(6).unwrap(),
-                    pfp_image: {
-                        match row.get(7) {
-                            Ok(val) => val,
-                            Err(_) => "".to_string()
-                        }
-                    },
-                    like_count: row.get(8).unwrap()
-                });
+                post_vec.push(post_from_row(row));
             }
             Ok(post_vec)
         })
         .await
         .unwrap();
+    attach_quoted_posts(&connection, &mut post_list, viewer_id).await;
+    attach_polls(&connection, &mut post_list, viewer_id).await;
 
     let post = PostList { post_list };
     Ok(warp::reply::with_status(
@@ -325,58 +292,42 @@ pub async fn get_posts_top(limit: i64, offset: i64, date_from: i64) -> Result<im
     ))
 }
 
-pub async fn get_posts_bottom(limit: i64, offset: i64, date_from: i64) -> Result<impl warp::Reply, warp::Rejection> {
-    let connection = tokio_rusqlite::Connection::open("projekt-db")
-        .await
-        .unwrap();
-    let timestamp = SystemTime::now()
-        .duration_since(SystemTime::UNIX_EPOCH)
-        .unwrap()
-        .as_secs() as i64;
+pub async fn get_posts_bottom(limit: i64, offset: i64, date_from: i64, token: Option<String>, state: AppState) -> Result<impl warp::Reply, warp::Rejection> {
+    let viewer_id = viewer_id(&state, token).await;
+    let connection = state.db.get().await;
     let query = format!(
         "
         SELECT posts.*, users.user_name, users.display_name, images.image_file,     
-        COUNT(comments.comment_id)
+        COUNT(comments.comment_id) AS comment_count
         FROM posts
         JOIN users ON posts.user_id = users.user_id
         LEFT JOIN images ON users.pfp_id=images.image_id
         LEFT JOIN comments ON comments.post_id=posts.post_id
         WHERE 
-        posts.user_id NOT IN (SELECT user_id FROM bans WHERE is_active = 1 AND expires_on > {})
+        {}
+        AND {}
         AND posts.date > ?
         GROUP BY posts.post_id
         ORDER BY posts.likes ASC
         LIMIT ? OFFSET ?",
-        timestamp
+        sanctioned_authors_filter("posts.user_id", viewer_id),
+        hidden_authors_filter("posts.user_id", viewer_id)
     );
 
-    let post_list = connection
+    let mut post_list = connection
         .call(move |conn| {
             let mut statement = conn.prepare(&query).unwrap();
             let mut rows = statement.query(params![date_from, limit, offset]).unwrap();
             let mut post_vec: Vec<Post> = Vec::new();
             while let Ok(Some(row)) = rows.next() {
-                post_vec.push(Post {
-                    post_id: row.get(0).unwrap(),
-                    user_id: row.get(1).unwrap(),
-                    date: row.get(2).unwrap(),
-                    body: row.get(3).unwrap(),
-                    likes: row.get(4).unwrap(),
-                    user_name: row.get(5).unwrap(),
-                    display_name: row.get(6).unwrap(),
-                    pfp_image: {
-                        match row.get(7) {
-                            Ok(val) => val,
-                            Err(_) => "".to_string()
-                        }
-                    },
-                    like_count: row.get(8).unwrap()
-                });
+                post_vec.push(post_from_row(row));
             }
             Ok(post_vec)
         })
         .await
         .unwrap();
+    attach_quoted_posts(&connection, &mut post_list, viewer_id).await;
+    attach_polls(&connection, &mut post_list, viewer_id).await;
 
     let post = PostList { post_list };
     Ok(warp::reply::with_status(
@@ -385,10 +336,9 @@ pub async fn get_posts_bottom(limit: i64, offset: i64, date_from: i64) -> Result
     ))
 }
 
-pub async fn get_posts_trending(limit: i64, offset: i64, date_from: i64) -> Result<impl warp::Reply, warp::Rejection> {
-    let connection = tokio_rusqlite::Connection::open("projekt-db")
-        .await
-        .unwrap();
+pub async fn get_posts_trending(limit: i64, offset: i64, date_from: i64, token: Option<String>, state: AppState) -> Result<impl warp::Reply, warp::Rejection> {
+    let viewer_id = viewer_id(&state, token).await;
+    let connection = state.db.get().await;
     let timestamp = SystemTime::now()
         .duration_since(SystemTime::UNIX_EPOCH)
         .unwrap()
@@ -396,47 +346,37 @@ pub async fn get_posts_trending(limit: i64, offset: i64, date_from: i64) -> Resu
     let query = format!(
         "
         SELECT posts.*, users.user_name, users.display_name, images.image_file,     
-        COUNT(comments.comment_id)
+        COUNT(comments.comment_id) AS comment_count
         FROM posts
         JOIN users ON posts.user_id = users.user_id
         LEFT JOIN images ON users.pfp_id=images.image_id
         LEFT JOIN comments ON comments.post_id=posts.post_id
         WHERE 
-        posts.user_id NOT IN (SELECT user_id FROM bans WHERE is_active = 1 AND expires_on > {})
+        {}
+        AND {}
         AND posts.date > ?
         GROUP BY posts.post_id
         ORDER BY (posts.likes / (({} - posts.date + 3600) / 3600)) DESC
         LIMIT ? OFFSET ?",
-        timestamp, timestamp
+        sanctioned_authors_filter("posts.user_id", viewer_id),
+        hidden_authors_filter("posts.user_id", viewer_id),
+        timestamp
     );
 
-    let post_list = connection
+    let mut post_list = connection
         .call(move |conn| {
             let mut statement = conn.prepare(&query).unwrap();
             let mut rows = statement.query(params![date_from, limit, offset]).unwrap();
             let mut post_vec: Vec<Post> = Vec::new();
             while let Ok(Some(row)) = rows.next() {
-                post_vec.push(Post {
-                    post_id: row.get(0).unwrap(),
-                    user_id: row.get(1).unwrap(),
-                    date: row.get(2).unwrap(),
-                    body: row.get(3).unwrap(),
-                    likes: row.get(4).unwrap(),
-                    user_name: row.get(5).unwrap(),
-                    display_name: row.get(6).unwrap(),
-                    pfp_image: {
-                        match row.get(7) {
-                            Ok(val) => val,
-                            Err(_) => "".to_string()
-                        }
-                    },
-                    like_count: row.get(8).unwrap()
-                });
+                post_vec.push(post_from_row(row));
             }
             Ok(post_vec)
         })
         .await
         .unwrap();
+    attach_quoted_posts(&connection, &mut post_list, viewer_id).await;
+    attach_polls(&connection, &mut post_list, viewer_id).await;
 
     let post = PostList { post_list };
     Ok(warp::reply::with_status(
@@ -445,17 +385,132 @@ pub async fn get_posts_trending(limit: i64, offset: i64, date_from: i64) -> Resu
     ))
 }
 
-pub async fn get_comments_from_post(post_id: i64) -> Result<impl warp::Reply, warp::Rejection> {
-    let connection = tokio_rusqlite::Connection::open("projekt-db").await.unwrap();
-    let query = "
-        SELECT comments.*, users.user_name, users.display_name, images.image_file
+// kursor to "{activity_date}_{post_id}" ostatniego posta z poprzedniej strony, "0" = od poczatku
+pub async fn get_feed(
+    limit: i64,
+    cursor: String,
+    token: String,
+    state: AppState,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let token = match verify_token(&state, token).await {
+        Ok(val) => val,
+        Err(_) => {
+            let r = "Wrong token";
+            return Ok(warp::reply::with_status(
+                warp::reply::json(&r),
+                warp::http::StatusCode::UNAUTHORIZED,
+            ));
+        }
+    };
+
+    let (before_date, before_id) = if cursor == "0" {
+        (i64::MAX, i64::MAX)
+    } else {
+        let parsed = cursor
+            .split_once('_')
+            .and_then(|(date, id)| Some((date.parse::<i64>().ok()?, id.parse::<i64>().ok()?)));
+        match parsed {
+            Some(val) => val,
+            None => {
+                let r = "Invalid cursor";
+                return Ok(warp::reply::with_status(
+                    warp::reply::json(&r),
+                    warp::http::StatusCode::BAD_REQUEST,
+                ));
+            }
+        }
+    };
+
+    let connection = state.db.get().await;
+    // posty i reposty obserwowanych, ten sam post wielokrotnie zrepostowany tylko raz, z najnowszym repostem
+    let query = format!(
+        "
+        WITH timeline AS (
+            SELECT posts.post_id, posts.date AS activity_date, NULL AS reposted_by
+            FROM posts
+            JOIN follows ON follows.followed_id = posts.user_id
+            WHERE follows.follower_id = ?1
+            UNION ALL
+            SELECT reposts.post_id, reposts.date AS activity_date, reposts.user_id AS reposted_by
+            FROM reposts
+            JOIN follows ON follows.followed_id = reposts.user_id
+            WHERE follows.follower_id = ?1
+            AND {}
+            AND {}
+        ),
+        -- sqlite bierze reposted_by z wiersza z MAX(activity_date)
+        latest AS (
+            SELECT post_id, MAX(activity_date) AS activity_date, reposted_by FROM timeline GROUP BY post_id
+        )
+        SELECT posts.*, users.user_name, users.display_name, images.image_file,
+        latest.activity_date, latest.reposted_by,
+        reposters.user_name AS reposted_by_name, reposters.display_name AS reposted_by_display_name,
+        (SELECT COUNT(*) FROM comments WHERE comments.post_id=posts.post_id) AS comment_count
+        FROM latest
+        JOIN posts ON posts.post_id=latest.post_id
+        JOIN users ON posts.user_id = users.user_id
+        LEFT JOIN users AS reposters ON reposters.user_id=latest.reposted_by
+        LEFT JOIN images ON users.pfp_id=images.image_id
+        WHERE posts.user_id != ?1
+        AND {}
+        AND {}
+        AND (latest.activity_date < ?2 OR (latest.activity_date = ?2 AND posts.post_id < ?3))
+        ORDER BY latest.activity_date DESC, posts.post_id DESC
+        LIMIT ?4",
+        sanctioned_authors_filter("reposts.user_id", token.claims.uid),
+        hidden_authors_filter("reposts.user_id", token.claims.uid),
+        sanctioned_authors_filter("posts.user_id", token.claims.uid),
+        hidden_authors_filter("posts.user_id", token.claims.uid)
+    );
+    let user_id = token.claims.uid;
+
+    let mut post_list = connection
+        .call(move |conn| {
+            let mut statement = conn.prepare(&query).unwrap();
+            let mut rows = statement
+                .query(params![user_id, before_date, before_id, limit])
+                .unwrap();
+            let mut post_vec: Vec<Post> = Vec::new();
+            while let Ok(Some(row)) = rows.next() {
+                post_vec.push(post_from_row(row));
+            }
+            Ok(post_vec)
+        })
+        .await
+        .unwrap();
+    attach_quoted_posts(&connection, &mut post_list, user_id).await;
+    attach_polls(&connection, &mut post_list, user_id).await;
+
+    let next_cursor = match post_list.last() {
+        Some(last) if post_list.len() as i64 == limit => {
+            let activity_date = last.reposted_by.as_ref().map(|r| r.date).unwrap_or(last.date);
+            format!("{}_{}", activity_date, last.post_id)
+        }
+        _ => "".to_string(),
+    };
+
+    let page = FeedPage { post_list, next_cursor };
+    Ok(warp::reply::with_status(
+        warp::reply::json(&page),
+        warp::http::StatusCode::OK,
+    ))
+}
+
+pub async fn get_comments_from_post(post_id: i64, token: Option<String>, state: AppState) -> Result<impl warp::Reply, warp::Rejection> {
+    let viewer_id = viewer_id(&state, token).await;
+    let connection = state.db.get().await;
+    let query = format!("
+        SELECT comments.*, users.user_name, users.display_name, images.image_file,
+        (SELECT COUNT(*) FROM comments AS replies WHERE replies.parent_comment_id=comments.comment_id) AS reply_count
         FROM comments
         JOIN users
         ON users.user_id = comments.user_id
         LEFT JOIN images ON users.pfp_id=images.image_id
         WHERE comments.post_id = ?
+        AND {}
+        AND {}
         ORDER BY comments.date DESC
-    ";
+    ", sanctioned_authors_filter("comments.user_id", viewer_id), hidden_authors_filter("comments.user_id", viewer_id));
     
     if !check_post(&connection, post_id).await {
         let r = "Post not found";
@@ -467,27 +522,11 @@ pub async fn get_comments_from_post(post_id: i64) -> Result<impl warp::Reply, wa
     
     let comment_list = connection
         .call(move |conn| {
-            let mut statement = conn.prepare(query).unwrap();
+            let mut statement = conn.prepare(&query).unwrap();
             let mut rows = statement.query(params![post_id]).unwrap();
             let mut comment_vec: Vec<Comment> = Vec::new();
             while let Ok(Some(row)) = rows.next() {
-                comment_vec.push(
-                    Comment { 
-                        post_id: row.get(0).unwrap(), 
-                        comment_id: row.get(1).unwrap(), 
-                        user_id: row.get(2).unwrap(), 
-                        body: row.get(3).unwrap(), 
-                        date: row.get(4).unwrap(), 
-                        user_name: row.get(5).unwrap(),
-                        display_name: row.get(6).unwrap(),
-                    pfp_image: {
-                        match row.get(7) {
-                            Ok(val) => val,
-                            Err(_) => "".to_string()
-                        }
-                    }
-                    }
-                );
+                comment_vec.push(comment_from_row(row));
             }
             Ok(comment_vec)
         })
@@ -501,17 +540,36 @@ pub async fn get_comments_from_post(post_id: i64) -> Result<impl warp::Reply, wa
     ))
 }
 
-pub async fn get_tags_from_post(post_id: i64) -> Result<impl warp::Reply, warp::Rejection> {
-    let connection = tokio_rusqlite::Connection::open("projekt-db")
-        .await
-        .unwrap();
-    let query = "
-        SELECT tags.tag_name
-        FROM posts_tags
-        JOIN tags
-        ON tags.tag_id=posts_tags.tag_id
-        WHERE posts_tags.post_id = ?
-    ";
+fn attach_replies(comment: &mut Comment, children: &mut HashMap<i64, Vec<Comment>>) {
+    let mut replies = children.remove(&comment.comment_id).unwrap_or_default();
+    for reply in replies.iter_mut() {
+        attach_replies(reply, children);
+    }
+    comment.replies = replies;
+}
+
+// komentarze najwyzszego poziomu sa stronicowane, odpowiedzi doklejane w calosci
+pub async fn get_comment_tree_from_post(
+    post_id: i64,
+    limit: i64,
+    offset: i64,
+    token: Option<String>,
+    state: AppState
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let viewer_id = viewer_id(&state, token).await;
+    let connection = state.db.get().await;
+    let query = format!("
+        SELECT comments.*, users.user_name, users.display_name, images.image_file,
+        (SELECT COUNT(*) FROM comments AS replies WHERE replies.parent_comment_id=comments.comment_id) AS reply_count
+        FROM comments
+        JOIN users
+        ON users.user_id = comments.user_id
+        LEFT JOIN images ON users.pfp_id=images.image_id
+        WHERE comments.post_id = ?
+        AND {}
+        AND {}
+        ORDER BY comments.date ASC, comments.comment_id ASC
+    ", sanctioned_authors_filter("comments.user_id", viewer_id), hidden_authors_filter("comments.user_id", viewer_id));
 
     if !check_post(&connection, post_id).await {
         let r = "Post not found";
@@ -521,36 +579,54 @@ pub async fn get_tags_from_post(post_id: i64) -> Result<impl warp::Reply, warp::
         ));
     }
 
-    let tag_list = connection
+    let all_comments = connection
         .call(move |conn| {
-            let mut statement = conn.prepare(query).unwrap();
+            let mut statement = conn.prepare(&query).unwrap();
             let mut rows = statement.query(params![post_id]).unwrap();
-            let mut tag_vec: Vec<String> = Vec::new();
+            let mut comment_vec: Vec<Comment> = Vec::new();
             while let Ok(Some(row)) = rows.next() {
-                tag_vec.push(row.get(0).unwrap());
+                comment_vec.push(comment_from_row(row));
             }
-            Ok(tag_vec)
+            Ok(comment_vec)
         })
         .await
         .unwrap();
 
-    let tags = TagList { tag_list };
+    let mut top_level: Vec<Comment> = Vec::new();
+    let mut children: HashMap<i64, Vec<Comment>> = HashMap::new();
+    for comment in all_comments {
+        match comment.parent_comment_id {
+            Some(parent) => children.entry(parent).or_default().push(comment),
+            None => top_level.push(comment),
+        }
+    }
+
+    // najnowsze watki pierwsze, tak jak w plaskiej liscie
+    top_level.reverse();
+    let mut comment_list: Vec<Comment> = top_level
+        .into_iter()
+        .skip(offset.max(0) as usize)
+        .take(limit.max(0) as usize)
+        .collect();
+    for comment in comment_list.iter_mut() {
+        attach_replies(comment, &mut children);
+    }
+
+    let comments = CommentList { comment_list };
     Ok(warp::reply::with_status(
-        warp::reply::json(&tags),
+        warp::reply::json(&comments),
         warp::http::StatusCode::OK,
     ))
 }
 
-pub async fn get_like_from_post_by_user(
-    post_id: i64,
-    user_id: i64,
-) -> Result<impl warp::Reply, warp::Rejection> {
-    let connection = tokio_rusqlite::Connection::open("projekt-db")
-        .await
-        .unwrap();
+pub async fn get_post_revisions(post_id: i64, token: Option<String>, state: AppState) -> Result<impl warp::Reply, warp::Rejection> {
+    let viewer_id = viewer_id(&state, token).await;
+    let connection = state.db.get().await;
     let query = "
-        SELECT user_id FROM likes
-        WHERE user_id=? AND post_id=?
+        SELECT revision_id, post_id, body, tags, date
+        FROM post_revisions
+        WHERE post_id = ?
+        ORDER BY date DESC, revision_id DESC
     ";
 
     if !check_post(&connection, post_id).await {
@@ -561,131 +637,281 @@ pub async fn get_like_from_post_by_user(
         ));
     }
 
-    if !check_user_id(&connection, user_id).await {
-        let r = "User not found";
+    // stare wersje ukrywamy tak samo jak sam post w get_post_by_id
+    let author_id = get_user_from_post(&connection, post_id).await;
+    if check_banned(&connection, author_id).await {
+        let r = "The user who made this post has been banned";
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::UNAUTHORIZED,
+        ));
+    }
+
+    if viewer_id != author_id && get_sanction(&connection, author_id).await == Sanction::Shadow {
+        let r = "Post not found";
         return Ok(warp::reply::with_status(
             warp::reply::json(&r),
             warp::http::StatusCode::NOT_FOUND,
         ));
     }
 
-    let exists = connection
+    let revision_list = connection
         .call(move |conn| {
             let mut statement = conn.prepare(query).unwrap();
-            let mut rows = statement.query(params![user_id, post_id]).unwrap();
-            if let Ok(Some(_)) = rows.next() {
-                Ok(true)
-            } else {
-                Ok(false)
+            let mut rows = statement.query(params![post_id]).unwrap();
+            let mut revision_vec: Vec<PostRevision> = Vec::new();
+            while let Ok(Some(row)) = rows.next() {
+                revision_vec.push(PostRevision {
+                    revision_id: row.get(0).unwrap(),
+                    post_id: row.get(1).unwrap(),
+                    body: row.get(2).unwrap(),
+                    tags: serde_json::from_str(&row.get::<_, String>(3).unwrap()).unwrap_or_default(),
+                    date: row.get(4).unwrap(),
+                });
             }
+            Ok(revision_vec)
         })
         .await
         .unwrap();
 
+    let revisions = RevisionList { revision_list };
     Ok(warp::reply::with_status(
-        warp::reply::json(&exists),
+        warp::reply::json(&revisions),
         warp::http::StatusCode::OK,
     ))
 }
 
-pub async fn get_post_by_id(post_id: i64) -> Result<impl warp::Reply, warp::Rejection> {
-    let connection = tokio_rusqlite::Connection::open("projekt-db")
-        .await
-        .unwrap();
+pub async fn get_tags_from_post(post_id: i64, state: AppState) -> Result<impl warp::Reply, warp::Rejection> {
+    let connection = state.db.get().await;
     let query = "
-        SELECT posts.*, users.user_name, users.display_name, images.image_file,
-        COUNT(comments.comment_id)
-        FROM posts
-        JOIN users
-        ON posts.user_id = users.user_id
-        LEFT JOIN comments ON comments.post_id=posts.post_id
-        LEFT JOIN images ON users.pfp_id=images.image_id
-        WHERE posts.post_id = ?";
+        SELECT tags.tag_name
+        FROM posts_tags
+        JOIN tags
+        ON tags.tag_id=posts_tags.tag_id
+        WHERE posts_tags.post_id = ?
+    ";
+
+    if !check_post(&connection, post_id).await {
+        let r = "Post not found";
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::NOT_FOUND,
+        ));
+    }
 
-    let post = connection
+    let tag_list = connection
         .call(move |conn| {
             let mut statement = conn.prepare(query).unwrap();
             let mut rows = statement.query(params![post_id]).unwrap();
-            let post: Post;
-            if let Ok(Some(row)) = rows.next() {
-                post = Post {
-                    post_id: row.get(0).unwrap(),
-                    user_id: row.get(1).unwrap(),
-                    date: row.get(2).unwrap(),
-                    body: row.get(3).unwrap(),
-                    likes: row.get(4).unwrap(),
-                    user_name: row.get(5).unwrap(),
-                    display_name: row.get(6).unwrap(),
-                    pfp_image: {
-                        match row.get(7) {
-                            Ok(val) => val,
-                            Err(_) => "".to_string()
-                        }
-                    },
-                    like_count: row.get(8).unwrap()
-                };
-            } else {
-                post = Post {
-                    post_id: -1,
-                    user_id: 0,
-                    date: 0,
-                    body: "".to_string(),
-                    likes: 0,
-                    user_name: "".to_string(),
-                    display_name: "".to_string(),
-                    pfp_image: "".to_string(),
-                    like_count: 0
-                };
+            let mut tag_vec: Vec<String> = Vec::new();
+            while let Ok(Some(row)) = rows.next() {
+                tag_vec.push(row.get(0).unwrap());
             }
-            Ok(post)
+            Ok(tag_vec)
         })
         .await
         .unwrap();
 
-    if check_banned(&connection, post.user_id).await == true {
-        let r = "The user who made this post has been banned";
-        return Ok(warp::reply::with_status(
-            warp::reply::json(&r),
-            warp::http::StatusCode::UNAUTHORIZED,
-        ));
-    }
-
-    if post.post_id != -1 {
-        Ok(warp::reply::with_status(
-            warp::reply::json(&post),
-            warp::http::StatusCode::OK,
-        ))
-    } else {
-        let r = "Post not found";
-        Ok(warp::reply::with_status(
-            warp::reply::json(&r),
-            warp::http::StatusCode::NOT_FOUND,
-        ))
-    }
+    let tags = TagList { tag_list };
+    Ok(warp::reply::with_status(
+        warp::reply::json(&tags),
+        warp::http::StatusCode::OK,
+    ))
 }
 
-pub async fn get_profile_by_id(user_id: i64) -> Result<impl warp::Reply, warp::Rejection> {
-    let connection = tokio_rusqlite::Connection::open("projekt-db")
+pub async fn get_posts_by_tag(
+    tag: String,
+    ordering: String,
+    limit: i64,
+    offset: i64,
+    token: Option<String>,
+    state: AppState
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let viewer_id = viewer_id(&state, token).await;
+    let connection = state.db.get().await;
+
+    let order_by = match ordering.as_str() {
+        "new" => "posts.date DESC, posts.post_id DESC",
+        "top" => "posts.likes DESC, posts.date DESC",
+        _ => {
+            let r = "Invalid ordering";
+            return Ok(warp::reply::with_status(
+                warp::reply::json(&r),
+                warp::http::StatusCode::BAD_REQUEST,
+            ));
+        }
+    };
+
+    let tag_name = match normalize_tag(&decode(&tag).unwrap()) {
+        Some(val) => val,
+        None => {
+            let r = "Tag not found";
+            return Ok(warp::reply::with_status(
+                warp::reply::json(&r),
+                warp::http::StatusCode::NOT_FOUND,
+            ));
+        }
+    };
+
+    let tag_id = match get_tag_by_name(&connection, tag_name).await {
+        Ok(val) => val,
+        Err(_) => {
+            let r = "Tag not found";
+            return Ok(warp::reply::with_status(
+                warp::reply::json(&r),
+                warp::http::StatusCode::NOT_FOUND,
+            ));
+        }
+    };
+
+    let query = format!(
+        "
+        SELECT posts.*, users.user_name, users.display_name, images.image_file,
+        COUNT(comments.comment_id) AS comment_count
+        FROM posts_tags
+        JOIN posts ON posts.post_id=posts_tags.post_id
+        JOIN users ON posts.user_id = users.user_id
+        LEFT JOIN images ON users.pfp_id=images.image_id
+        LEFT JOIN comments ON comments.post_id=posts.post_id
+        WHERE posts_tags.tag_id = ?
+        AND {}
+        AND {}
+        GROUP BY posts.post_id
+        ORDER BY {}
+        LIMIT ? OFFSET ?",
+        sanctioned_authors_filter("posts.user_id", viewer_id),
+        hidden_authors_filter("posts.user_id", viewer_id),
+        order_by
+    );
+
+    let mut post_list = connection
+        .call(move |conn| {
+            let mut statement = conn.prepare(&query).unwrap();
+            let mut rows = statement.query(params![tag_id, limit, offset]).unwrap();
+            let mut post_vec: Vec<Post> = Vec::new();
+            while let Ok(Some(row)) = rows.next() {
+                post_vec.push(post_from_row(row));
+            }
+            Ok(post_vec)
+        })
         .await
         .unwrap();
+    attach_quoted_posts(&connection, &mut post_list, viewer_id).await;
+    attach_polls(&connection, &mut post_list, viewer_id).await;
 
-    if check_banned(&connection, user_id).await == true {
-        let r = "This user has been banned";
+    let post = PostList { post_list };
+    Ok(warp::reply::with_status(
+        warp::reply::json(&post),
+        warp::http::StatusCode::OK,
+    ))
+}
+
+pub async fn get_tags_autocomplete(prefix: String, limit: i64, state: AppState) -> Result<impl warp::Reply, warp::Rejection> {
+    let connection = state.db.get().await;
+    // zakres zamiast LIKE, zeby szlo po indeksie z UNIQUE
+    let query = "
+        SELECT tags.tag_name, COUNT(posts_tags.post_id) AS post_count
+        FROM tags
+        LEFT JOIN posts_tags ON posts_tags.tag_id=tags.tag_id
+        WHERE tags.tag_name >= ? AND tags.tag_name < ?
+        GROUP BY tags.tag_id
+        ORDER BY post_count DESC, tags.tag_name ASC
+        LIMIT ?
+    ";
+
+    let tag_list = match normalize_tag(&decode(&prefix).unwrap()) {
+        Some(prefix) => {
+            let upper_bound = format!("{}{}", prefix, char::MAX);
+            connection
+                .call(move |conn| {
+                    let mut statement = conn.prepare(query).unwrap();
+                    let mut rows = statement.query(params![prefix, upper_bound, limit]).unwrap();
+                    let mut tag_vec: Vec<TagCount> = Vec::new();
+                    while let Ok(Some(row)) = rows.next() {
+                        tag_vec.push(TagCount {
+                            tag_name: row.get(0).unwrap(),
+                            post_count: row.get(1).unwrap(),
+                        });
+                    }
+                    Ok(tag_vec)
+                })
+                .await
+                .unwrap()
+        }
+        None => Vec::new(),
+    };
+
+    let tags = TagCountList { tag_list };
+    Ok(warp::reply::with_status(
+        warp::reply::json(&tags),
+        warp::http::StatusCode::OK,
+    ))
+}
+
+pub async fn get_tags_trending(limit: i64, state: AppState) -> Result<impl warp::Reply, warp::Rejection> {
+    let connection = state.db.get().await;
+    let timestamp = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64;
+    let window_start = timestamp - state.config.trending_tags_window;
+
+    let query = format!(
+        "
+        SELECT tags.tag_name, COUNT(posts.post_id) AS post_count
+        FROM posts_tags
+        JOIN tags ON tags.tag_id=posts_tags.tag_id
+        JOIN posts ON posts.post_id=posts_tags.post_id
+        WHERE posts.date > ?
+        AND {}
+        GROUP BY tags.tag_id
+        ORDER BY post_count DESC, MAX(posts.date) DESC
+        LIMIT ?",
+        sanctioned_authors_filter("posts.user_id", -1)
+    );
+
+    let tag_list = connection
+        .call(move |conn| {
+            let mut statement = conn.prepare(&query).unwrap();
+            let mut rows = statement.query(params![window_start, limit]).unwrap();
+            let mut tag_vec: Vec<TagCount> = Vec::new();
+            while let Ok(Some(row)) = rows.next() {
+                tag_vec.push(TagCount {
+                    tag_name: row.get(0).unwrap(),
+                    post_count: row.get(1).unwrap(),
+                });
+            }
+            Ok(tag_vec)
+        })
+        .await
+        .unwrap();
+
+    let tags = TagCountList { tag_list };
+    Ok(warp::reply::with_status(
+        warp::reply::json(&tags),
+        warp::http::StatusCode::OK,
+    ))
+}
+
+pub async fn get_like_from_post_by_user(
+    post_id: i64,
+    user_id: i64,
+    state: AppState,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let connection = state.db.get().await;
+    let query = "
+        SELECT user_id FROM reactions
+        WHERE user_id=? AND target_type='post' AND target_id=?
+    ";
+
+    if !check_post(&connection, post_id).await {
+        let r = "Post not found";
         return Ok(warp::reply::with_status(
             warp::reply::json(&r),
-            warp::http::StatusCode::UNAUTHORIZED, // nw czy nie ma lepszego kodu do zwrocenia
+            warp::http::StatusCode::NOT_FOUND,
         ));
     }
 
-    let query = "
-        SELECT users.user_id, users.user_name, 
-               users.display_name, users.description,
-               images.image_file
-        FROM users 
-        LEFT JOIN images ON images.image_id=users.pfp_id
-        WHERE users.user_id = ?
-    "; // na razie jest left join zeby zwracalo cokolwiek, do naprawienia
-
     if !check_user_id(&connection, user_id).await {
         let r = "User not found";
         return Ok(warp::reply::with_status(
@@ -694,91 +920,181 @@ pub async fn get_profile_by_id(user_id: i64) -> Result<impl warp::Reply, warp::R
         ));
     }
 
-    let profile = connection
+    let exists = connection
         .call(move |conn| {
             let mut statement = conn.prepare(query).unwrap();
-            let mut rows = statement.query(params![user_id]).unwrap();
-            let profile: Profile;
-            if let Ok(Some(row)) = rows.next() {
-                let pfp = match row.get::<_, String>(4) {
-                    Ok(val) => format!("pfp_{}", val),
-                    Err(_) => "".to_string()
-                };
-                profile = Profile {
-                    user_id: row.get(0).unwrap(),
-                    user_name: row.get(1).unwrap(),
-                    display_name: row.get(2).unwrap(),
-                    description: row.get(3).unwrap(),
-                    pfp_image: pfp
-                };
+            let mut rows = statement.query(params![user_id, post_id]).unwrap();
+            if let Ok(Some(_)) = rows.next() {
+                Ok(true)
             } else {
-                profile = Profile {
-                    user_id: -1,
-                    user_name: "".to_string(),
-                    display_name: "".to_string(),
-                    description: "".to_string(),
-                    pfp_image: "".to_string()
-                };
+                Ok(false)
             }
-            Ok(profile)
         })
         .await
         .unwrap();
 
     Ok(warp::reply::with_status(
-        warp::reply::json(&profile),
+        warp::reply::json(&exists),
         warp::http::StatusCode::OK,
     ))
 }
 
-pub async fn get_user_name(user_id: i64) -> Result<impl warp::Reply, warp::Rejection> {
-    let connection = tokio_rusqlite::Connection::open("projekt-db")
-        .await
-        .unwrap();
-    let query = "SELECT user_name FROM users WHERE user_id = ?";
+pub async fn get_reactions(
+    target_type: String,
+    target_id: i64,
+    limit: i64,
+    offset: i64,
+    token: Option<String>,
+    state: AppState,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let viewer_id = viewer_id(&state, token).await;
+    let connection = state.db.get().await;
 
-    if !check_user_id(&connection, user_id).await {
-        let r = "User not found";
+    if !REACTION_TARGETS.contains(&target_type.as_str()) {
+        let r = "Invalid target type";
         return Ok(warp::reply::with_status(
             warp::reply::json(&r),
-            warp::http::StatusCode::NOT_FOUND,
+            warp::http::StatusCode::BAD_REQUEST,
         ));
     }
 
-    if check_banned(&connection, user_id).await == true {
-        let r = "This user has been banned";
+    let (_, author_id) = get_reaction_target(&connection, &target_type, target_id).await;
+    if author_id == -1 {
+        let r = if target_type == "post" { "Post not found" } else { "Comment not found" };
         return Ok(warp::reply::with_status(
             warp::reply::json(&r),
-            warp::http::StatusCode::UNAUTHORIZED,
+            warp::http::StatusCode::NOT_FOUND,
         ));
     }
 
-    let name = connection
+    let query = format!(
+        "
+        SELECT reactions.user_id, reactions.kind, reactions.date,
+        users.user_name, users.display_name, images.image_file
+        FROM reactions
+        JOIN users ON users.user_id=reactions.user_id
+        LEFT JOIN images ON images.image_id=users.pfp_id
+        WHERE reactions.target_type = ? AND reactions.target_id = ?
+        AND {}
+        ORDER BY reactions.date DESC
+        LIMIT ? OFFSET ?",
+        sanctioned_authors_filter("reactions.user_id", viewer_id)
+    );
+
+    let reaction_list = connection
         .call(move |conn| {
-            let mut statement = conn.prepare(query).unwrap();
-            let mut rows = statement.query(params![user_id]).unwrap();
-            if let Ok(Some(row)) = rows.next() {
-                Ok(row.get(0).unwrap())
-            } else {
-                Ok("".to_string())
+            let mut statement = conn.prepare(&query).unwrap();
+            let mut rows = statement.query(params![target_type, target_id, limit, offset]).unwrap();
+            let mut reaction_vec: Vec<Reaction> = Vec::new();
+            while let Ok(Some(row)) = rows.next() {
+                reaction_vec.push(Reaction {
+                    user_id: row.get("user_id").unwrap(),
+                    user_name: row.get("user_name").unwrap(),
+                    display_name: row.get("display_name").unwrap(),
+                    pfp_image: row.get("image_file").unwrap_or_default(),
+                    kind: row.get("kind").unwrap(),
+                    date: row.get("date").unwrap(),
+                });
             }
+            Ok(reaction_vec)
         })
         .await
         .unwrap();
 
+    let reactions = ReactionList { reaction_list };
     Ok(warp::reply::with_status(
-        warp::reply::json(&name),
+        warp::reply::json(&reactions),
         warp::http::StatusCode::OK,
     ))
 }
 
-pub async fn get_user_id(user_name: String) -> Result<impl warp::Reply, warp::Rejection> {
-    let connection = tokio_rusqlite::Connection::open("projekt-db")
+pub async fn get_reaction_kinds(state: AppState) -> Result<impl warp::Reply, warp::Rejection> {
+    Ok(warp::reply::with_status(
+        warp::reply::json(&state.config.reaction_kinds),
+        warp::http::StatusCode::OK,
+    ))
+}
+
+pub async fn get_post_by_id(post_id: i64, token: Option<String>, state: AppState) -> Result<impl warp::Reply, warp::Rejection> {
+    let viewer_id = viewer_id(&state, token).await;
+    let connection = state.db.get().await;
+    let query = "
+        SELECT posts.*, users.user_name, users.display_name, images.image_file,
+        COUNT(comments.comment_id) AS comment_count
+        FROM posts
+        JOIN users
+        ON posts.user_id = users.user_id
+        LEFT JOIN comments ON comments.post_id=posts.post_id
+        LEFT JOIN images ON users.pfp_id=images.image_id
+        WHERE posts.post_id = ?
+        GROUP BY posts.post_id";
+
+    let mut post = connection
+        .call(move |conn| {
+            let mut statement = conn.prepare(query).unwrap();
+            let mut rows = statement.query(params![post_id]).unwrap();
+            let post: Post;
+            if let Ok(Some(row)) = rows.next() {
+                post = post_from_row(row);
+            } else {
+                post = Post {
+                    post_id: -1,
+                    ..Default::default()
+                };
+            }
+            Ok(post)
+        })
         .await
         .unwrap();
-    let query = "SELECT user_id FROM users WHERE user_name = ?";
 
-    if !check_user_name(&connection, user_name.clone()).await {
+    if check_banned(&connection, post.user_id).await {
+        let r = "The user who made this post has been banned";
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::UNAUTHORIZED,
+        ));
+    }
+
+    let hidden = viewer_id != post.user_id && get_sanction(&connection, post.user_id).await == Sanction::Shadow;
+    if post.post_id != -1 && !hidden {
+        attach_quoted_posts(&connection, std::slice::from_mut(&mut post), viewer_id).await;
+        attach_polls(&connection, std::slice::from_mut(&mut post), viewer_id).await;
+        Ok(warp::reply::with_status(
+            warp::reply::json(&post),
+            warp::http::StatusCode::OK,
+        ))
+    } else {
+        let r = "Post not found";
+        Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::NOT_FOUND,
+        ))
+    }
+}
+
+pub async fn get_profile_by_id(user_id: i64, state: AppState) -> Result<impl warp::Reply, warp::Rejection> {
+    let connection = state.db.get().await;
+
+    if check_banned(&connection, user_id).await {
+        let r = "This user has been banned";
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::UNAUTHORIZED, // nw czy nie ma lepszego kodu do zwrocenia
+        ));
+    }
+
+    let query = "
+        SELECT users.user_id, users.user_name, 
+               users.display_name, users.description,
+               images.image_file,
+        (SELECT COUNT(*) FROM follows WHERE follows.followed_id=users.user_id) AS follower_count,
+               (SELECT COUNT(*) FROM follows WHERE follows.follower_id=users.user_id) AS following_count
+        FROM users 
+        LEFT JOIN images ON images.image_id=users.pfp_id
+        WHERE users.user_id = ?
+    "; // na razie jest left join zeby zwracalo cokolwiek, do naprawienia
+
+    if !check_user_id(&connection, user_id).await {
         let r = "User not found";
         return Ok(warp::reply::with_status(
             warp::reply::json(&r),
@@ -786,37 +1102,210 @@ pub async fn get_user_id(user_name: String) -> Result<impl warp::Reply, warp::Re
         ));
     }
 
-    let id = connection
+    let profile = connection
         .call(move |conn| {
             let mut statement = conn.prepare(query).unwrap();
-            let mut rows = statement.query(params![user_name]).unwrap();
+            let mut rows = statement.query(params![user_id]).unwrap();
+            let profile: Profile;
             if let Ok(Some(row)) = rows.next() {
-                Ok(row.get(0).unwrap())
+                profile = profile_from_row(row);
             } else {
-                Ok(-1)
+                profile = Profile {
+                    user_id: -1,
+                    ..Default::default()
+                };
             }
+            Ok(profile)
         })
         .await
         .unwrap();
 
     Ok(warp::reply::with_status(
-        warp::reply::json(&id),
+        warp::reply::json(&profile),
         warp::http::StatusCode::OK,
     ))
 }
 
-pub async fn get_images_from_post(post_id: i64) -> Result<impl warp::Reply, warp::Rejection> {
-    let connection = tokio_rusqlite::Connection::open("projekt-db")
-        .await
-        .unwrap();
-    let query = "SELECT image_file 
-                 FROM posts_images 
-                 JOIN images ON images.image_id=posts_images.image_id 
-                 WHERE post_id = ?";
-
-    if !check_post(&connection, post_id).await {
-        let r = "Post not found";
-        return Ok(warp::reply::with_status(
+pub async fn get_followers(user_id: i64, limit: i64, offset: i64, state: AppState) -> Result<impl warp::Reply, warp::Rejection> {
+    let connection = state.db.get().await;
+    let timestamp = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64;
+    let query = format!(
+        "
+        SELECT users.user_id, users.user_name, users.display_name, users.description, images.image_file,
+        (SELECT COUNT(*) FROM follows WHERE follows.followed_id=users.user_id) AS follower_count,
+        (SELECT COUNT(*) FROM follows WHERE follows.follower_id=users.user_id) AS following_count
+        FROM follows
+        JOIN users ON users.user_id=follows.follower_id
+        LEFT JOIN images ON images.image_id=users.pfp_id
+        WHERE follows.followed_id = ?
+        AND users.user_id NOT IN 
+        (SELECT user_id FROM bans WHERE is_active = 1 AND expires_on > {} AND level = 'ban')
+        ORDER BY follows.date DESC
+        LIMIT ? OFFSET ?",
+        timestamp
+    );
+
+    if !check_user_id(&connection, user_id).await {
+        let r = "User not found";
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::NOT_FOUND,
+        ));
+    }
+
+    let profile_list = connection
+        .call(move |conn| {
+            let mut statement = conn.prepare(&query).unwrap();
+            let mut rows = statement.query(params![user_id, limit, offset]).unwrap();
+            let mut profile_vec: Vec<Profile> = Vec::new();
+            while let Ok(Some(row)) = rows.next() {
+                profile_vec.push(profile_from_row(row));
+            }
+            Ok(profile_vec)
+        })
+        .await
+        .unwrap();
+
+    let profiles = ProfileList { profile_list };
+    Ok(warp::reply::with_status(
+        warp::reply::json(&profiles),
+        warp::http::StatusCode::OK,
+    ))
+}
+
+pub async fn get_following(user_id: i64, limit: i64, offset: i64, state: AppState) -> Result<impl warp::Reply, warp::Rejection> {
+    let connection = state.db.get().await;
+    let timestamp = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64;
+    let query = format!(
+        "
+        SELECT users.user_id, users.user_name, users.display_name, users.description, images.image_file,
+        (SELECT COUNT(*) FROM follows WHERE follows.followed_id=users.user_id) AS follower_count,
+        (SELECT COUNT(*) FROM follows WHERE follows.follower_id=users.user_id) AS following_count
+        FROM follows
+        JOIN users ON users.user_id=follows.followed_id
+        LEFT JOIN images ON images.image_id=users.pfp_id
+        WHERE follows.follower_id = ?
+        AND users.user_id NOT IN 
+        (SELECT user_id FROM bans WHERE is_active = 1 AND expires_on > {} AND level = 'ban')
+        ORDER BY follows.date DESC
+        LIMIT ? OFFSET ?",
+        timestamp
+    );
+
+    if !check_user_id(&connection, user_id).await {
+        let r = "User not found";
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::NOT_FOUND,
+        ));
+    }
+
+    let profile_list = connection
+        .call(move |conn| {
+            let mut statement = conn.prepare(&query).unwrap();
+            let mut rows = statement.query(params![user_id, limit, offset]).unwrap();
+            let mut profile_vec: Vec<Profile> = Vec::new();
+            while let Ok(Some(row)) = rows.next() {
+                profile_vec.push(profile_from_row(row));
+            }
+            Ok(profile_vec)
+        })
+        .await
+        .unwrap();
+
+    let profiles = ProfileList { profile_list };
+    Ok(warp::reply::with_status(
+        warp::reply::json(&profiles),
+        warp::http::StatusCode::OK,
+    ))
+}
+
+pub async fn get_user_name(user_id: i64, state: AppState) -> Result<impl warp::Reply, warp::Rejection> {
+    let connection = state.db.get().await;
+    let query = "SELECT user_name FROM users WHERE user_id = ?";
+
+    if !check_user_id(&connection, user_id).await {
+        let r = "User not found";
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::NOT_FOUND,
+        ));
+    }
+
+    if check_banned(&connection, user_id).await {
+        let r = "This user has been banned";
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::UNAUTHORIZED,
+        ));
+    }
+
+    let name = connection
+        .call(move |conn| {
+            let mut statement = conn.prepare(query).unwrap();
+            let mut rows = statement.query(params![user_id]).unwrap();
+            if let Ok(Some(row)) = rows.next() {
+                Ok(row.get(0).unwrap())
+            } else {
+                Ok("".to_string())
+            }
+        })
+        .await
+        .unwrap();
+
+    Ok(warp::reply::with_status(
+        warp::reply::json(&name),
+        warp::http::StatusCode::OK,
+    ))
+}
+
+pub async fn get_user_id(user_name: String, state: AppState) -> Result<impl warp::Reply, warp::Rejection> {
+    let connection = state.db.get().await;
+    let query = "SELECT user_id FROM users WHERE user_name = ?";
+
+    if !check_user_name(&connection, user_name.clone()).await {
+        let r = "User not found";
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::NOT_FOUND,
+        ));
+    }
+
+    let id = connection
+        .call(move |conn| {
+            let mut statement = conn.prepare(query).unwrap();
+            let mut rows = statement.query(params![user_name]).unwrap();
+            if let Ok(Some(row)) = rows.next() {
+                Ok(row.get(0).unwrap())
+            } else {
+                Ok(-1)
+            }
+        })
+        .await
+        .unwrap();
+
+    Ok(warp::reply::with_status(
+        warp::reply::json(&id),
+        warp::http::StatusCode::OK,
+    ))
+}
+
+pub async fn get_images_from_post(post_id: i64, state: AppState) -> Result<impl warp::Reply, warp::Rejection> {
+    let connection = state.db.get().await;
+    let query = "SELECT image_file 
+                 FROM posts_images 
+                 JOIN images ON images.image_id=posts_images.image_id 
+                 WHERE post_id = ?";
+
+    if !check_post(&connection, post_id).await {
+        let r = "Post not found";
+        return Ok(warp::reply::with_status(
             warp::reply::json(&r),
             warp::http::StatusCode::NOT_FOUND,
         ));
@@ -842,9 +1331,20 @@ pub async fn get_images_from_post(post_id: i64) -> Result<impl warp::Reply, warp
     ))
 }
 
-pub async fn validate_token(token: Option<String>) -> Result<impl warp::Reply, warp::Rejection> {
+pub async fn validate_token(token: Option<String>, state: AppState) -> Result<impl warp::Reply, warp::Rejection> {
+    // ban odwoluje sesje, wiec o banie mowi sam podpisany token
+    if let Some(Ok(token_data)) = token.as_ref().map(|token| decode_token(&state.config, token)) {
+        if let Some(ban) = get_active_ban(&state.db.get().await, token_data.claims.uid).await {
+            let r = BanInfo { ban_message: ban.ban_message, expires_on: ban.expires_on };
+            return Ok(warp::reply::with_status(
+                warp::reply::json(&r),
+                warp::http::StatusCode::UNAUTHORIZED,
+            ));
+        }
+    }
+
     match token {
-        Some(token) => match verify_token(token) {
+        Some(token) => match verify_token(&state, token).await {
             Ok(val) => {
                 let r = val.claims.uid;
                 Ok(warp::reply::with_status(
@@ -873,8 +1373,9 @@ pub async fn validate_token(token: Option<String>) -> Result<impl warp::Reply, w
 pub async fn post(
     token: String,
     request: PostCreateRequest,
+    state: AppState,
 ) -> Result<impl warp::Reply, warp::Rejection> {
-    let token = match verify_token(token) {
+    let token = match verify_token(&state, token).await {
         Ok(val) => val,
         Err(_) => {
             let r = "Wrong token";
@@ -886,12 +1387,10 @@ pub async fn post(
         }
     };
 
-    let connection = tokio_rusqlite::Connection::open("projekt-db")
-        .await
-        .unwrap();
+    let connection = state.db.get().await;
     let id = token.claims.uid;
 
-    if is_limited(&connection, token.claims.uid).await && token.claims.is_admin == 0 {
+    if is_limited(&connection, &state.config, token.claims.uid).await && !has_permission(&token.claims.role, Permission::BypassRateLimit) {
         let r = "Ur too fast";
         return Ok(warp::reply::with_status(
             warp::reply::json(&r),
@@ -899,12 +1398,11 @@ pub async fn post(
         ));
     }
 
-    if check_banned(&connection, token.claims.uid).await {
+    if let Some((r, status)) = write_restriction(&connection, token.claims.uid).await {
         info!("User {} not allowed to post", token.claims.uid);
-        let r = "User is banned";
         return Ok(warp::reply::with_status(
             warp::reply::json(&r),
-            warp::http::StatusCode::UNAUTHORIZED,
+            status,
         ));
     };
 
@@ -916,25 +1414,61 @@ pub async fn post(
         ));
     };
 
-    add_upload_db(&connection, token.claims.uid, 5).await;
-    let post_id = get_next_post_id(&connection).await.unwrap();
+    if let Some(poll) = request.poll.as_ref() {
+        if let Err(r) = validate_poll(poll) {
+            return Ok(warp::reply::with_status(
+                warp::reply::json(&r),
+                warp::http::StatusCode::BAD_REQUEST,
+            ));
+        }
+    }
+
+    let mut quoted_author_id = -1;
+    if let Some(quoted_post_id) = request.quoted_post_id {
+        quoted_author_id = get_user_from_post(&connection, quoted_post_id).await;
+        if quoted_author_id == -1 {
+            let r = "Quoted post not found";
+            return Ok(warp::reply::with_status(
+                warp::reply::json(&r),
+                warp::http::StatusCode::NOT_FOUND,
+            ));
+        }
+        if check_block(&connection, quoted_author_id, id).await {
+            let r = "User is blocked";
+            return Ok(warp::reply::with_status(
+                warp::reply::json(&r),
+                warp::http::StatusCode::FORBIDDEN,
+            ));
+        }
+    }
 
-    add_post_db(
+    add_upload_db(&connection, token.claims.uid, 5).await;
+    let (post_id, new_mentions) = add_post_db(
         &connection,
         Post {
-            post_id,
             user_id: id,
             date: -1,
             body: request.body,
-            likes: 0,
-            user_name: "".to_string(),
-            display_name: "".to_string(),
-            pfp_image: "".to_string(),
-            like_count: 0
+            quoted_post_id: request.quoted_post_id,
+            ..Default::default()
         },
         request.tags,
     )
     .await;
+    if let Some(poll) = request.poll {
+        add_poll_db(&connection, post_id, poll).await;
+    }
+
+    // posty ukaranych shadow nie ida na strumien, i tak nikt poza autorem ich nie zobaczy
+    if get_sanction(&connection, id).await != Sanction::Shadow {
+        state.events.publish(Event::NewPost { post_id, user_id: id });
+    }
+    for user_id in new_mentions {
+        notify(&state, &connection, user_id, "mention", id, Some(post_id), None).await;
+    }
+    if quoted_author_id != -1 {
+        notify(&state, &connection, quoted_author_id, "quote", id, Some(post_id), None).await;
+    }
 
     Ok(warp::reply::with_status(
         warp::reply::json(&post_id),
@@ -942,11 +1476,33 @@ pub async fn post(
     ))
 }
 
-pub async fn comment(
+const MIN_POLL_OPTIONS: usize = 2;
+const MAX_POLL_OPTIONS: usize = 10;
+const MAX_POLL_OPTION_LENGTH: usize = 128;
+
+fn validate_poll(poll: &PollCreateRequest) -> Result<(), &'static str> {
+    if poll.options.len() < MIN_POLL_OPTIONS || poll.options.len() > MAX_POLL_OPTIONS {
+        return Err("Invalid poll options");
+    }
+    if poll.options.iter().any(|option| option.trim().is_empty() || option.chars().count() > MAX_POLL_OPTION_LENGTH) {
+        return Err("Invalid poll options");
+    }
+    let timestamp = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64;
+    if poll.closes_on.is_some_and(|closes_on| closes_on <= timestamp) {
+        return Err("Invalid poll closing time");
+    }
+    Ok(())
+}
+
+pub async fn edit_post(
     token: String,
-    request: CommentCreateRequest,
+    request: PostEditRequest,
+    state: AppState,
 ) -> Result<impl warp::Reply, warp::Rejection> {
-    let token = match verify_token(token) {
+    let token = match verify_token(&state, token).await {
         Ok(val) => val,
         Err(_) => {
             let r = "Wrong token";
@@ -958,12 +1514,9 @@ pub async fn comment(
         }
     };
 
-    let connection = tokio_rusqlite::Connection::open("projekt-db")
-        .await
-        .unwrap();
-    let id = token.claims.uid;
+    let connection = state.db.get().await;
 
-    if is_limited(&connection, token.claims.uid).await && token.claims.is_admin == 0 {
+    if is_limited(&connection, &state.config, token.claims.uid).await && !has_permission(&token.claims.role, Permission::BypassRateLimit) {
         let r = "Ur too fast";
         return Ok(warp::reply::with_status(
             warp::reply::json(&r),
@@ -971,58 +1524,60 @@ pub async fn comment(
         ));
     }
 
-    if !check_post(&connection, request.post_id).await {
-        info!("Post {} not found", request.post_id);
-        let r = "Post not found";
+    if let Some((r, status)) = write_restriction(&connection, token.claims.uid).await {
+        info!("User {} not allowed to edit", token.claims.uid);
         return Ok(warp::reply::with_status(
             warp::reply::json(&r),
-            warp::http::StatusCode::NOT_FOUND,
+            status,
         ));
-    }
+    };
 
-    if check_banned(&connection, token.claims.uid).await {
-        info!("User {} not allowed to post", token.claims.uid);
-        let r = "User is banned";
+    if !check_post(&connection, request.post_id).await {
+        let r = "Post not found";
         return Ok(warp::reply::with_status(
             warp::reply::json(&r),
-            warp::http::StatusCode::UNAUTHORIZED,
+            warp::http::StatusCode::NOT_FOUND,
         ));
-    };
+    }
 
-    if !check_user_id(&connection, id).await {
-        let r = "User not found";
+    if token.claims.uid != get_user_from_post(&connection, request.post_id).await
+        && !has_permission(&token.claims.role, Permission::EditAnyPost)
+    {
+        let r = "User not authorized";
         return Ok(warp::reply::with_status(
             warp::reply::json(&r),
-            warp::http::StatusCode::NOT_FOUND,
+            warp::http::StatusCode::FORBIDDEN,
         ));
-    };
-
-    add_upload_db(&connection, token.claims.uid, 3).await;
-    let comment_id = get_next_comment_id(&connection, request.post_id).await.unwrap();
+    }
 
-    add_comment_db(
-        &connection,
-        request.post_id,
-        comment_id,
-        token.claims.uid,
-        request.body
-    )
-    .await;
+    add_upload_db(&connection, token.claims.uid, 5).await;
+    let author_id = get_user_from_post(&connection, request.post_id).await;
+    if author_id != token.claims.uid {
+        let payload = serde_json::json!({ "author_id": author_id, "body": request.body, "tags": request.tags });
+        add_audit_log_db(&connection, token.claims.uid, "edit_post", "post", request.post_id, payload).await;
+    }
+    let new_mentions = edit_post_db(&connection, request.post_id, request.body, request.tags).await;
+    for user_id in new_mentions {
+        notify(&state, &connection, user_id, "mention", author_id, Some(request.post_id), None).await;
+    }
 
+    let r = "Post edited";
     Ok(warp::reply::with_status(
-        warp::reply::json(&comment_id),
-        warp::http::StatusCode::CREATED,
+        warp::reply::json(&r),
+        warp::http::StatusCode::OK,
     ))
 }
 
-pub async fn react(
+pub async fn comment(
     token: String,
-    request: LikeRequest,
+    request: CommentCreateRequest,
+    state: AppState,
 ) -> Result<impl warp::Reply, warp::Rejection> {
-    let token = match verify_token(token) {
+    let token = match verify_token(&state, token).await {
         Ok(val) => val,
         Err(_) => {
             let r = "Wrong token";
+            info!("{}", r);
             return Ok(warp::reply::with_status(
                 warp::reply::json(&r),
                 warp::http::StatusCode::UNAUTHORIZED,
@@ -1030,11 +1585,10 @@ pub async fn react(
         }
     };
 
-    let connection = tokio_rusqlite::Connection::open("projekt-db")
-        .await
-        .unwrap();
+    let connection = state.db.get().await;
+    let id = token.claims.uid;
 
-    if is_limited(&connection, token.claims.uid).await && token.claims.is_admin == 0 {
+    if is_limited(&connection, &state.config, token.claims.uid).await && !has_permission(&token.claims.role, Permission::BypassRateLimit) {
         let r = "Ur too fast";
         return Ok(warp::reply::with_status(
             warp::reply::json(&r),
@@ -1042,49 +1596,111 @@ pub async fn react(
         ));
     }
 
-    if check_banned(&connection, token.claims.uid).await {
-        info!("User {} not allowed to react", token.claims.uid);
-        let r = "User is banned";
+    if !check_post(&connection, request.post_id).await {
+        info!("Post {} not found", request.post_id);
+        let r = "Post not found";
         return Ok(warp::reply::with_status(
             warp::reply::json(&r),
-            warp::http::StatusCode::UNAUTHORIZED,
+            warp::http::StatusCode::NOT_FOUND,
+        ));
+    }
+
+    if let Some((r, status)) = write_restriction(&connection, token.claims.uid).await {
+        info!("User {} not allowed to post", token.claims.uid);
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            status,
         ));
     };
 
-    if !check_post(&connection, request.post_id).await {
-        let r = "Post not found";
+    if !check_user_id(&connection, id).await {
+        let r = "User not found";
         return Ok(warp::reply::with_status(
             warp::reply::json(&r),
             warp::http::StatusCode::NOT_FOUND,
         ));
-    }
+    };
 
-    add_upload_db(&connection, token.claims.uid, 1).await;
-    let existed = add_like_db(&connection, token.claims.uid, request.post_id).await;
-
-    if existed {
-        let r = "Like already exists";
-        Ok(warp::reply::with_status(
-            warp::reply::json(&r),
-            warp::http::StatusCode::NOT_ACCEPTABLE,
-        ))
-    } else {
-        let r = "Like added";
-        Ok(warp::reply::with_status(
+    let post_author_id = get_user_from_post(&connection, request.post_id).await;
+    if check_block(&connection, post_author_id, id).await {
+        let r = "User is blocked";
+        return Ok(warp::reply::with_status(
             warp::reply::json(&r),
-            warp::http::StatusCode::OK,
-        ))
+            warp::http::StatusCode::FORBIDDEN,
+        ));
+    }
+
+    let mut parent_author_id = -1;
+    if let Some(parent_id) = request.parent_comment_id {
+        let (parent_post_id, parent_user_id) = get_comment_post_user(&connection, parent_id).await;
+        if parent_post_id == -1 {
+            let r = "Parent comment not found";
+            return Ok(warp::reply::with_status(
+                warp::reply::json(&r),
+                warp::http::StatusCode::NOT_FOUND,
+            ));
+        }
+        if parent_post_id != request.post_id {
+            let r = "Parent comment belongs to a different post";
+            return Ok(warp::reply::with_status(
+                warp::reply::json(&r),
+                warp::http::StatusCode::BAD_REQUEST,
+            ));
+        }
+        if check_block(&connection, parent_user_id, id).await {
+            let r = "User is blocked";
+            return Ok(warp::reply::with_status(
+                warp::reply::json(&r),
+                warp::http::StatusCode::FORBIDDEN,
+            ));
+        }
+        parent_author_id = parent_user_id;
+    }
+
+    add_upload_db(&connection, token.claims.uid, 3).await;
+    let (comment_id, new_mentions) = add_comment_db(
+        &connection,
+        request.post_id,
+        token.claims.uid,
+        request.body,
+        request.parent_comment_id
+    )
+    .await;
+
+    if get_sanction(&connection, id).await != Sanction::Shadow {
+        state.events.publish(Event::NewComment {
+            post_id: request.post_id,
+            comment_id,
+            user_id: id,
+            parent_comment_id: request.parent_comment_id,
+        });
+    }
+    if parent_author_id != -1 {
+        notify(&state, &connection, parent_author_id, "reply", id, Some(request.post_id), Some(comment_id)).await;
+    }
+    if parent_author_id != post_author_id {
+        notify(&state, &connection, post_author_id, "comment", id, Some(request.post_id), Some(comment_id)).await;
     }
+    for user_id in new_mentions {
+        notify(&state, &connection, user_id, "mention", id, Some(request.post_id), Some(comment_id)).await;
+    }
+
+    Ok(warp::reply::with_status(
+        warp::reply::json(&comment_id),
+        warp::http::StatusCode::CREATED,
+    ))
 }
 
-pub async fn unreact(
+pub async fn edit_comment(
     token: String,
-    request: UnlikeRequest,
+    request: CommentEditRequest,
+    state: AppState,
 ) -> Result<impl warp::Reply, warp::Rejection> {
-    let token = match verify_token(token) {
+    let token = match verify_token(&state, token).await {
         Ok(val) => val,
         Err(_) => {
             let r = "Wrong token";
+            info!("{}", r);
             return Ok(warp::reply::with_status(
                 warp::reply::json(&r),
                 warp::http::StatusCode::UNAUTHORIZED,
@@ -1092,11 +1708,9 @@ pub async fn unreact(
         }
     };
 
-    let connection = tokio_rusqlite::Connection::open("projekt-db")
-        .await
-        .unwrap();
+    let connection = state.db.get().await;
 
-    if is_limited(&connection, token.claims.uid).await && token.claims.is_admin == 0 {
+    if is_limited(&connection, &state.config, token.claims.uid).await && !has_permission(&token.claims.role, Permission::BypassRateLimit) {
         let r = "Ur too fast";
         return Ok(warp::reply::with_status(
             warp::reply::json(&r),
@@ -1104,217 +1718,55 @@ pub async fn unreact(
         ));
     }
 
-    if check_banned(&connection, token.claims.uid).await {
-        info!("User {} not allowed to react", token.claims.uid);
-        let r = "User is banned";
+    if let Some((r, status)) = write_restriction(&connection, token.claims.uid).await {
+        info!("User {} not allowed to edit", token.claims.uid);
         return Ok(warp::reply::with_status(
             warp::reply::json(&r),
-            warp::http::StatusCode::UNAUTHORIZED,
+            status,
         ));
     };
 
-    if !check_post(&connection, request.post_id).await {
-        let r = "Post not found";
+    let (_, author_id) = get_comment_post_user(&connection, request.comment_id).await;
+    if author_id == -1 {
+        let r = "Comment not found";
         return Ok(warp::reply::with_status(
             warp::reply::json(&r),
             warp::http::StatusCode::NOT_FOUND,
         ));
     }
 
-    add_upload_db(&connection, token.claims.uid, 1).await;
-    let existed = remove_like_db(&connection, token.claims.uid, request.post_id).await;
-
-    if existed {
-        let r = "Like doesn't exists";
-        Ok(warp::reply::with_status(
-            warp::reply::json(&r),
-            warp::http::StatusCode::NOT_ACCEPTABLE,
-        ))
-    } else {
-        let r = "Like removed";
-        Ok(warp::reply::with_status(
+    if token.claims.uid != author_id {
+        let r = "User not authorized";
+        return Ok(warp::reply::with_status(
             warp::reply::json(&r),
-            warp::http::StatusCode::OK,
-        ))
+            warp::http::StatusCode::FORBIDDEN,
+        ));
     }
-}
-
-#[derive(Debug)]
-struct IncorrectPassword;
-impl Reject for IncorrectPassword {}
-
-#[derive(Debug)]
-struct UserBanned;
-impl Reject for UserBanned {}
-
-#[derive(Debug)]
-struct UserNotFound;
-impl Reject for UserNotFound {}
 
-pub async fn login(request: LoginRequest) -> Result<impl warp::Reply, warp::Rejection> {
-    let connection = tokio_rusqlite::Connection::open("projekt-db")
-        .await
-        .unwrap();
-    let name = request.user_name;
-
-    match get_id_passwd_adm(&connection, name.clone()).await {
-        Ok((user_id, hash, is_admin)) => {
-            if check_banned(&connection, user_id).await {
-                info!("Can't log in user {}, reason - ban", user_id);
-                return Err(warp::reject::custom(UserBanned));
-            };
-
-            if verify_hash(request.passwd, hash) {
-                info!("User {} logged in", name);
-                let token = get_token(user_id, is_admin);
-                let mut cookie_params =
-                    "Path=/; HttpOnly; Secure; SameSite=None; Partitioned;".to_string();
-                if request.remember_password == true {
-                    cookie_params += "Max-Age=1209600;";
-                }
-                Ok(warp::reply::with_header(
-                    token.clone(),
-                    "set-cookie",
-                    format!("token={}; {}", token, cookie_params),
-                ))
-            } else {
-                info!("User {} failed to log in", name);
-                Err(warp::reject::custom(IncorrectPassword))
-            }
-        }
-        Err(_) => Err(warp::reject::custom(UserNotFound)),
+    add_upload_db(&connection, token.claims.uid, 3).await;
+    let (post_id, _) = get_comment_post_user(&connection, request.comment_id).await;
+    let new_mentions = edit_comment_db(&connection, request.comment_id, request.body).await;
+    for user_id in new_mentions {
+        notify(&state, &connection, user_id, "mention", author_id, Some(post_id), Some(request.comment_id)).await;
     }
-}
-
-pub async fn logout(token: String) -> Result<impl warp::Reply, warp::Rejection> {
-    match verify_token(token) {
-        Ok(_) => {}
-        Err(_) => {
-            return Err(warp::reject::custom(WrongToken));
-        }
-    };
 
-    let cookie_params =
-        "Path=/; HttpOnly; Secure; SameSite=None; Partitioned; Max-Age=0".to_string();
-    Ok(warp::reply::with_header(
-        "Logout",
-        "set-cookie",
-        format!("token=\"\"; {}", cookie_params),
+    let r = "Comment edited";
+    Ok(warp::reply::with_status(
+        warp::reply::json(&r),
+        warp::http::StatusCode::OK,
     ))
 }
 
-#[derive(Debug)]
-struct UserAlereadyExists;
-impl Reject for UserAlereadyExists {}
-
-#[derive(Debug)]
-struct WrongToken;
-impl Reject for WrongToken {}
-
-#[derive(Debug)]
-struct SpacesNotAllowed;
-impl Reject for SpacesNotAllowed {}
-
-#[derive(Debug)]
-struct EmptyNotAllowed;
-impl Reject for EmptyNotAllowed {}
-
-pub async fn signup(request: SignupRequest) -> Result<impl warp::Reply, warp::Rejection> {
-    let connection = tokio_rusqlite::Connection::open("projekt-db")
-        .await
-        .unwrap();
-
-    if request.user_name.contains(" ") {
-        return Err(warp::reject::custom(SpacesNotAllowed));
-    }
-
-    if request.user_name.is_empty() {
-        return Err(warp::reject::custom(EmptyNotAllowed));
-    }
-
-    if check_user_name(&connection, request.user_name.clone()).await {
-        Err(warp::reject::custom(UserAlereadyExists))
-    } else {
-        let mut cookie_params = "Path=/; HttpOnly; Secure; SameSite=None; Partitioned;".to_string();
-        if request.remember_password == true {
-            cookie_params += "Max-Age=1209600;";
-        }
-        let token = add_user_db(&connection, request).await;
-        Ok(warp::reply::with_header(
-            token.clone(),
-            "set-cookie",
-            format!("token={}; {}", token, cookie_params),
-        ))
-    }
-}
-
-pub async fn delete_user(
-    token: String,
-    _request: UserDeleteRequest,
-) -> Result<impl warp::Reply, warp::Rejection> {
-    let token = match verify_token(token) {
-        Ok(val) => val,
-        Err(_) => {
-            return Err(warp::reject::custom(WrongToken));
-        }
-    };
-    let connection = tokio_rusqlite::Connection::open("projekt-db")
-        .await
-        .unwrap();
-    let id = token.claims.uid;
-    if check_user_id(&connection, id).await {
-        let delete_query = "DELETE FROM users WHERE user_id = ?";
-        connection
-            .call(move |conn| {
-                let mut statement = conn.prepare(delete_query).unwrap();
-                statement.execute(params![id]).unwrap();
-                Ok(0)
-            })
-            .await
-            .unwrap();
-
-        purge_data(&connection, id).await;
-
-        info!("User {} deleted", id);
-        let r = "User deleted";
-        let res = warp::reply::with_status(r, warp::http::StatusCode::OK);
-        let res = warp::reply::with_header(res, "Access-Control-Allow-Origin", "*");
-        Ok(res)
-    } else {
-        Err(warp::reject::custom(UserNotFound))
-    }
-}
-
-pub async fn delete_post(token: String, _request: PostDeleteRequest,) -> Result<impl warp::Reply, warp::Rejection> {
-    info!("{}", token);
-    let token = match verify_token(token) {
-        Ok(val) => val,
-        Err(_) => {
-            return Err(warp::reject::custom(WrongToken));
-        }
-    };
-    let connection = tokio_rusqlite::Connection::open("projekt-db")
-        .await
-        .unwrap();
-    let id = token.claims.uid;
-    if check_user_id(&connection, id).await || token.claims.is_admin == 1 {
-        purge_data(&connection, id).await;
-        let r = "Post deleted";
-        let res = warp::reply::with_status(r, warp::http::StatusCode::OK);
-        Ok(res)
-    } else {
-        Err(warp::reject::custom(UserNotFound))
-    }
-}
-
-pub async fn upgrade_user(
+pub async fn delete_comment(
     token: String,
-    request: UserUpgradeRequest,
+    request: CommentDeleteRequest,
+    state: AppState,
 ) -> Result<impl warp::Reply, warp::Rejection> {
-    let token = match verify_token(token) {
+    let token = match verify_token(&state, token).await {
         Ok(val) => val,
         Err(_) => {
             let r = "Wrong token";
+            info!("{}", r);
             return Ok(warp::reply::with_status(
                 warp::reply::json(&r),
                 warp::http::StatusCode::UNAUTHORIZED,
@@ -1322,49 +1774,65 @@ pub async fn upgrade_user(
         }
     };
 
-    if token.claims.is_admin != 1 {
-        let r = "User is not admin";
+    let connection = state.db.get().await;
+
+    let (_, author_id) = get_comment_post_user(&connection, request.comment_id).await;
+    if author_id == -1 {
+        let r = "Comment not found";
         return Ok(warp::reply::with_status(
             warp::reply::json(&r),
-            warp::http::StatusCode::UNAUTHORIZED,
+            warp::http::StatusCode::NOT_FOUND,
         ));
     }
 
-    let connection = tokio_rusqlite::Connection::open("projekt-db")
-        .await
-        .unwrap();
-    let id = request.user_id;
-
-    if check_user_id(&connection, id).await {
-        let update_query = "UPDATE users SET is_admin=1 WHERE user_id = ?";
-        connection
-            .call(move |conn| {
-                let mut statement = conn.prepare(update_query).unwrap();
-                statement.execute(params![id]).unwrap();
-                Ok(0)
-            })
-            .await
-            .unwrap();
-
-        let r = "Upgrade succesful";
-        Ok(warp::reply::with_status(
+    if token.claims.uid != author_id && !has_permission(&token.claims.role, Permission::DeleteAnyPost) {
+        let r = "User not authorized";
+        return Ok(warp::reply::with_status(
             warp::reply::json(&r),
-            warp::http::StatusCode::OK,
-        ))
-    } else {
-        let r = "User not found";
-        Ok(warp::reply::with_status(
+            warp::http::StatusCode::FORBIDDEN,
+        ));
+    }
+
+    if token.claims.uid != author_id && !outranks(&connection, &token.claims.role, author_id).await {
+        let r = "User has equal or higher role";
+        return Ok(warp::reply::with_status(
             warp::reply::json(&r),
-            warp::http::StatusCode::NOT_FOUND,
-        ))
+            warp::http::StatusCode::FORBIDDEN,
+        ));
+    }
+
+    if token.claims.uid != author_id {
+        let payload = serde_json::json!({ "author_id": author_id });
+        add_audit_log_db(&connection, token.claims.uid, "delete_comment", "comment", request.comment_id, payload).await;
     }
+    delete_comment_db(&connection, request.comment_id).await;
+
+    let r = "Comment deleted";
+    Ok(warp::reply::with_status(
+        warp::reply::json(&r),
+        warp::http::StatusCode::OK,
+    ))
 }
 
-pub async fn ban_user(
+const REACTION_TARGETS: [&str; 2] = ["post", "comment"];
+
+// (post_id, autor) celu reakcji, (-1, -1) jesli cel nie istnieje
+async fn get_reaction_target(connection: &Connection, target_type: &str, target_id: i64) -> (i64, i64) {
+    match target_type {
+        "post" => match get_user_from_post(connection, target_id).await {
+            -1 => (-1, -1),
+            author_id => (target_id, author_id),
+        },
+        _ => get_comment_post_user(connection, target_id).await,
+    }
+}
+
+pub async fn react(
     token: String,
-    request: UserBanRequest,
+    request: ReactRequest,
+    state: AppState,
 ) -> Result<impl warp::Reply, warp::Rejection> {
-    let token = match verify_token(token) {
+    let token = match verify_token(&state, token).await {
         Ok(val) => val,
         Err(_) => {
             let r = "Wrong token";
@@ -1375,38 +1843,2402 @@ pub async fn ban_user(
         }
     };
 
-    if token.claims.is_admin != 1 {
-        let r = "User is not admin";
+    let connection = state.db.get().await;
+
+    if is_limited(&connection, &state.config, token.claims.uid).await && !has_permission(&token.claims.role, Permission::BypassRateLimit) {
+        let r = "Ur too fast";
         return Ok(warp::reply::with_status(
             warp::reply::json(&r),
-            warp::http::StatusCode::UNAUTHORIZED,
+            warp::http::StatusCode::FORBIDDEN,
+        ));
+    }
+
+    if let Some((r, status)) = write_restriction(&connection, token.claims.uid).await {
+        info!("User {} not allowed to react", token.claims.uid);
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            status,
+        ));
+    };
+
+    if !REACTION_TARGETS.contains(&request.target_type.as_str()) {
+        let r = "Invalid target type";
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::BAD_REQUEST,
+        ));
+    }
+
+    if !state.config.reaction_kinds.contains(&request.kind) {
+        let r = "Invalid reaction kind";
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::BAD_REQUEST,
+        ));
+    }
+
+    let (post_id, author_id) = get_reaction_target(&connection, &request.target_type, request.target_id).await;
+    if author_id == -1 {
+        let r = if request.target_type == "post" { "Post not found" } else { "Comment not found" };
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::NOT_FOUND,
+        ));
+    }
+
+    if check_block(&connection, author_id, token.claims.uid).await {
+        let r = "User is blocked";
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::FORBIDDEN,
+        ));
+    }
+
+    add_upload_db(&connection, token.claims.uid, 1).await;
+    let existed = add_reaction_db(&connection, token.claims.uid, request.target_type.clone(), request.target_id, request.kind).await;
+    if !existed {
+        let reactions = get_reaction_counts(&connection, request.target_type.clone(), request.target_id).await;
+        let comment_id = if request.target_type == "comment" { Some(request.target_id) } else { None };
+        state.events.publish(Event::ReactionCount {
+            target_type: request.target_type,
+            target_id: request.target_id,
+            reactions,
+        });
+        notify(&state, &connection, author_id, "reaction", token.claims.uid, Some(post_id), comment_id).await;
+    }
+
+    if existed {
+        let r = "Reaction already exists";
+        Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::NOT_ACCEPTABLE,
+        ))
+    } else {
+        let r = "Reaction added";
+        Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::OK,
+        ))
+    }
+}
+
+pub async fn unreact(
+    token: String,
+    request: UnreactRequest,
+    state: AppState,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let token = match verify_token(&state, token).await {
+        Ok(val) => val,
+        Err(_) => {
+            let r = "Wrong token";
+            return Ok(warp::reply::with_status(
+                warp::reply::json(&r),
+                warp::http::StatusCode::UNAUTHORIZED,
+            ));
+        }
+    };
+
+    let connection = state.db.get().await;
+
+    if is_limited(&connection, &state.config, token.claims.uid).await && !has_permission(&token.claims.role, Permission::BypassRateLimit) {
+        let r = "Ur too fast";
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::FORBIDDEN,
+        ));
+    }
+
+    if let Some((r, status)) = write_restriction(&connection, token.claims.uid).await {
+        info!("User {} not allowed to react", token.claims.uid);
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            status,
+        ));
+    };
+
+    if !REACTION_TARGETS.contains(&request.target_type.as_str()) {
+        let r = "Invalid target type";
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::BAD_REQUEST,
+        ));
+    }
+
+    // rodzaju nie sprawdzamy z configiem, zeby dalo sie zdjac reakcje usunieta z listy
+    let (_, author_id) = get_reaction_target(&connection, &request.target_type, request.target_id).await;
+    if author_id == -1 {
+        let r = if request.target_type == "post" { "Post not found" } else { "Comment not found" };
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::NOT_FOUND,
+        ));
+    }
+
+    add_upload_db(&connection, token.claims.uid, 1).await;
+    let existed = remove_reaction_db(&connection, token.claims.uid, request.target_type.clone(), request.target_id, request.kind).await;
+    if !existed {
+        let reactions = get_reaction_counts(&connection, request.target_type.clone(), request.target_id).await;
+        state.events.publish(Event::ReactionCount {
+            target_type: request.target_type,
+            target_id: request.target_id,
+            reactions,
+        });
+    }
+
+    if existed {
+        let r = "Reaction doesn't exists";
+        Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::NOT_ACCEPTABLE,
+        ))
+    } else {
+        let r = "Reaction removed";
+        Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::OK,
+        ))
+    }
+}
+
+pub async fn repost(
+    token: String,
+    request: RepostRequest,
+    state: AppState,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let token = match verify_token(&state, token).await {
+        Ok(val) => val,
+        Err(_) => {
+            let r = "Wrong token";
+            return Ok(warp::reply::with_status(
+                warp::reply::json(&r),
+                warp::http::StatusCode::UNAUTHORIZED,
+            ));
+        }
+    };
+
+    let connection = state.db.get().await;
+
+    if is_limited(&connection, &state.config, token.claims.uid).await && !has_permission(&token.claims.role, Permission::BypassRateLimit) {
+        let r = "Ur too fast";
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::FORBIDDEN,
+        ));
+    }
+
+    if let Some((r, status)) = write_restriction(&connection, token.claims.uid).await {
+        info!("User {} not allowed to repost", token.claims.uid);
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            status,
+        ));
+    };
+
+    let author_id = get_user_from_post(&connection, request.post_id).await;
+    if author_id == -1 {
+        let r = "Post not found";
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::NOT_FOUND,
+        ));
+    }
+
+    if author_id == token.claims.uid {
+        let r = "Can't repost your own post";
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::BAD_REQUEST,
+        ));
+    }
+
+    if check_block(&connection, author_id, token.claims.uid).await {
+        let r = "User is blocked";
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::FORBIDDEN,
+        ));
+    }
+
+    add_upload_db(&connection, token.claims.uid, 1).await;
+    let existed = add_repost_db(&connection, token.claims.uid, request.post_id).await;
+    if !existed {
+        notify(&state, &connection, author_id, "repost", token.claims.uid, Some(request.post_id), None).await;
+    }
+
+    if existed {
+        let r = "Repost already exists";
+        Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::NOT_ACCEPTABLE,
+        ))
+    } else {
+        let r = "Post reposted";
+        Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::OK,
+        ))
+    }
+}
+
+pub async fn unrepost(
+    token: String,
+    request: UnrepostRequest,
+    state: AppState,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let token = match verify_token(&state, token).await {
+        Ok(val) => val,
+        Err(_) => {
+            let r = "Wrong token";
+            return Ok(warp::reply::with_status(
+                warp::reply::json(&r),
+                warp::http::StatusCode::UNAUTHORIZED,
+            ));
+        }
+    };
+
+    let connection = state.db.get().await;
+
+    if is_limited(&connection, &state.config, token.claims.uid).await && !has_permission(&token.claims.role, Permission::BypassRateLimit) {
+        let r = "Ur too fast";
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::FORBIDDEN,
+        ));
+    }
+
+    if let Some((r, status)) = write_restriction(&connection, token.claims.uid).await {
+        info!("User {} not allowed to repost", token.claims.uid);
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            status,
+        ));
+    };
+
+    let author_id = get_user_from_post(&connection, request.post_id).await;
+    if author_id == -1 {
+        let r = "Post not found";
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::NOT_FOUND,
+        ));
+    }
+
+    add_upload_db(&connection, token.claims.uid, 1).await;
+    let existed = remove_repost_db(&connection, token.claims.uid, request.post_id).await;
+
+    if existed {
+        let r = "Repost doesn't exist";
+        Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::NOT_ACCEPTABLE,
+        ))
+    } else {
+        let r = "Repost removed";
+        Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::OK,
+        ))
+    }
+}
+
+pub async fn get_reposts(
+    post_id: i64,
+    limit: i64,
+    offset: i64,
+    token: Option<String>,
+    state: AppState,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let viewer_id = viewer_id(&state, token).await;
+    let connection = state.db.get().await;
+
+    if !check_post(&connection, post_id).await {
+        let r = "Post not found";
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::NOT_FOUND,
+        ));
+    }
+
+    let query = format!(
+        "
+        SELECT users.user_id, users.user_name, users.display_name, users.description, images.image_file,
+        (SELECT COUNT(*) FROM follows WHERE follows.followed_id=users.user_id) AS follower_count,
+        (SELECT COUNT(*) FROM follows WHERE follows.follower_id=users.user_id) AS following_count
+        FROM reposts
+        JOIN users ON users.user_id=reposts.user_id
+        LEFT JOIN images ON images.image_id=users.pfp_id
+        WHERE reposts.post_id = ?
+        AND {}
+        AND {}
+        ORDER BY reposts.date DESC
+        LIMIT ? OFFSET ?",
+        sanctioned_authors_filter("users.user_id", viewer_id),
+        hidden_authors_filter("users.user_id", viewer_id)
+    );
+
+    let profile_list = connection
+        .call(move |conn| {
+            let mut statement = conn.prepare(&query).unwrap();
+            let mut rows = statement.query(params![post_id, limit, offset]).unwrap();
+            let mut profile_vec: Vec<Profile> = Vec::new();
+            while let Ok(Some(row)) = rows.next() {
+                profile_vec.push(profile_from_row(row));
+            }
+            Ok(profile_vec)
+        })
+        .await
+        .unwrap();
+
+    let profiles = ProfileList { profile_list };
+    Ok(warp::reply::with_status(
+        warp::reply::json(&profiles),
+        warp::http::StatusCode::OK,
+    ))
+}
+
+// jeden glos na ankiete, przy multiple_choice moze obejmowac kilka opcji
+pub async fn vote_poll(
+    token: String,
+    request: PollVoteRequest,
+    state: AppState,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let token = match verify_token(&state, token).await {
+        Ok(val) => val,
+        Err(_) => {
+            let r = "Wrong token";
+            return Ok(warp::reply::with_status(
+                warp::reply::json(&r),
+                warp::http::StatusCode::UNAUTHORIZED,
+            ));
+        }
+    };
+
+    let connection = state.db.get().await;
+
+    if is_limited(&connection, &state.config, token.claims.uid).await && !has_permission(&token.claims.role, Permission::BypassRateLimit) {
+        let r = "Ur too fast";
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::FORBIDDEN,
+        ));
+    }
+
+    if let Some((r, status)) = write_restriction(&connection, token.claims.uid).await {
+        info!("User {} not allowed to vote", token.claims.uid);
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            status,
+        ));
+    };
+
+    let author_id = get_user_from_post(&connection, request.post_id).await;
+    if author_id == -1 {
+        let r = "Post not found";
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::NOT_FOUND,
+        ));
+    }
+
+    let poll = match get_poll(&connection, request.post_id, token.claims.uid).await {
+        Some(val) => val,
+        None => {
+            let r = "Poll not found";
+            return Ok(warp::reply::with_status(
+                warp::reply::json(&r),
+                warp::http::StatusCode::NOT_FOUND,
+            ));
+        }
+    };
+
+    if check_block(&connection, author_id, token.claims.uid).await {
+        let r = "User is blocked";
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::FORBIDDEN,
+        ));
+    }
+
+    if poll.is_closed {
+        let r = "Poll is closed";
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::FORBIDDEN,
+        ));
+    }
+
+    let mut option_ids = request.option_ids;
+    option_ids.sort_unstable();
+    option_ids.dedup();
+    let valid_options = !option_ids.is_empty()
+        && (poll.multiple_choice || option_ids.len() == 1)
+        && option_ids.iter().all(|id| poll.options.iter().any(|option| option.option_id == *id));
+    if !valid_options {
+        let r = "Invalid poll options";
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::BAD_REQUEST,
+        ));
+    }
+
+    add_upload_db(&connection, token.claims.uid, 1).await;
+    let existed = add_poll_vote_db(&connection, request.post_id, token.claims.uid, option_ids).await;
+
+    if existed {
+        let r = "Already voted";
+        Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::NOT_ACCEPTABLE,
+        ))
+    } else {
+        let r = "Vote added";
+        Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::OK,
+        ))
+    }
+}
+
+pub async fn follow(
+    token: String,
+    request: FollowRequest,
+    state: AppState,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let token = match verify_token(&state, token).await {
+        Ok(val) => val,
+        Err(_) => {
+            let r = "Wrong token";
+            return Ok(warp::reply::with_status(
+                warp::reply::json(&r),
+                warp::http::StatusCode::UNAUTHORIZED,
+            ));
+        }
+    };
+
+    let connection = state.db.get().await;
+
+    if is_limited(&connection, &state.config, token.claims.uid).await && !has_permission(&token.claims.role, Permission::BypassRateLimit) {
+        let r = "Ur too fast";
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::FORBIDDEN,
+        ));
+    }
+
+    if let Some((r, status)) = write_restriction(&connection, token.claims.uid).await {
+        info!("User {} not allowed to follow", token.claims.uid);
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            status,
+        ));
+    };
+
+    if !check_user_id(&connection, request.user_id).await {
+        let r = "User not found";
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::NOT_FOUND,
+        ));
+    }
+
+    if request.user_id == token.claims.uid {
+        let r = "Can't follow yourself";
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::BAD_REQUEST,
+        ));
+    }
+
+    if check_block(&connection, request.user_id, token.claims.uid).await {
+        let r = "User is blocked";
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::FORBIDDEN,
+        ));
+    }
+
+    add_upload_db(&connection, token.claims.uid, 1).await;
+    let existed = add_follow_db(&connection, token.claims.uid, request.user_id).await;
+    if !existed {
+        notify(&state, &connection, request.user_id, "follow", token.claims.uid, None, None).await;
+    }
+
+    if existed {
+        let r = "Follow already exists";
+        Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::NOT_ACCEPTABLE,
+        ))
+    } else {
+        let r = "Follow added";
+        Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::OK,
+        ))
+    }
+}
+
+pub async fn block(
+    token: String,
+    request: BlockRequest,
+    state: AppState,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let token = match verify_token(&state, token).await {
+        Ok(val) => val,
+        Err(_) => {
+            let r = "Wrong token";
+            return Ok(warp::reply::with_status(
+                warp::reply::json(&r),
+                warp::http::StatusCode::UNAUTHORIZED,
+            ));
+        }
+    };
+
+    let connection = state.db.get().await;
+
+    if is_limited(&connection, &state.config, token.claims.uid).await && !has_permission(&token.claims.role, Permission::BypassRateLimit) {
+        let r = "Ur too fast";
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::FORBIDDEN,
+        ));
+    }
+
+    if !check_user_id(&connection, request.user_id).await {
+        let r = "User not found";
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::NOT_FOUND,
+        ));
+    }
+
+    if request.user_id == token.claims.uid {
+        let r = "Can't block yourself";
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::BAD_REQUEST,
+        ));
+    }
+
+    add_upload_db(&connection, token.claims.uid, 1).await;
+    let existed = add_block_db(&connection, token.claims.uid, request.user_id).await;
+
+    if existed {
+        let r = "Block already exists";
+        Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::NOT_ACCEPTABLE,
+        ))
+    } else {
+        let r = "User blocked";
+        Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::OK,
+        ))
+    }
+}
+
+pub async fn unblock(
+    token: String,
+    request: BlockRequest,
+    state: AppState,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let token = match verify_token(&state, token).await {
+        Ok(val) => val,
+        Err(_) => {
+            let r = "Wrong token";
+            return Ok(warp::reply::with_status(
+                warp::reply::json(&r),
+                warp::http::StatusCode::UNAUTHORIZED,
+            ));
+        }
+    };
+
+    let connection = state.db.get().await;
+
+    if is_limited(&connection, &state.config, token.claims.uid).await && !has_permission(&token.claims.role, Permission::BypassRateLimit) {
+        let r = "Ur too fast";
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::FORBIDDEN,
+        ));
+    }
+
+    if !check_user_id(&connection, request.user_id).await {
+        let r = "User not found";
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::NOT_FOUND,
+        ));
+    }
+
+    if request.user_id == token.claims.uid {
+        let r = "Can't unblock yourself";
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::BAD_REQUEST,
+        ));
+    }
+
+    add_upload_db(&connection, token.claims.uid, 1).await;
+    let existed = remove_block_db(&connection, token.claims.uid, request.user_id).await;
+
+    if existed {
+        let r = "Block doesn't exist";
+        Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::NOT_ACCEPTABLE,
+        ))
+    } else {
+        let r = "User unblocked";
+        Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::OK,
+        ))
+    }
+}
+
+pub async fn mute(
+    token: String,
+    request: MuteRequest,
+    state: AppState,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let token = match verify_token(&state, token).await {
+        Ok(val) => val,
+        Err(_) => {
+            let r = "Wrong token";
+            return Ok(warp::reply::with_status(
+                warp::reply::json(&r),
+                warp::http::StatusCode::UNAUTHORIZED,
+            ));
+        }
+    };
+
+    let connection = state.db.get().await;
+
+    if is_limited(&connection, &state.config, token.claims.uid).await && !has_permission(&token.claims.role, Permission::BypassRateLimit) {
+        let r = "Ur too fast";
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::FORBIDDEN,
+        ));
+    }
+
+    if !check_user_id(&connection, request.user_id).await {
+        let r = "User not found";
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::NOT_FOUND,
+        ));
+    }
+
+    if request.user_id == token.claims.uid {
+        let r = "Can't mute yourself";
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::BAD_REQUEST,
+        ));
+    }
+
+    add_upload_db(&connection, token.claims.uid, 1).await;
+    let existed = add_mute_db(&connection, token.claims.uid, request.user_id).await;
+
+    if existed {
+        let r = "Mute already exists";
+        Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::NOT_ACCEPTABLE,
+        ))
+    } else {
+        let r = "User muted";
+        Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::OK,
+        ))
+    }
+}
+
+pub async fn unmute(
+    token: String,
+    request: MuteRequest,
+    state: AppState,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let token = match verify_token(&state, token).await {
+        Ok(val) => val,
+        Err(_) => {
+            let r = "Wrong token";
+            return Ok(warp::reply::with_status(
+                warp::reply::json(&r),
+                warp::http::StatusCode::UNAUTHORIZED,
+            ));
+        }
+    };
+
+    let connection = state.db.get().await;
+
+    if is_limited(&connection, &state.config, token.claims.uid).await && !has_permission(&token.claims.role, Permission::BypassRateLimit) {
+        let r = "Ur too fast";
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::FORBIDDEN,
+        ));
+    }
+
+    if !check_user_id(&connection, request.user_id).await {
+        let r = "User not found";
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::NOT_FOUND,
+        ));
+    }
+
+    if request.user_id == token.claims.uid {
+        let r = "Can't unmute yourself";
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::BAD_REQUEST,
+        ));
+    }
+
+    add_upload_db(&connection, token.claims.uid, 1).await;
+    let existed = remove_mute_db(&connection, token.claims.uid, request.user_id).await;
+
+    if existed {
+        let r = "Mute doesn't exist";
+        Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::NOT_ACCEPTABLE,
+        ))
+    } else {
+        let r = "User unmuted";
+        Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::OK,
+        ))
+    }
+}
+
+pub async fn get_blocked(token: String, state: AppState) -> Result<impl warp::Reply, warp::Rejection> {
+    let token = match verify_token(&state, token).await {
+        Ok(val) => val,
+        Err(_) => {
+            let r = "Wrong token";
+            return Ok(warp::reply::with_status(
+                warp::reply::json(&r),
+                warp::http::StatusCode::UNAUTHORIZED,
+            ));
+        }
+    };
+
+    let connection = state.db.get().await;
+    let query = "
+        SELECT users.user_id, users.user_name, users.display_name, users.description, images.image_file,
+        (SELECT COUNT(*) FROM follows WHERE follows.followed_id=users.user_id) AS follower_count,
+        (SELECT COUNT(*) FROM follows WHERE follows.follower_id=users.user_id) AS following_count
+        FROM blocks
+        JOIN users ON users.user_id=blocks.blocked_id
+        LEFT JOIN images ON images.image_id=users.pfp_id
+        WHERE blocks.blocker_id = ?
+        ORDER BY blocks.date DESC
+    ";
+    let user_id = token.claims.uid;
+
+    let profile_list = connection
+        .call(move |conn| {
+            let mut statement = conn.prepare(query).unwrap();
+            let mut rows = statement.query(params![user_id]).unwrap();
+            let mut profile_vec: Vec<Profile> = Vec::new();
+            while let Ok(Some(row)) = rows.next() {
+                profile_vec.push(profile_from_row(row));
+            }
+            Ok(profile_vec)
+        })
+        .await
+        .unwrap();
+
+    let profiles = ProfileList { profile_list };
+    Ok(warp::reply::with_status(
+        warp::reply::json(&profiles),
+        warp::http::StatusCode::OK,
+    ))
+}
+
+pub async fn get_muted(token: String, state: AppState) -> Result<impl warp::Reply, warp::Rejection> {
+    let token = match verify_token(&state, token).await {
+        Ok(val) => val,
+        Err(_) => {
+            let r = "Wrong token";
+            return Ok(warp::reply::with_status(
+                warp::reply::json(&r),
+                warp::http::StatusCode::UNAUTHORIZED,
+            ));
+        }
+    };
+
+    let connection = state.db.get().await;
+    let query = "
+        SELECT users.user_id, users.user_name, users.display_name, users.description, images.image_file,
+        (SELECT COUNT(*) FROM follows WHERE follows.followed_id=users.user_id) AS follower_count,
+        (SELECT COUNT(*) FROM follows WHERE follows.follower_id=users.user_id) AS following_count
+        FROM mutes
+        JOIN users ON users.user_id=mutes.muted_id
+        LEFT JOIN images ON images.image_id=users.pfp_id
+        WHERE mutes.muter_id = ?
+        ORDER BY mutes.date DESC
+    ";
+    let user_id = token.claims.uid;
+
+    let profile_list = connection
+        .call(move |conn| {
+            let mut statement = conn.prepare(query).unwrap();
+            let mut rows = statement.query(params![user_id]).unwrap();
+            let mut profile_vec: Vec<Profile> = Vec::new();
+            while let Ok(Some(row)) = rows.next() {
+                profile_vec.push(profile_from_row(row));
+            }
+            Ok(profile_vec)
+        })
+        .await
+        .unwrap();
+
+    let profiles = ProfileList { profile_list };
+    Ok(warp::reply::with_status(
+        warp::reply::json(&profiles),
+        warp::http::StatusCode::OK,
+    ))
+}
+
+const MAX_COLLECTION_NAME_LENGTH: usize = 64;
+
+pub async fn create_collection(
+    token: String,
+    request: CollectionCreateRequest,
+    state: AppState,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let token = match verify_token(&state, token).await {
+        Ok(val) => val,
+        Err(_) => {
+            let r = "Wrong token";
+            return Ok(warp::reply::with_status(
+                warp::reply::json(&r),
+                warp::http::StatusCode::UNAUTHORIZED,
+            ));
+        }
+    };
+
+    let connection = state.db.get().await;
+
+    if is_limited(&connection, &state.config, token.claims.uid).await && !has_permission(&token.claims.role, Permission::BypassRateLimit) {
+        let r = "Ur too fast";
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::FORBIDDEN,
+        ));
+    }
+
+    let name = request.name.trim().to_string();
+    if name.is_empty() || name.chars().count() > MAX_COLLECTION_NAME_LENGTH {
+        let r = "Invalid collection name";
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::BAD_REQUEST,
+        ));
+    }
+
+    if check_collection_name(&connection, token.claims.uid, name.clone()).await {
+        let r = "Collection already exists";
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::NOT_ACCEPTABLE,
+        ));
+    }
+
+    add_upload_db(&connection, token.claims.uid, 1).await;
+    let collection_id = add_collection_db(&connection, token.claims.uid, name).await;
+    Ok(warp::reply::with_status(
+        warp::reply::json(&collection_id),
+        warp::http::StatusCode::CREATED,
+    ))
+}
+
+pub async fn delete_collection(
+    token: String,
+    request: CollectionDeleteRequest,
+    state: AppState,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let token = match verify_token(&state, token).await {
+        Ok(val) => val,
+        Err(_) => {
+            let r = "Wrong token";
+            return Ok(warp::reply::with_status(
+                warp::reply::json(&r),
+                warp::http::StatusCode::UNAUTHORIZED,
+            ));
+        }
+    };
+
+    let connection = state.db.get().await;
+
+    // cudze kolekcje udajemy ze nie istnieja
+    if get_collection_owner(&connection, request.collection_id).await != token.claims.uid {
+        let r = "Collection not found";
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::NOT_FOUND,
+        ));
+    }
+
+    delete_collection_db(&connection, request.collection_id).await;
+    let r = "Collection deleted";
+    Ok(warp::reply::with_status(
+        warp::reply::json(&r),
+        warp::http::StatusCode::OK,
+    ))
+}
+
+pub async fn add_to_collection(
+    token: String,
+    request: CollectionAddRequest,
+    state: AppState,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let token = match verify_token(&state, token).await {
+        Ok(val) => val,
+        Err(_) => {
+            let r = "Wrong token";
+            return Ok(warp::reply::with_status(
+                warp::reply::json(&r),
+                warp::http::StatusCode::UNAUTHORIZED,
+            ));
+        }
+    };
+
+    let connection = state.db.get().await;
+
+    if is_limited(&connection, &state.config, token.claims.uid).await && !has_permission(&token.claims.role, Permission::BypassRateLimit) {
+        let r = "Ur too fast";
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::FORBIDDEN,
+        ));
+    }
+
+    if get_collection_owner(&connection, request.collection_id).await != token.claims.uid {
+        let r = "Collection not found";
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::NOT_FOUND,
+        ));
+    }
+
+    if !check_post(&connection, request.post_id).await {
+        let r = "Post not found";
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::NOT_FOUND,
+        ));
+    }
+
+    add_upload_db(&connection, token.claims.uid, 1).await;
+    let existed = add_collection_post_db(&connection, request.collection_id, request.post_id).await;
+
+    if existed {
+        let r = "Post already in collection";
+        Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::NOT_ACCEPTABLE,
+        ))
+    } else {
+        let r = "Post saved";
+        Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::OK,
+        ))
+    }
+}
+
+pub async fn remove_from_collection(
+    token: String,
+    request: CollectionRemoveRequest,
+    state: AppState,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let token = match verify_token(&state, token).await {
+        Ok(val) => val,
+        Err(_) => {
+            let r = "Wrong token";
+            return Ok(warp::reply::with_status(
+                warp::reply::json(&r),
+                warp::http::StatusCode::UNAUTHORIZED,
+            ));
+        }
+    };
+
+    let connection = state.db.get().await;
+
+    if get_collection_owner(&connection, request.collection_id).await != token.claims.uid {
+        let r = "Collection not found";
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::NOT_FOUND,
+        ));
+    }
+
+    let existed = remove_collection_post_db(&connection, request.collection_id, request.post_id).await;
+
+    if existed {
+        let r = "Post not in collection";
+        Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::NOT_ACCEPTABLE,
+        ))
+    } else {
+        let r = "Post removed from collection";
+        Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::OK,
+        ))
+    }
+}
+
+pub async fn get_collections(token: String, state: AppState) -> Result<impl warp::Reply, warp::Rejection> {
+    let token = match verify_token(&state, token).await {
+        Ok(val) => val,
+        Err(_) => {
+            let r = "Wrong token";
+            return Ok(warp::reply::with_status(
+                warp::reply::json(&r),
+                warp::http::StatusCode::UNAUTHORIZED,
+            ));
+        }
+    };
+
+    let connection = state.db.get().await;
+    let query = "
+        SELECT collections.*,
+        (SELECT COUNT(*) FROM collections_posts WHERE collections_posts.collection_id=collections.collection_id) AS post_count
+        FROM collections
+        WHERE collections.user_id = ?
+        ORDER BY collections.date DESC
+    ";
+    let user_id = token.claims.uid;
+
+    let collection_list = connection
+        .call(move |conn| {
+            let mut statement = conn.prepare(query).unwrap();
+            let mut rows = statement.query(params![user_id]).unwrap();
+            let mut collection_vec: Vec<Collection> = Vec::new();
+            while let Ok(Some(row)) = rows.next() {
+                collection_vec.push(Collection {
+                    collection_id: row.get("collection_id").unwrap(),
+                    name: row.get("name").unwrap(),
+                    date: row.get("date").unwrap(),
+                    post_count: row.get("post_count").unwrap(),
+                });
+            }
+            Ok(collection_vec)
+        })
+        .await
+        .unwrap();
+
+    let collections = CollectionList { collection_list };
+    Ok(warp::reply::with_status(
+        warp::reply::json(&collections),
+        warp::http::StatusCode::OK,
+    ))
+}
+
+// ostatnio zapisane pierwsze
+pub async fn get_collection_posts(
+    collection_id: i64,
+    limit: i64,
+    offset: i64,
+    token: String,
+    state: AppState,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let token = match verify_token(&state, token).await {
+        Ok(val) => val,
+        Err(_) => {
+            let r = "Wrong token";
+            return Ok(warp::reply::with_status(
+                warp::reply::json(&r),
+                warp::http::StatusCode::UNAUTHORIZED,
+            ));
+        }
+    };
+
+    let connection = state.db.get().await;
+
+    if get_collection_owner(&connection, collection_id).await != token.claims.uid {
+        let r = "Collection not found";
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::NOT_FOUND,
+        ));
+    }
+
+    let query = format!(
+        "
+        SELECT posts.*, users.user_name, users.display_name, images.image_file,
+        COUNT(comments.comment_id) AS comment_count
+        FROM collections_posts
+        JOIN posts ON posts.post_id=collections_posts.post_id
+        JOIN users ON posts.user_id = users.user_id
+        LEFT JOIN images ON users.pfp_id=images.image_id
+        LEFT JOIN comments ON comments.post_id=posts.post_id
+        WHERE collections_posts.collection_id = ?
+        AND {}
+        AND {}
+        GROUP BY posts.post_id
+        ORDER BY collections_posts.date DESC
+        LIMIT ? OFFSET ?",
+        sanctioned_authors_filter("posts.user_id", token.claims.uid),
+        hidden_authors_filter("posts.user_id", token.claims.uid)
+    );
+
+    let mut post_list = connection
+        .call(move |conn| {
+            let mut statement = conn.prepare(&query).unwrap();
+            let mut rows = statement.query(params![collection_id, limit, offset]).unwrap();
+            let mut post_vec: Vec<Post> = Vec::new();
+            while let Ok(Some(row)) = rows.next() {
+                post_vec.push(post_from_row(row));
+            }
+            Ok(post_vec)
+        })
+        .await
+        .unwrap();
+    attach_quoted_posts(&connection, &mut post_list, token.claims.uid).await;
+    attach_polls(&connection, &mut post_list, token.claims.uid).await;
+
+    let post = PostList { post_list };
+    Ok(warp::reply::with_status(
+        warp::reply::json(&post),
+        warp::http::StatusCode::OK,
+    ))
+}
+
+pub async fn unfollow(
+    token: String,
+    request: UnfollowRequest,
+    state: AppState,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let token = match verify_token(&state, token).await {
+        Ok(val) => val,
+        Err(_) => {
+            let r = "Wrong token";
+            return Ok(warp::reply::with_status(
+                warp::reply::json(&r),
+                warp::http::StatusCode::UNAUTHORIZED,
+            ));
+        }
+    };
+
+    let connection = state.db.get().await;
+
+    if is_limited(&connection, &state.config, token.claims.uid).await && !has_permission(&token.claims.role, Permission::BypassRateLimit) {
+        let r = "Ur too fast";
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::FORBIDDEN,
+        ));
+    }
+
+    if let Some((r, status)) = write_restriction(&connection, token.claims.uid).await {
+        info!("User {} not allowed to unfollow", token.claims.uid);
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            status,
+        ));
+    };
+
+    if !check_user_id(&connection, request.user_id).await {
+        let r = "User not found";
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::NOT_FOUND,
+        ));
+    }
+
+    add_upload_db(&connection, token.claims.uid, 1).await;
+    let existed = remove_follow_db(&connection, token.claims.uid, request.user_id).await;
+
+    if existed {
+        let r = "Follow doesn't exist";
+        Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::NOT_ACCEPTABLE,
+        ))
+    } else {
+        let r = "Follow removed";
+        Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::OK,
+        ))
+    }
+}
+
+#[derive(Debug)]
+struct IncorrectPassword;
+impl Reject for IncorrectPassword {}
+
+#[derive(Debug)]
+struct UserNotFound;
+impl Reject for UserNotFound {}
+
+// access token idzie w ciasteczku "token", refresh token tylko do /api/post/refresh
+fn session_reply(
+    config: &Config,
+    access_token: String,
+    refresh_token: String,
+    remember: bool,
+) -> warp::http::Response<String> {
+    let cookie_params = "HttpOnly; Secure; SameSite=None; Partitioned;";
+    let mut refresh_cookie_params = format!("Path=/api/post/refresh; {}", cookie_params);
+    if remember {
+        refresh_cookie_params += &format!(" Max-Age={};", config.refresh_lifetime(remember));
+    }
+    warp::http::Response::builder()
+        .header(
+            "set-cookie",
+            format!(
+                "token={}; Path=/; {} Max-Age={};",
+                access_token, cookie_params, config.access_token_lifetime
+            ),
+        )
+        .header(
+            "set-cookie",
+            format!("refresh_token={}; {}", refresh_token, refresh_cookie_params),
+        )
+        .body(access_token)
+        .unwrap()
+}
+
+pub async fn login(request: LoginRequest, state: AppState) -> Result<impl warp::Reply, warp::Rejection> {
+    let connection = state.db.get().await;
+    let name = request.user_name;
+
+    match get_id_passwd_role(&connection, name.clone()).await {
+        Ok((user_id, hash, role)) => {
+            if verify_hash(request.passwd, hash) {
+                // powod bana dopiero po hasle, inaczej kazdy znajacy nazwe moglby go odczytac
+                if let Some(ban) = get_active_ban(&connection, user_id).await {
+                    info!("Can't log in user {}, reason - ban", user_id);
+                    let r = BanInfo { ban_message: ban.ban_message, expires_on: ban.expires_on };
+                    return Ok(warp::reply::with_status(
+                        warp::reply::json(&r),
+                        warp::http::StatusCode::UNAUTHORIZED,
+                    ).into_response());
+                };
+
+                info!("User {} logged in", name);
+                let (session_id, refresh_token) =
+                    add_session_db(&connection, &state.config, user_id, request.remember_password).await;
+                let token = get_token(&state.config, user_id, role, session_id);
+                Ok(session_reply(&state.config, token, refresh_token, request.remember_password).into_response())
+            } else {
+                info!("User {} failed to log in", name);
+                Err(warp::reject::custom(IncorrectPassword))
+            }
+        }
+        Err(_) => Err(warp::reject::custom(UserNotFound)),
+    }
+}
+
+// dziala tez po wygasnieciu tokenu: sesje bierzemy z przeterminowanego tokenu albo z refresh tokenu,
+// ciasteczka czyscimy zawsze
+pub async fn logout(
+    token: Option<String>,
+    refresh_token: Option<String>,
+    state: AppState,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let connection = state.db.get().await;
+
+    if let Some(token) = token {
+        if let Ok(token) = decode_expired_token(&state.config, &token) {
+            revoke_session_db(&connection, token.claims.jti, token.claims.uid).await;
+        }
+    }
+    if let Some(refresh_token) = refresh_token {
+        revoke_session_by_refresh_db(&connection, refresh_token).await;
+    }
+
+    let cookie_params = "HttpOnly; Secure; SameSite=None; Partitioned; Max-Age=0";
+    Ok(warp::http::Response::builder()
+        .header("set-cookie", format!("token=\"\"; Path=/; {}", cookie_params))
+        .header(
+            "set-cookie",
+            format!("refresh_token=\"\"; Path=/api/post/refresh; {}", cookie_params),
+        )
+        .body("Logout")
+        .unwrap())
+}
+
+pub async fn refresh(refresh_token: String, state: AppState) -> Result<impl warp::Reply, warp::Rejection> {
+    let connection = state.db.get().await;
+
+    match rotate_refresh_token_db(&connection, &state.config, refresh_token).await {
+        Ok((user_id, role, session_id, new_refresh_token, remember)) => {
+            let token = get_token(&state.config, user_id, role, session_id);
+            Ok(session_reply(&state.config, token, new_refresh_token, remember))
+        }
+        Err(e) => {
+            if e == "Refresh token reused" {
+                warn!("Refresh token reused, session revoked");
+            } else {
+                info!("Refresh failed: {}", e);
+            }
+            Err(warp::reject::custom(WrongToken))
+        }
+    }
+}
+
+pub async fn get_mentions(limit: i64, offset: i64, token: String, state: AppState) -> Result<impl warp::Reply, warp::Rejection> {
+    let token = match verify_token(&state, token).await {
+        Ok(val) => val,
+        Err(_) => {
+            let r = "Wrong token";
+            return Ok(warp::reply::with_status(
+                warp::reply::json(&r),
+                warp::http::StatusCode::UNAUTHORIZED,
+            ));
+        }
+    };
+
+    let connection = state.db.get().await;
+    let user_id = token.claims.uid;
+    // tresc idzie prosto do wspomnianego, wiec te same filtry co na listach postow
+    let query = format!("
+        SELECT mentions.mention_id, mentions.post_id, mentions.comment_id, mentions.author_id,
+        users.user_name, users.display_name, images.image_file,
+        COALESCE(comments.body, posts.body) AS body, mentions.date
+        FROM mentions
+        JOIN posts ON posts.post_id=mentions.post_id
+        LEFT JOIN comments ON comments.comment_id=mentions.comment_id
+        JOIN users ON users.user_id=mentions.author_id
+        LEFT JOIN images ON users.pfp_id=images.image_id
+        WHERE mentions.user_id = ?
+        AND {}
+        AND {}
+        ORDER BY mentions.date DESC, mentions.mention_id DESC
+        LIMIT ? OFFSET ?
+    ", sanctioned_authors_filter("mentions.author_id", user_id), hidden_authors_filter("mentions.author_id", user_id));
+
+    let mention_list = connection
+        .call(move |conn| {
+            let mut statement = conn.prepare(&query).unwrap();
+            let mut rows = statement.query(params![user_id, limit, offset]).unwrap();
+            let mut mention_vec: Vec<Mention> = Vec::new();
+            while let Ok(Some(row)) = rows.next() {
+                mention_vec.push(Mention {
+                    mention_id: row.get("mention_id").unwrap(),
+                    post_id: row.get("post_id").unwrap(),
+                    comment_id: row.get("comment_id").unwrap(),
+                    author_id: row.get("author_id").unwrap(),
+                    user_name: row.get("user_name").unwrap(),
+                    display_name: row.get("display_name").unwrap(),
+                    pfp_image: row.get("image_file").unwrap_or_default(),
+                    body: row.get("body").unwrap(),
+                    date: row.get("date").unwrap(),
+                });
+            }
+            Ok(mention_vec)
+        })
+        .await
+        .unwrap();
+
+    let mentions = MentionList { mention_list };
+    Ok(warp::reply::with_status(
+        warp::reply::json(&mentions),
+        warp::http::StatusCode::OK,
+    ))
+}
+
+// -1 gdy nie ma tokena albo jest niewazny, wtedy nic nie jest ukrywane
+async fn viewer_id(state: &AppState, token: Option<String>) -> i64 {
+    match token {
+        Some(token) => match verify_token(state, token).await {
+            Ok(val) => val.claims.uid,
+            Err(_) => -1,
+        },
+        None => -1,
+    }
+}
+
+// None jesli uzytkownik moze pisac, inaczej komunikat i status do zwrocenia
+async fn write_restriction(connection: &Connection, user_id: i64) -> Option<(&'static str, warp::http::StatusCode)> {
+    match get_sanction(connection, user_id).await {
+        Sanction::Ban => Some(("User is banned", warp::http::StatusCode::UNAUTHORIZED)),
+        Sanction::ReadOnly => Some(("User is read-only", warp::http::StatusCode::FORBIDDEN)),
+        _ => None,
+    }
+}
+
+// zapisuje powiadomienie i wypycha je na strumien adresata,
+// akcje ukaranych shadow nie powiadamiaja nikogo
+async fn notify(
+    state: &AppState,
+    connection: &Connection,
+    user_id: i64,
+    kind: &'static str,
+    actor_id: i64,
+    post_id: Option<i64>,
+    comment_id: Option<i64>
+) {
+    if get_sanction(connection, actor_id).await == Sanction::Shadow {
+        return;
+    }
+    if let Some(notification_id) = add_notification_db(connection, user_id, kind, actor_id, post_id, comment_id).await {
+        let unread_count = count_unread_notifications(connection, user_id).await;
+        state.events.publish(Event::Notification {
+            user_id,
+            notification_id,
+            kind: kind.to_string(),
+            unread_count,
+        });
+    }
+}
+
+pub async fn get_events(token: String, state: AppState) -> Result<warp::reply::Response, warp::Rejection> {
+    let token = match verify_token(&state, token).await {
+        Ok(val) => val,
+        Err(_) => {
+            let r = "Wrong token";
+            return Ok(warp::reply::with_status(
+                warp::reply::json(&r),
+                warp::http::StatusCode::UNAUTHORIZED,
+            ).into_response());
+        }
+    };
+
+    info!("User {} subscribed to events", token.claims.uid);
+    let db = state.db.clone();
+    let session_id = token.claims.jti.clone();
+    let user_id = token.claims.uid;
+    let stream = state.events.subscribe(user_id, move || {
+        let db = db.clone();
+        let session_id = session_id.clone();
+        async move { check_session(&db.get().await, session_id, user_id).await }
+    });
+    Ok(warp::sse::reply(warp::sse::keep_alive().stream(stream)).into_response())
+}
+
+const MAX_CONVERSATION_MEMBERS: usize = 10;
+
+pub async fn start_conversation(
+    token: String,
+    request: ConversationCreateRequest,
+    state: AppState,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let token = match verify_token(&state, token).await {
+        Ok(val) => val,
+        Err(_) => {
+            let r = "Wrong token";
+            return Ok(warp::reply::with_status(
+                warp::reply::json(&r),
+                warp::http::StatusCode::UNAUTHORIZED,
+            ));
+        }
+    };
+
+    let connection = state.db.get().await;
+    let id = token.claims.uid;
+
+    if is_limited(&connection, &state.config, id).await && !has_permission(&token.claims.role, Permission::BypassRateLimit) {
+        let r = "Ur too fast";
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::FORBIDDEN,
+        ));
+    }
+
+    if let Some((r, status)) = write_restriction(&connection, id).await {
+        info!("User {} not allowed to start conversations", id);
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            status,
+        ));
+    };
+
+    let mut members: Vec<i64> = Vec::new();
+    for user_id in request.user_ids.iter() {
+        if *user_id != id && !members.contains(user_id) {
+            members.push(*user_id);
+        }
+    }
+
+    if members.is_empty() {
+        let r = "No other users in conversation";
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::BAD_REQUEST,
+        ));
+    }
+
+    if members.len() + 1 > MAX_CONVERSATION_MEMBERS {
+        let r = "Too many users in conversation";
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::BAD_REQUEST,
+        ));
+    }
+
+    if request.title.as_ref().is_some_and(|title| title.chars().count() > 64) {
+        let r = "Title too long";
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::BAD_REQUEST,
+        ));
+    }
+
+    for user_id in members.iter() {
+        if !check_user_id(&connection, *user_id).await {
+            let r = "User not found";
+            return Ok(warp::reply::with_status(
+                warp::reply::json(&r),
+                warp::http::StatusCode::NOT_FOUND,
+            ));
+        }
+    }
+
+    if check_blocked_by_any(&connection, members.clone(), id).await {
+        let r = "User is blocked";
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::FORBIDDEN,
+        ));
+    }
+
+    // jeden odbiorca bez tytulu to rozmowa 1:1, taka moze byc tylko jedna
+    let is_group = members.len() > 1 || request.title.is_some();
+    if !is_group {
+        if let Some(conversation_id) = get_direct_conversation(&connection, id, members[0]).await {
+            return Ok(warp::reply::with_status(
+                warp::reply::json(&conversation_id),
+                warp::http::StatusCode::OK,
+            ));
+        }
+    }
+
+    add_upload_db(&connection, id, 3).await;
+    let conversation_id = add_conversation_db(&connection, id, members, is_group, request.title).await;
+
+    Ok(warp::reply::with_status(
+        warp::reply::json(&conversation_id),
+        warp::http::StatusCode::CREATED,
+    ))
+}
+
+pub async fn send_message(
+    token: String,
+    request: MessageSendRequest,
+    state: AppState,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let token = match verify_token(&state, token).await {
+        Ok(val) => val,
+        Err(_) => {
+            let r = "Wrong token";
+            return Ok(warp::reply::with_status(
+                warp::reply::json(&r),
+                warp::http::StatusCode::UNAUTHORIZED,
+            ));
+        }
+    };
+
+    let connection = state.db.get().await;
+    let id = token.claims.uid;
+
+    if is_limited(&connection, &state.config, id).await && !has_permission(&token.claims.role, Permission::BypassRateLimit) {
+        let r = "Ur too fast";
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::FORBIDDEN,
+        ));
+    }
+
+    if let Some((r, status)) = write_restriction(&connection, id).await {
+        info!("User {} not allowed to send messages", id);
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            status,
+        ));
+    };
+
+    if request.body.trim().is_empty() {
+        let r = "Empty message not allowed";
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::NOT_ACCEPTABLE,
+        ));
+    }
+
+    // obcy nie dowiaduja sie nawet ze rozmowa istnieje
+    if !check_conversation_member(&connection, request.conversation_id, id).await {
+        let r = "Conversation not found";
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::NOT_FOUND,
+        ));
+    }
+
+    let member_ids = get_conversation_members(&connection, request.conversation_id).await;
+    if check_blocked_by_any(&connection, member_ids.clone(), id).await {
+        let r = "User is blocked";
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::FORBIDDEN,
+        ));
+    }
+
+    add_upload_db(&connection, id, 1).await;
+    let message_id = add_message_db(&connection, request.conversation_id, id, request.body).await;
+
+    // wiadomosci ukaranych shadow widzi tylko autor, wiec nie ida na strumien
+    if get_sanction(&connection, id).await != Sanction::Shadow {
+        state.events.publish(Event::NewMessage {
+            conversation_id: request.conversation_id,
+            message_id,
+            user_id: id,
+            member_ids,
+        });
+    }
+
+    Ok(warp::reply::with_status(
+        warp::reply::json(&message_id),
+        warp::http::StatusCode::CREATED,
+    ))
+}
+
+pub async fn get_conversations(limit: i64, offset: i64, token: String, state: AppState) -> Result<impl warp::Reply, warp::Rejection> {
+    let token = match verify_token(&state, token).await {
+        Ok(val) => val,
+        Err(_) => {
+            let r = "Wrong token";
+            return Ok(warp::reply::with_status(
+                warp::reply::json(&r),
+                warp::http::StatusCode::UNAUTHORIZED,
+            ));
+        }
+    };
+
+    let connection = state.db.get().await;
+    let user_id = token.claims.uid;
+    // rozmowa zalozona przez ukaranego shadow jest widoczna dla reszty dopiero gdy ma widoczna wiadomosc
+    let query = format!("
+        SELECT conversations.conversation_id, conversations.is_group, conversations.title,
+        conversation_members.last_read_message_id,
+        COALESCE((SELECT MAX(date) FROM messages WHERE messages.conversation_id=conversations.conversation_id
+            AND {}),
+            conversations.created_on) AS last_activity
+        FROM conversations
+        JOIN conversation_members ON conversation_members.conversation_id=conversations.conversation_id
+        WHERE conversation_members.user_id = ?
+        AND ({} OR EXISTS (SELECT 1 FROM messages WHERE messages.conversation_id=conversations.conversation_id
+            AND {}))
+        ORDER BY last_activity DESC, conversations.conversation_id DESC
+        LIMIT ? OFFSET ?
+    ", sanctioned_authors_filter("messages.user_id", user_id),
+    sanctioned_authors_filter("conversations.created_by", user_id),
+    sanctioned_authors_filter("messages.user_id", user_id));
+    let members_query = "
+        SELECT users.user_id, users.user_name, users.display_name
+        FROM conversation_members
+        JOIN users ON users.user_id=conversation_members.user_id
+        WHERE conversation_members.conversation_id = ?
+        ORDER BY conversation_members.joined_on, users.user_id
+    ";
+    let last_message_query = format!("
+        SELECT messages.*, users.user_name, users.display_name, images.image_file
+        FROM messages
+        JOIN users ON users.user_id=messages.user_id
+        LEFT JOIN images ON users.pfp_id=images.image_id
+        WHERE messages.conversation_id = ?
+        AND {}
+        ORDER BY messages.message_id DESC
+        LIMIT 1
+    ", sanctioned_authors_filter("messages.user_id", user_id));
+    let unread_query = format!("
+        SELECT COUNT(*) FROM messages
+        WHERE conversation_id = ? AND message_id > ? AND user_id != ?
+        AND {}
+    ", sanctioned_authors_filter("messages.user_id", user_id));
+
+    let conversation_list = connection
+        .call(move |conn| {
+            let mut statement = conn.prepare(&query).unwrap();
+            let mut rows = statement.query(params![user_id, limit, offset]).unwrap();
+            let mut conversation_vec: Vec<Conversation> = Vec::new();
+            while let Ok(Some(row)) = rows.next() {
+                let conversation_id: i64 = row.get(0).unwrap();
+                let last_read: i64 = row.get(3).unwrap();
+
+                let mut members_statement = conn.prepare(members_query).unwrap();
+                let mut member_rows = members_statement.query(params![conversation_id]).unwrap();
+                let mut members: Vec<ConversationMember> = Vec::new();
+                while let Ok(Some(member)) = member_rows.next() {
+                    members.push(ConversationMember {
+                        user_id: member.get(0).unwrap(),
+                        user_name: member.get(1).unwrap(),
+                        display_name: member.get(2).unwrap(),
+                    });
+                }
+
+                let mut last_statement = conn.prepare(&last_message_query).unwrap();
+                let mut last_rows = last_statement.query(params![conversation_id]).unwrap();
+                let last_message = last_rows.next().unwrap().map(message_from_row);
+
+                let mut unread_statement = conn.prepare(&unread_query).unwrap();
+                let mut unread_rows = unread_statement.query(params![conversation_id, last_read, user_id]).unwrap();
+                let unread_count: i64 = unread_rows.next().unwrap().unwrap().get(0).unwrap();
+
+                conversation_vec.push(Conversation {
+                    conversation_id,
+                    is_group: row.get::<_, i64>(1).unwrap() == 1,
+                    title: row.get(2).unwrap(),
+                    members,
+                    last_message,
+                    unread_count,
+                });
+            }
+            Ok(conversation_vec)
+        })
+        .await
+        .unwrap();
+
+    let conversations = ConversationList { conversation_list };
+    Ok(warp::reply::with_status(
+        warp::reply::json(&conversations),
+        warp::http::StatusCode::OK,
+    ))
+}
+
+// {before} = 0 to najnowsze wiadomosci, potem message_id najstarszej z poprzedniej strony
+pub async fn get_messages(
+    conversation_id: i64,
+    limit: i64,
+    before: i64,
+    token: String,
+    state: AppState
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let token = match verify_token(&state, token).await {
+        Ok(val) => val,
+        Err(_) => {
+            let r = "Wrong token";
+            return Ok(warp::reply::with_status(
+                warp::reply::json(&r),
+                warp::http::StatusCode::UNAUTHORIZED,
+            ));
+        }
+    };
+
+    let connection = state.db.get().await;
+    let user_id = token.claims.uid;
+
+    if !check_conversation_member(&connection, conversation_id, user_id).await {
+        let r = "Conversation not found";
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::NOT_FOUND,
+        ));
+    }
+
+    // wiadomosci ukaranych shadow widzi tylko ich autor
+    let query = format!("
+        SELECT messages.*, users.user_name, users.display_name, images.image_file
+        FROM messages
+        JOIN users ON users.user_id=messages.user_id
+        LEFT JOIN images ON users.pfp_id=images.image_id
+        WHERE messages.conversation_id = ?
+        AND (? = 0 OR messages.message_id < ?)
+        AND {}
+        ORDER BY messages.message_id DESC
+        LIMIT ?
+    ", sanctioned_authors_filter("messages.user_id", user_id));
+    let message_list = connection
+        .call(move |conn| {
+            let mut statement = conn.prepare(&query).unwrap();
+            let mut rows = statement.query(params![conversation_id, before, before, limit]).unwrap();
+            let mut message_vec: Vec<Message> = Vec::new();
+            while let Ok(Some(row)) = rows.next() {
+                message_vec.push(message_from_row(row));
+            }
+            Ok(message_vec)
+        })
+        .await
+        .unwrap();
+
+    if let Some(newest) = message_list.first() {
+        mark_conversation_read_db(&connection, conversation_id, user_id, newest.message_id).await;
+    }
+
+    let messages = MessageList { message_list };
+    Ok(warp::reply::with_status(
+        warp::reply::json(&messages),
+        warp::http::StatusCode::OK,
+    ))
+}
+
+pub async fn get_notifications(limit: i64, offset: i64, token: String, state: AppState) -> Result<impl warp::Reply, warp::Rejection> {
+    let token = match verify_token(&state, token).await {
+        Ok(val) => val,
+        Err(_) => {
+            let r = "Wrong token";
+            return Ok(warp::reply::with_status(
+                warp::reply::json(&r),
+                warp::http::StatusCode::UNAUTHORIZED,
+            ));
+        }
+    };
+
+    let connection = state.db.get().await;
+    // actor to ostatnia osoba ktora dolaczyla do zbiorczego powiadomienia
+    let query = "
+        SELECT notifications.*, actors.actor_id, actors.actor_count,
+        users.user_name, users.display_name, images.image_file
+        FROM notifications
+        JOIN (
+            SELECT notification_id, actor_id, MAX(rowid), COUNT(*) AS actor_count
+            FROM notification_actors
+            GROUP BY notification_id
+        ) AS actors ON actors.notification_id=notifications.notification_id
+        JOIN users ON users.user_id=actors.actor_id
+        LEFT JOIN images ON users.pfp_id=images.image_id
+        WHERE notifications.user_id = ?
+        ORDER BY notifications.date DESC, notifications.notification_id DESC
+        LIMIT ? OFFSET ?
+    ";
+    let user_id = token.claims.uid;
+
+    let notification_list = connection
+        .call(move |conn| {
+            let mut statement = conn.prepare(query).unwrap();
+            let mut rows = statement.query(params![user_id, limit, offset]).unwrap();
+            let mut notification_vec: Vec<Notification> = Vec::new();
+            while let Ok(Some(row)) = rows.next() {
+                notification_vec.push(Notification {
+                    notification_id: row.get("notification_id").unwrap(),
+                    kind: row.get("kind").unwrap(),
+                    post_id: row.get("post_id").unwrap(),
+                    comment_id: row.get("comment_id").unwrap(),
+                    date: row.get("date").unwrap(),
+                    is_read: row.get::<_, i64>("is_read").unwrap() == 1,
+                    actor_count: row.get("actor_count").unwrap(),
+                    actor_id: row.get("actor_id").unwrap(),
+                    user_name: row.get("user_name").unwrap(),
+                    display_name: row.get("display_name").unwrap(),
+                    pfp_image: row.get("image_file").unwrap_or_default(),
+                });
+            }
+            Ok(notification_vec)
+        })
+        .await
+        .unwrap();
+
+    let unread_count = count_unread_notifications(&connection, user_id).await;
+    let notifications = NotificationList { notification_list, unread_count };
+    Ok(warp::reply::with_status(
+        warp::reply::json(&notifications),
+        warp::http::StatusCode::OK,
+    ))
+}
+
+pub async fn get_unread_notification_count(token: String, state: AppState) -> Result<impl warp::Reply, warp::Rejection> {
+    let token = match verify_token(&state, token).await {
+        Ok(val) => val,
+        Err(_) => {
+            let r = "Wrong token";
+            return Ok(warp::reply::with_status(
+                warp::reply::json(&r),
+                warp::http::StatusCode::UNAUTHORIZED,
+            ));
+        }
+    };
+
+    let connection = state.db.get().await;
+    let unread_count = count_unread_notifications(&connection, token.claims.uid).await;
+    Ok(warp::reply::with_status(
+        warp::reply::json(&unread_count),
+        warp::http::StatusCode::OK,
+    ))
+}
+
+pub async fn read_notifications(
+    token: String,
+    request: NotificationReadRequest,
+    state: AppState,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let token = match verify_token(&state, token).await {
+        Ok(val) => val,
+        Err(_) => {
+            let r = "Wrong token";
+            return Ok(warp::reply::with_status(
+                warp::reply::json(&r),
+                warp::http::StatusCode::UNAUTHORIZED,
+            ));
+        }
+    };
+
+    let connection = state.db.get().await;
+    let changed = read_notifications_db(&connection, token.claims.uid, request.notification_ids).await;
+    Ok(warp::reply::with_status(
+        warp::reply::json(&changed),
+        warp::http::StatusCode::OK,
+    ))
+}
+
+pub async fn read_all_notifications(token: String, state: AppState) -> Result<impl warp::Reply, warp::Rejection> {
+    let token = match verify_token(&state, token).await {
+        Ok(val) => val,
+        Err(_) => {
+            let r = "Wrong token";
+            return Ok(warp::reply::with_status(
+                warp::reply::json(&r),
+                warp::http::StatusCode::UNAUTHORIZED,
+            ));
+        }
+    };
+
+    let connection = state.db.get().await;
+    let changed = read_all_notifications_db(&connection, token.claims.uid).await;
+    Ok(warp::reply::with_status(
+        warp::reply::json(&changed),
+        warp::http::StatusCode::OK,
+    ))
+}
+
+pub async fn get_sessions(token: String, state: AppState) -> Result<impl warp::Reply, warp::Rejection> {
+    let token = match verify_token(&state, token).await {
+        Ok(val) => val,
+        Err(_) => {
+            let r = "Wrong token";
+            return Ok(warp::reply::with_status(
+                warp::reply::json(&r),
+                warp::http::StatusCode::UNAUTHORIZED,
+            ));
+        }
+    };
+
+    let connection = state.db.get().await;
+    let timestamp = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64;
+    let query = "
+        SELECT session_id, created_on, expires_on
+        FROM sessions
+        WHERE user_id = ? AND is_active = 1 AND expires_on > ?
+        ORDER BY created_on DESC
+    ";
+    let user_id = token.claims.uid;
+    let current_session = token.claims.jti;
+
+    let session_list = connection
+        .call(move |conn| {
+            let mut statement = conn.prepare(query).unwrap();
+            let mut rows = statement.query(params![user_id, timestamp]).unwrap();
+            let mut session_vec: Vec<Session> = Vec::new();
+            while let Ok(Some(row)) = rows.next() {
+                let session_id: String = row.get(0).unwrap();
+                session_vec.push(Session {
+                    is_current: session_id == current_session,
+                    session_id,
+                    created_on: row.get(1).unwrap(),
+                    expires_on: row.get(2).unwrap(),
+                });
+            }
+            Ok(session_vec)
+        })
+        .await
+        .unwrap();
+
+    let sessions = SessionList { session_list };
+    Ok(warp::reply::with_status(
+        warp::reply::json(&sessions),
+        warp::http::StatusCode::OK,
+    ))
+}
+
+pub async fn revoke_session(
+    token: String,
+    request: SessionRevokeRequest,
+    state: AppState,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let token = match verify_token(&state, token).await {
+        Ok(val) => val,
+        Err(_) => {
+            let r = "Wrong token";
+            return Ok(warp::reply::with_status(
+                warp::reply::json(&r),
+                warp::http::StatusCode::UNAUTHORIZED,
+            ));
+        }
+    };
+
+    let connection = state.db.get().await;
+
+    if revoke_session_db(&connection, request.session_id, token.claims.uid).await {
+        info!("User {} revoked a session", token.claims.uid);
+        let r = "Session revoked";
+        Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::OK,
+        ))
+    } else {
+        let r = "Session not found";
+        Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::NOT_FOUND,
+        ))
+    }
+}
+
+#[derive(Debug)]
+struct UserAlereadyExists;
+impl Reject for UserAlereadyExists {}
+
+#[derive(Debug)]
+struct WrongToken;
+impl Reject for WrongToken {}
+
+#[derive(Debug)]
+struct MissingPermission;
+impl Reject for MissingPermission {}
+
+#[derive(Debug)]
+struct SpacesNotAllowed;
+impl Reject for SpacesNotAllowed {}
+
+#[derive(Debug)]
+struct EmptyNotAllowed;
+impl Reject for EmptyNotAllowed {}
+
+pub async fn signup(request: SignupRequest, state: AppState) -> Result<impl warp::Reply, warp::Rejection> {
+    let connection = state.db.get().await;
+
+    if request.user_name.contains(" ") {
+        return Err(warp::reject::custom(SpacesNotAllowed));
+    }
+
+    if request.user_name.is_empty() {
+        return Err(warp::reject::custom(EmptyNotAllowed));
+    }
+
+    if check_user_name(&connection, request.user_name.clone()).await {
+        Err(warp::reject::custom(UserAlereadyExists))
+    } else {
+        let remember = request.remember_password;
+        let user_id = add_user_db(&connection, request).await;
+        let (session_id, refresh_token) =
+            add_session_db(&connection, &state.config, user_id, remember).await;
+        let token = get_token(&state.config, user_id, "user".to_string(), session_id);
+        Ok(session_reply(&state.config, token, refresh_token, remember))
+    }
+}
+
+pub async fn delete_user(
+    token: String,
+    _request: UserDeleteRequest,
+    state: AppState,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let token = match verify_token(&state, token).await {
+        Ok(val) => val,
+        Err(_) => {
+            return Err(warp::reject::custom(WrongToken));
+        }
+    };
+    let connection = state.db.get().await;
+    let id = token.claims.uid;
+    if check_user_id(&connection, id).await {
+        let delete_query = "DELETE FROM users WHERE user_id = ?";
+        connection
+            .call(move |conn| {
+                let mut statement = conn.prepare(delete_query).unwrap();
+                statement.execute(params![id]).unwrap();
+                Ok(0)
+            })
+            .await
+            .unwrap();
+
+        purge_data(&connection, id).await;
+
+        info!("User {} deleted", id);
+        let r = "User deleted";
+        let res = warp::reply::with_status(r, warp::http::StatusCode::OK);
+        let res = warp::reply::with_header(res, "Access-Control-Allow-Origin", "*");
+        Ok(res)
+    } else {
+        Err(warp::reject::custom(UserNotFound))
+    }
+}
+
+pub async fn delete_post(
+    token: String,
+    request: PostDeleteRequest,
+    state: AppState,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let token = match verify_token(&state, token).await {
+        Ok(val) => val,
+        Err(_) => {
+            return Err(warp::reject::custom(WrongToken));
+        }
+    };
+    let connection = state.db.get().await;
+
+    if !check_post(&connection, request.post_id).await {
+        let r = "Post not found";
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::NOT_FOUND,
+        ));
+    }
+
+    let author_id = get_user_from_post(&connection, request.post_id).await;
+    if token.claims.uid != author_id && !has_permission(&token.claims.role, Permission::DeleteAnyPost) {
+        info!("User {} not allowed to delete post {}", token.claims.uid, request.post_id);
+        let r = "User not authorized";
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::FORBIDDEN,
+        ));
+    }
+
+    if token.claims.uid != author_id && !outranks(&connection, &token.claims.role, author_id).await {
+        info!("User {} not allowed to delete post {}", token.claims.uid, request.post_id);
+        let r = "User has equal or higher role";
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::FORBIDDEN,
+        ));
+    }
+
+    if token.claims.uid != author_id {
+        let payload = serde_json::json!({ "author_id": author_id });
+        add_audit_log_db(&connection, token.claims.uid, "delete_post", "post", request.post_id, payload).await;
+    }
+    delete_post_db(&connection, request.post_id).await;
+
+    let r = "Post deleted";
+    Ok(warp::reply::with_status(
+        warp::reply::json(&r),
+        warp::http::StatusCode::OK,
+    ))
+}
+
+pub async fn upgrade_user(
+    token: TokenData<Claims>,
+    request: UserUpgradeRequest,
+    state: AppState,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let connection = state.db.get().await;
+    let id = request.user_id;
+
+    if let Some(old_role) = get_user_role(&connection, id).await {
+        if old_role != "admin" {
+            apply_role_change(&connection, token.claims.uid, id, old_role, "admin".to_string()).await;
+        }
+
+        let r = "Upgrade succesful";
+        Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::OK,
+        ))
+    } else {
+        let r = "User not found";
+        Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::NOT_FOUND,
+        ))
+    }
+}
+
+// kary, ich zmiany i usuwanie cudzych tresci tylko wobec nizszej roli,
+// nieistniejacego usera obsluguje wywolujacy
+async fn outranks(connection: &Connection, actor_role: &str, user_id: i64) -> bool {
+    match get_user_role(connection, user_id).await {
+        Some(role) => role_rank(actor_role) > role_rank(&role),
+        None => true,
+    }
+}
+
+// degradacja odwoluje sesje, bo stary token dalej nosilby stara role
+async fn apply_role_change(connection: &Connection, actor_id: i64, user_id: i64, old_role: String, new_role: String) {
+    set_user_role_db(connection, user_id, new_role.clone()).await;
+    if role_rank(&new_role) < role_rank(&old_role) {
+        revoke_user_sessions_db(connection, user_id).await;
+    }
+    let payload = serde_json::json!({ "old_role": old_role, "new_role": new_role });
+    add_audit_log_db(connection, actor_id, "change_role", "user", user_id, payload).await;
+}
+
+pub async fn grant_role(
+    token: TokenData<Claims>,
+    request: RoleGrantRequest,
+    state: AppState,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let connection = state.db.get().await;
+
+    if !is_role(&request.role) {
+        let r = "Invalid role";
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::BAD_REQUEST,
+        ));
+    }
+
+    if request.user_id == token.claims.uid {
+        let r = "Can't change your own role";
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::BAD_REQUEST,
+        ));
+    }
+
+    let old_role = match get_user_role(&connection, request.user_id).await {
+        Some(val) => val,
+        None => {
+            let r = "User not found";
+            return Ok(warp::reply::with_status(
+                warp::reply::json(&r),
+                warp::http::StatusCode::NOT_FOUND,
+            ));
+        }
+    };
+
+    if old_role == request.role {
+        let r = "User already has this role";
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::NOT_ACCEPTABLE,
+        ));
+    }
+
+    apply_role_change(&connection, token.claims.uid, request.user_id, old_role, request.role).await;
+
+    let r = "Role granted";
+    Ok(warp::reply::with_status(
+        warp::reply::json(&r),
+        warp::http::StatusCode::OK,
+    ))
+}
+
+pub async fn revoke_role(
+    token: TokenData<Claims>,
+    request: RoleRevokeRequest,
+    state: AppState,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let connection = state.db.get().await;
+
+    if request.user_id == token.claims.uid {
+        let r = "Can't change your own role";
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::BAD_REQUEST,
+        ));
+    }
+
+    let old_role = match get_user_role(&connection, request.user_id).await {
+        Some(val) => val,
+        None => {
+            let r = "User not found";
+            return Ok(warp::reply::with_status(
+                warp::reply::json(&r),
+                warp::http::StatusCode::NOT_FOUND,
+            ));
+        }
+    };
+
+    if old_role == "user" {
+        let r = "User has no role";
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::NOT_ACCEPTABLE,
+        ));
+    }
+
+    apply_role_change(&connection, token.claims.uid, request.user_id, old_role, "user".to_string()).await;
+
+    let r = "Role revoked";
+    Ok(warp::reply::with_status(
+        warp::reply::json(&r),
+        warp::http::StatusCode::OK,
+    ))
+}
+
+pub async fn ban_user(
+    token: TokenData<Claims>,
+    request: UserBanRequest,
+    state: AppState,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let connection = state.db.get().await;
+    let id = request.user_id;
+    let level = request.level.unwrap_or("ban".to_string());
+    if !is_sanction_level(&level) {
+        let r = "Invalid ban level";
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::BAD_REQUEST,
+        ));
+    }
+
+    if !outranks(&connection, &token.claims.role, id).await {
+        let r = "User has equal or higher role";
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::FORBIDDEN,
+        ));
+    }
+
+    if check_user_id(&connection, id).await {
+        let mut payload = serde_json::json!({ "ban_length": request.ban_length, "ban_message": request.ban_message, "level": level });
+        // lzejsze kary nie wylogowuja
+        if level == "ban" {
+            revoke_user_sessions_db(&connection, id).await;
+        }
+        payload["ban_id"] = add_ban_db(&connection, id, request.ban_length, request.ban_message, level).await.into();
+        add_audit_log_db(&connection, token.claims.uid, "ban_user", "user", id, payload).await;
+
+        info!("User banned with id: {}", request.user_id);
+        let r = "Ban successful";
+        Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::OK,
+        ))
+    } else {
+        let r = "User not found";
+        Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::NOT_FOUND,
+        ))
+    }
+}
+
+pub async fn unban_user(
+    token: TokenData<Claims>,
+    request: UserUnbanRequest,
+    state: AppState,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let connection = state.db.get().await;
+    let id = request.user_id;
+    if !outranks(&connection, &token.claims.role, id).await {
+        let r = "User has equal or higher role";
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::FORBIDDEN,
         ));
     }
 
-    let connection = tokio_rusqlite::Connection::open("projekt-db")
-        .await
-        .unwrap();
-    let id = request.user_id;
-    let timestamp = SystemTime::now()
-        .duration_since(SystemTime::UNIX_EPOCH)
-        .unwrap()
-        .as_secs() as i64;
-    let expiration = timestamp + request.ban_length;
     if check_user_id(&connection, id).await {
-        let ban_query = "INSERT INTO bans VALUES (?, ?, ?, ?, ?)";
+        let unban_query = "UPDATE bans SET is_active = 0 WHERE user_id = ? AND is_active = 1";
         connection
             .call(move |conn| {
-                let mut statement = conn.prepare(ban_query).unwrap();
-                statement
-                    .execute(params![id, timestamp, expiration, request.ban_message, 1])
-                    .unwrap();
+                let mut statement = conn.prepare(unban_query).unwrap();
+                statement.execute(params![id]).unwrap();
                 Ok(0)
             })
             .await
             .unwrap();
+        add_audit_log_db(&connection, token.claims.uid, "unban_user", "user", id, serde_json::json!({})).await;
 
-        info!("User banned with id: {}", request.user_id);
-        let r = "Ban successful";
+        info!("User unbanned with id: {}", request.user_id);
+        let r = "Unban successful";
         Ok(warp::reply::with_status(
             warp::reply::json(&r),
             warp::http::StatusCode::OK,
@@ -1420,11 +4252,15 @@ pub async fn ban_user(
     }
 }
 
-pub async fn unban_user(
+const REPORT_TARGETS: [&str; 3] = ["post", "comment", "user"];
+const REPORT_REASONS: [&str; 7] = ["spam", "harassment", "hate", "violence", "nudity", "misinformation", "other"];
+
+pub async fn report(
     token: String,
-    request: UserUnbanRequest,
+    request: ReportCreateRequest,
+    state: AppState,
 ) -> Result<impl warp::Reply, warp::Rejection> {
-    let token = match verify_token(token) {
+    let token = match verify_token(&state, token).await {
         Ok(val) => val,
         Err(_) => {
             let r = "Wrong token";
@@ -1435,33 +4271,513 @@ pub async fn unban_user(
         }
     };
 
-    if token.claims.is_admin != 1 {
-        let r = "User is not admin";
+    let connection = state.db.get().await;
+    let id = token.claims.uid;
+
+    if is_limited(&connection, &state.config, id).await && !has_permission(&token.claims.role, Permission::BypassRateLimit) {
+        let r = "Ur too fast";
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::FORBIDDEN,
+        ));
+    }
+
+    if let Some((r, status)) = write_restriction(&connection, id).await {
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            status,
+        ));
+    }
+
+    if !REPORT_TARGETS.contains(&request.target_type.as_str()) {
+        let r = "Invalid target type";
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::BAD_REQUEST,
+        ));
+    }
+
+    if !REPORT_REASONS.contains(&request.reason.as_str()) {
+        let r = "Invalid reason";
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::BAD_REQUEST,
+        ));
+    }
+
+    if request.details.chars().count() > 2048 {
+        let r = "Details too long";
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::BAD_REQUEST,
+        ));
+    }
+
+    let author_id = match request.target_type.as_str() {
+        "post" => get_user_from_post(&connection, request.target_id).await,
+        "comment" => get_comment_post_user(&connection, request.target_id).await.1,
+        _ => {
+            if check_user_id(&connection, request.target_id).await {
+                request.target_id
+            } else {
+                -1
+            }
+        }
+    };
+    if author_id == -1 {
+        let r = match request.target_type.as_str() {
+            "post" => "Post not found",
+            "comment" => "Comment not found",
+            _ => "User not found",
+        };
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::NOT_FOUND,
+        ));
+    }
+
+    if author_id == id {
+        let r = "Can't report yourself";
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::BAD_REQUEST,
+        ));
+    }
+
+    add_upload_db(&connection, id, 3).await;
+    let report_id = add_report_db(
+        &connection,
+        id,
+        request.target_type,
+        request.target_id,
+        author_id,
+        request.reason,
+        request.details
+    )
+    .await;
+
+    match report_id {
+        Some(report_id) => Ok(warp::reply::with_status(
+            warp::reply::json(&report_id),
+            warp::http::StatusCode::CREATED,
+        )),
+        None => {
+            let r = "Report already exists";
+            Ok(warp::reply::with_status(
+                warp::reply::json(&r),
+                warp::http::StatusCode::NOT_ACCEPTABLE,
+            ))
+        }
+    }
+}
+
+// otwarte zgloszenia zgrupowane po celu, najczesciej zglaszane na gorze
+pub async fn get_reports(
+    limit: i64,
+    offset: i64,
+    _token: TokenData<Claims>,
+    state: AppState
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let connection = state.db.get().await;
+    let targets_query = "
+        SELECT target_type, target_id, author_id, COUNT(*) AS report_count, MAX(date) AS last_reported
+        FROM reports
+        WHERE status = 'open'
+        GROUP BY target_type, target_id
+        ORDER BY report_count DESC, last_reported DESC
+        LIMIT ? OFFSET ?
+    ";
+    let reports_query = "
+        SELECT reports.*, users.user_name FROM reports
+        LEFT JOIN users ON users.user_id=reports.reporter_id
+        WHERE reports.target_type = ? AND reports.target_id = ? AND reports.status = 'open'
+        ORDER BY reports.date DESC
+    ";
+
+    let target_list = connection
+        .call(move |conn| {
+            let mut statement = conn.prepare(targets_query).unwrap();
+            let mut rows = statement.query(params![limit, offset]).unwrap();
+            let mut target_vec: Vec<ReportTarget> = Vec::new();
+            while let Ok(Some(row)) = rows.next() {
+                target_vec.push(ReportTarget {
+                    target_type: row.get("target_type").unwrap(),
+                    target_id: row.get("target_id").unwrap(),
+                    author_id: row.get("author_id").unwrap(),
+                    report_count: row.get("report_count").unwrap(),
+                    last_reported: row.get("last_reported").unwrap(),
+                    reports: Vec::new(),
+                });
+            }
+
+            let mut statement = conn.prepare(reports_query).unwrap();
+            for target in target_vec.iter_mut() {
+                let mut rows = statement.query(params![target.target_type, target.target_id]).unwrap();
+                while let Ok(Some(row)) = rows.next() {
+                    target.reports.push(report_from_row(row));
+                }
+            }
+            Ok(target_vec)
+        })
+        .await
+        .unwrap();
+
+    let r = ReportTargetList { target_list };
+    Ok(warp::reply::with_status(
+        warp::reply::json(&r),
+        warp::http::StatusCode::OK,
+    ))
+}
+
+pub async fn get_report_history(
+    report_id: i64,
+    _token: TokenData<Claims>,
+    state: AppState
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let connection = state.db.get().await;
+    let report = match get_report_db(&connection, report_id).await {
+        Some(val) => val,
+        None => {
+            let r = "Report not found";
+            return Ok(warp::reply::with_status(
+                warp::reply::json(&r),
+                warp::http::StatusCode::NOT_FOUND,
+            ));
+        }
+    };
+
+    let query = "
+        SELECT report_actions.*, users.user_name FROM report_actions
+        LEFT JOIN users ON users.user_id=report_actions.admin_id
+        WHERE report_actions.report_id = ?
+        ORDER BY report_actions.action_id ASC
+    ";
+    let action_list = connection
+        .call(move |conn| {
+            let mut statement = conn.prepare(query).unwrap();
+            let mut rows = statement.query(params![report_id]).unwrap();
+            let mut action_vec: Vec<ReportAction> = Vec::new();
+            while let Ok(Some(row)) = rows.next() {
+                action_vec.push(report_action_from_row(row));
+            }
+            Ok(action_vec)
+        })
+        .await
+        .unwrap();
+
+    let r = ReportHistory { report, action_list };
+    Ok(warp::reply::with_status(
+        warp::reply::json(&r),
+        warp::http::StatusCode::OK,
+    ))
+}
+
+// dismiss - nic sie nie dzieje, delete - usuwa post/komentarz, ban - banuje autora
+pub async fn resolve_report(
+    token: TokenData<Claims>,
+    request: ReportResolveRequest,
+    state: AppState,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let connection = state.db.get().await;
+
+    let permission = match request.action.as_str() {
+        "delete" => Some(Permission::DeleteAnyPost),
+        "ban" => Some(Permission::Ban),
+        _ => None,
+    };
+    if permission.is_some_and(|permission| !has_permission(&token.claims.role, permission)) {
+        let r = "Missing permission";
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::FORBIDDEN,
+        ));
+    }
+
+    if count_open_reports(&connection, request.target_type.clone(), request.target_id).await == 0 {
+        let r = "No open reports";
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::NOT_FOUND,
+        ));
+    }
+
+    match request.action.as_str() {
+        "dismiss" => {}
+        "delete" => {
+            let author_id = match request.target_type.as_str() {
+                "post" => get_user_from_post(&connection, request.target_id).await,
+                "comment" => get_comment_post_user(&connection, request.target_id).await.1,
+                _ => {
+                    let r = "Can't delete a user";
+                    return Ok(warp::reply::with_status(
+                        warp::reply::json(&r),
+                        warp::http::StatusCode::BAD_REQUEST,
+                    ));
+                }
+            };
+            if author_id != token.claims.uid && !outranks(&connection, &token.claims.role, author_id).await {
+                let r = "User has equal or higher role";
+                return Ok(warp::reply::with_status(
+                    warp::reply::json(&r),
+                    warp::http::StatusCode::FORBIDDEN,
+                ));
+            }
+            if request.target_type == "post" {
+                delete_post_db(&connection, request.target_id).await;
+            } else {
+                delete_comment_db(&connection, request.target_id).await;
+            }
+        }
+        "ban" => {
+            if request.ban_length <= 0 {
+                let r = "Invalid ban length";
+                return Ok(warp::reply::with_status(
+                    warp::reply::json(&r),
+                    warp::http::StatusCode::BAD_REQUEST,
+                ));
+            }
+            let level = request.ban_level.clone().unwrap_or("ban".to_string());
+            if !is_sanction_level(&level) {
+                let r = "Invalid ban level";
+                return Ok(warp::reply::with_status(
+                    warp::reply::json(&r),
+                    warp::http::StatusCode::BAD_REQUEST,
+                ));
+            }
+            // autor jest zapisany przy zgloszeniu, bo cel mogl juz zniknac
+            let report_query = "
+                SELECT author_id FROM reports
+                WHERE target_type = ? AND target_id = ? AND status = 'open' LIMIT 1";
+            let target_type = request.target_type.clone();
+            let target_id = request.target_id;
+            let author_id: Option<i64> = connection
+                .call(move |conn| {
+                    let mut statement = conn.prepare(report_query).unwrap();
+                    let mut rows = statement.query(params![target_type, target_id]).unwrap();
+                    Ok(rows.next().unwrap().map(|row| row.get(0).unwrap()))
+                })
+                .await
+                .unwrap();
+            // inny moderator mogl rozpatrzyc zgloszenia miedzy sprawdzeniem a tym zapytaniem
+            let Some(author_id) = author_id else {
+                let r = "No open reports";
+                return Ok(warp::reply::with_status(
+                    warp::reply::json(&r),
+                    warp::http::StatusCode::NOT_FOUND,
+                ));
+            };
+            if !outranks(&connection, &token.claims.role, author_id).await {
+                let r = "User has equal or higher role";
+                return Ok(warp::reply::with_status(
+                    warp::reply::json(&r),
+                    warp::http::StatusCode::FORBIDDEN,
+                ));
+            }
+            let mut payload = serde_json::json!({
+                "ban_length": request.ban_length,
+                "ban_message": request.ban_message,
+                "report_target_type": request.target_type,
+                "report_target_id": request.target_id,
+                "level": level,
+            });
+            if level == "ban" {
+                revoke_user_sessions_db(&connection, author_id).await;
+            }
+            payload["ban_id"] = add_ban_db(&connection, author_id, request.ban_length, request.ban_message, level).await.into();
+            add_audit_log_db(&connection, token.claims.uid, "ban_user", "user", author_id, payload).await;
+        }
+        _ => {
+            let r = "Invalid action";
+            return Ok(warp::reply::with_status(
+                warp::reply::json(&r),
+                warp::http::StatusCode::BAD_REQUEST,
+            ));
+        }
+    }
+
+    let payload = serde_json::json!({ "action": request.action, "note": request.note });
+    let resolved = resolve_reports_db(
+        &connection,
+        request.target_type.clone(),
+        request.target_id,
+        token.claims.uid,
+        request.action,
+        request.note
+    )
+    .await;
+    add_audit_log_db(&connection, token.claims.uid, "resolve_report", &request.target_type, request.target_id, payload).await;
+
+    Ok(warp::reply::with_status(
+        warp::reply::json(&resolved),
+        warp::http::StatusCode::OK,
+    ))
+}
+
+pub async fn get_bans(
+    limit: i64,
+    offset: i64,
+    _token: TokenData<Claims>,
+    state: AppState
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let connection = state.db.get().await;
+    let timestamp = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64;
+    let query = "
+        SELECT bans.*, users.user_name FROM bans
+        LEFT JOIN users ON users.user_id=bans.user_id
+        WHERE bans.is_active = 1 AND bans.expires_on > ?
+        ORDER BY bans.expires_on ASC
+        LIMIT ? OFFSET ?
+    ";
+
+    let ban_list = connection
+        .call(move |conn| {
+            let mut statement = conn.prepare(query).unwrap();
+            let mut rows = statement.query(params![timestamp, limit, offset]).unwrap();
+            let mut ban_vec: Vec<Ban> = Vec::new();
+            while let Ok(Some(row)) = rows.next() {
+                ban_vec.push(ban_from_row(row));
+            }
+            Ok(ban_vec)
+        })
+        .await
+        .unwrap();
+
+    let r = BanList { ban_list };
+    Ok(warp::reply::with_status(
+        warp::reply::json(&r),
+        warp::http::StatusCode::OK,
+    ))
+}
+
+pub async fn get_user_bans(
+    user_id: i64,
+    _token: TokenData<Claims>,
+    state: AppState
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let connection = state.db.get().await;
+    let query = "
+        SELECT bans.*, users.user_name FROM bans
+        LEFT JOIN users ON users.user_id=bans.user_id
+        WHERE bans.user_id = ?
+        ORDER BY bans.given_on DESC, bans.ban_id DESC
+    ";
+
+    let ban_list = connection
+        .call(move |conn| {
+            let mut statement = conn.prepare(query).unwrap();
+            let mut rows = statement.query(params![user_id]).unwrap();
+            let mut ban_vec: Vec<Ban> = Vec::new();
+            while let Ok(Some(row)) = rows.next() {
+                ban_vec.push(ban_from_row(row));
+            }
+            Ok(ban_vec)
+        })
+        .await
+        .unwrap();
+
+    let r = BanList { ban_list };
+    Ok(warp::reply::with_status(
+        warp::reply::json(&r),
+        warp::http::StatusCode::OK,
+    ))
+}
+
+// skrocenie/przedluzenie bana albo zmiana powodu
+pub async fn edit_ban(
+    token: TokenData<Claims>,
+    request: BanEditRequest,
+    state: AppState,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let connection = state.db.get().await;
+
+    let ban = match get_ban_db(&connection, request.ban_id).await {
+        Some(val) => val,
+        None => {
+            let r = "Ban not found";
+            return Ok(warp::reply::with_status(
+                warp::reply::json(&r),
+                warp::http::StatusCode::NOT_FOUND,
+            ));
+        }
+    };
+
+    if !outranks(&connection, &token.claims.role, ban.user_id).await {
+        let r = "User has equal or higher role";
         return Ok(warp::reply::with_status(
             warp::reply::json(&r),
-            warp::http::StatusCode::UNAUTHORIZED,
+            warp::http::StatusCode::FORBIDDEN,
         ));
     }
 
-    let connection = tokio_rusqlite::Connection::open("projekt-db")
-        .await
-        .unwrap();
+    if request.expires_on.is_none() && request.ban_message.is_none() {
+        let r = "Nothing to change";
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::BAD_REQUEST,
+        ));
+    }
+
+    let expires_on = request.expires_on.unwrap_or(ban.expires_on);
+    if expires_on < ban.given_on {
+        let r = "Ban can't expire before it was given";
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::BAD_REQUEST,
+        ));
+    }
+
+    let ban_message = request.ban_message.unwrap_or(ban.ban_message.clone());
+    let payload = serde_json::json!({
+        "ban_id": ban.ban_id,
+        "old_expires_on": ban.expires_on,
+        "expires_on": expires_on,
+        "old_ban_message": ban.ban_message,
+        "ban_message": ban_message,
+    });
+    edit_ban_db(&connection, ban.ban_id, expires_on, ban_message).await;
+    add_audit_log_db(&connection, token.claims.uid, "edit_ban", "user", ban.user_id, payload).await;
+
+    let r = "Ban edited";
+    Ok(warp::reply::with_status(
+        warp::reply::json(&r),
+        warp::http::StatusCode::OK,
+    ))
+}
+
+pub async fn get_audit_log(
+    filter: AuditLogQuery,
+    _token: TokenData<Claims>,
+    state: AppState
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let connection = state.db.get().await;
+    let entry_list = get_audit_log_db(&connection, filter).await;
+
+    let r = AuditLogList { entry_list };
+    Ok(warp::reply::with_status(
+        warp::reply::json(&r),
+        warp::http::StatusCode::OK,
+    ))
+}
+
+pub async fn create_password_reset(
+    token: TokenData<Claims>,
+    request: PasswordResetCreateRequest,
+    state: AppState,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let connection = state.db.get().await;
     let id = request.user_id;
+
     if check_user_id(&connection, id).await {
-        let unban_query = "UPDATE bans SET is_active = 0 WHERE user_id = ? AND is_active = 1";
-        connection
-            .call(move |conn| {
-                let mut statement = conn.prepare(unban_query).unwrap();
-                statement.execute(params![id]).unwrap();
-                Ok(0)
-            })
-            .await
-            .unwrap();
+        // sesje leca dopiero przy uzyciu kodu w reset_password
+        let reset_code = add_password_reset_db(&connection, &state.config, id).await;
+        add_audit_log_db(&connection, token.claims.uid, "create_password_reset", "user", id, serde_json::json!({})).await;
 
-        info!("User unbanned with id: {}", request.user_id);
-        let r = "Unban successful";
+        info!("Password reset code created for user {} by admin {}", id, token.claims.uid);
         Ok(warp::reply::with_status(
-            warp::reply::json(&r),
+            warp::reply::json(&reset_code),
             warp::http::StatusCode::OK,
         ))
     } else {
@@ -1476,8 +4792,9 @@ pub async fn unban_user(
 pub async fn change_display_name(
     token: String,
     request: DisplayNameChangeRequest,
+    state: AppState,
 ) -> Result<impl warp::Reply, warp::Rejection> {
-    let token = match verify_token(token) {
+    let token = match verify_token(&state, token).await {
         Ok(val) => val,
         Err(_) => {
             let r = "Wrong token";
@@ -1488,12 +4805,10 @@ pub async fn change_display_name(
         }
     };
 
-    let connection = tokio_rusqlite::Connection::open("projekt-db")
-        .await
-        .unwrap();
+    let connection = state.db.get().await;
     let id = token.claims.uid;
 
-    if is_limited(&connection, token.claims.uid).await && token.claims.is_admin == 0 {
+    if is_limited(&connection, &state.config, token.claims.uid).await && !has_permission(&token.claims.role, Permission::BypassRateLimit) {
         let r = "Ur too fast";
         return Ok(warp::reply::with_status(
             warp::reply::json(&r),
@@ -1501,6 +4816,14 @@ pub async fn change_display_name(
         ));
     }
 
+    if let Some((r, status)) = write_restriction(&connection, token.claims.uid).await {
+        info!("User {} not allowed to change display name", token.claims.uid);
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            status,
+        ));
+    };
+
     if check_user_id(&connection, id).await {
         let change_query = "UPDATE users SET display_name= ? WHERE user_id = ?";
         connection
@@ -1536,8 +4859,9 @@ pub async fn change_display_name(
 pub async fn change_user_name(
     token: String,
     request: UserNameChangeRequest,
+    state: AppState,
 ) -> Result<impl warp::Reply, warp::Rejection> {
-    let token = match verify_token(token) {
+    let token = match verify_token(&state, token).await {
         Ok(val) => val,
         Err(_) => {
             let r = "Wrong token";
@@ -1548,12 +4872,10 @@ pub async fn change_user_name(
         }
     };
 
-    let connection = tokio_rusqlite::Connection::open("projekt-db")
-        .await
-        .unwrap();
+    let connection = state.db.get().await;
     let id = token.claims.uid;
 
-    if is_limited(&connection, token.claims.uid).await && token.claims.is_admin == 0 {
+    if is_limited(&connection, &state.config, token.claims.uid).await && !has_permission(&token.claims.role, Permission::BypassRateLimit) {
         let r = "Ur too fast";
         return Ok(warp::reply::with_status(
             warp::reply::json(&r),
@@ -1561,6 +4883,14 @@ pub async fn change_user_name(
         ));
     }
 
+    if let Some((r, status)) = write_restriction(&connection, token.claims.uid).await {
+        info!("User {} not allowed to change user name", token.claims.uid);
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            status,
+        ));
+    };
+
     if check_user_id(&connection, id).await {
         let change_query = "UPDATE users SET user_name = ? WHERE user_id = ?";
         connection
@@ -1596,8 +4926,9 @@ pub async fn change_user_name(
 pub async fn change_description(
     token: String,
     request: DescriptionChangeRequest,
+    state: AppState,
 ) -> Result<impl warp::Reply, warp::Rejection> {
-    let token = match verify_token(token) {
+    let token = match verify_token(&state, token).await {
         Ok(val) => val,
         Err(_) => {
             let r = "Wrong token";
@@ -1608,12 +4939,10 @@ pub async fn change_description(
         }
     };
 
-    let connection = tokio_rusqlite::Connection::open("projekt-db")
-        .await
-        .unwrap();
+    let connection = state.db.get().await;
     let id = token.claims.uid;
 
-    if is_limited(&connection, token.claims.uid).await && token.claims.is_admin == 0 {
+    if is_limited(&connection, &state.config, token.claims.uid).await && !has_permission(&token.claims.role, Permission::BypassRateLimit) {
         let r = "Ur too fast";
         return Ok(warp::reply::with_status(
             warp::reply::json(&r),
@@ -1621,6 +4950,14 @@ pub async fn change_description(
         ));
     }
 
+    if let Some((r, status)) = write_restriction(&connection, token.claims.uid).await {
+        info!("User {} not allowed to change description", token.claims.uid);
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            status,
+        ));
+    };
+
     if check_user_id(&connection, id).await {
         let change_query = "UPDATE users SET description= ? WHERE user_id = ?";
         connection
@@ -1650,11 +4987,111 @@ pub async fn change_description(
     }
 }
 
+pub async fn change_password(
+    token: String,
+    request: PasswordChangeRequest,
+    state: AppState,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let token = match verify_token(&state, token).await {
+        Ok(val) => val,
+        Err(_) => {
+            let r = "Wrong token";
+            return Ok(warp::reply::with_status(
+                warp::reply::json(&r),
+                warp::http::StatusCode::UNAUTHORIZED,
+            ));
+        }
+    };
+
+    let connection = state.db.get().await;
+    let id = token.claims.uid;
+
+    if is_limited(&connection, &state.config, token.claims.uid).await && !has_permission(&token.claims.role, Permission::BypassRateLimit) {
+        let r = "Ur too fast";
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::FORBIDDEN,
+        ));
+    }
+
+    let hash = match get_passwd(&connection, id).await {
+        Ok(val) => val,
+        Err(_) => {
+            let r = "User not found";
+            return Ok(warp::reply::with_status(
+                warp::reply::json(&r),
+                warp::http::StatusCode::NOT_FOUND,
+            ));
+        }
+    };
+
+    add_upload_db(&connection, token.claims.uid, 5).await;
+
+    if !verify_hash(request.passwd, hash) {
+        info!("User {} failed to change password", id);
+        let r = "Incorrect password";
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::UNAUTHORIZED,
+        ));
+    }
+
+    if request.new_passwd.is_empty() {
+        let r = "Empty password not allowed";
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::NOT_ACCEPTABLE,
+        ));
+    }
+
+    set_passwd_db(&connection, id, request.new_passwd).await;
+    revoke_user_sessions_db(&connection, id).await;
+
+    let r = "Password change successful";
+    Ok(warp::reply::with_status(
+        warp::reply::json(&r),
+        warp::http::StatusCode::OK,
+    ))
+}
+
+pub async fn reset_password(
+    request: PasswordResetRequest,
+    state: AppState,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let connection = state.db.get().await;
+
+    if request.new_passwd.is_empty() {
+        let r = "Empty password not allowed";
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            warp::http::StatusCode::NOT_ACCEPTABLE,
+        ));
+    }
+
+    match use_password_reset_db(&connection, request.user_name, request.reset_code).await {
+        Ok(id) => {
+            set_passwd_db(&connection, id, request.new_passwd).await;
+            revoke_user_sessions_db(&connection, id).await;
+            info!("Password reset for user {}", id);
+            let r = "Password reset successful";
+            Ok(warp::reply::with_status(
+                warp::reply::json(&r),
+                warp::http::StatusCode::OK,
+            ))
+        }
+        Err(e) => Ok(warp::reply::with_status(
+            warp::reply::json(&e),
+            warp::http::StatusCode::UNAUTHORIZED,
+        )),
+    }
+}
+
 pub async fn upload_image(
     token: String,
     form: FormData,
+    state: AppState,
 ) -> Result<impl warp::Reply, warp::Rejection> {
-    let token = match verify_token(token) {
+    let token = match verify_token(&state, token).await {
         Ok(val) => val,
         Err(_) => {
             let r = "Wrong token";
@@ -1665,11 +5102,9 @@ pub async fn upload_image(
         }
     };
 
-    let connection = tokio_rusqlite::Connection::open("projekt-db")
-        .await
-        .unwrap();
+    let connection = state.db.get().await;
 
-    if is_limited(&connection, token.claims.uid).await && token.claims.is_admin == 0 {
+    if is_limited(&connection, &state.config, token.claims.uid).await && !has_permission(&token.claims.role, Permission::BypassRateLimit) {
         let r = "Ur too fast";
         return Ok(warp::reply::with_status(
             warp::reply::json(&r),
@@ -1677,6 +5112,14 @@ pub async fn upload_image(
         ));
     }
 
+    if let Some((r, status)) = write_restriction(&connection, token.claims.uid).await {
+        info!("User {} not allowed to upload images", token.claims.uid);
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            status,
+        ));
+    };
+
     let mut parts = form.into_stream();
     while let Some(Ok(p)) = parts.next().await {
         if p.name() == "file" {
@@ -1721,8 +5164,9 @@ pub async fn upload_image(
                 }
             };
             let image_uuid = uuid::Uuid::new_v4().to_string();
-            let file_name = format!("./media/images/{}.{}", image_uuid, file_ending);
-            let pfp_file_name = format!("./media/images/pfp_{}.{}", image_uuid, file_ending);
+            let images_dir = state.config.images_dir();
+            let file_name = format!("{}/{}.{}", images_dir, image_uuid, file_ending);
+            let pfp_file_name = format!("{}/pfp_{}.{}", images_dir, image_uuid, file_ending);
 
             match add_image_db(&connection, format!("{}.{}", image_uuid, file_ending)).await {
                 Ok(val) => {
@@ -1762,12 +5206,11 @@ pub async fn upload_image(
 pub async fn set_pfp(
     token: String,
     request: SetPFPRequest,
+    state: AppState,
 ) -> Result<impl warp::Reply, warp::Rejection> {
-    let connection = tokio_rusqlite::Connection::open("projekt-db")
-        .await
-        .unwrap();
+    let connection = state.db.get().await;
 
-    let token = match verify_token(token) {
+    let token = match verify_token(&state, token).await {
         Ok(val) => val,
         Err(_) => {
             let r = "Wrong token";
@@ -1778,7 +5221,7 @@ pub async fn set_pfp(
         }
     };
 
-    if is_limited(&connection, token.claims.uid).await && token.claims.is_admin == 0 {
+    if is_limited(&connection, &state.config, token.claims.uid).await && !has_permission(&token.claims.role, Permission::BypassRateLimit) {
         let r = "Ur too fast";
         return Ok(warp::reply::with_status(
             warp::reply::json(&r),
@@ -1786,6 +5229,14 @@ pub async fn set_pfp(
         ));
     }
 
+    if let Some((r, status)) = write_restriction(&connection, token.claims.uid).await {
+        info!("User {} not allowed to set profile picture", token.claims.uid);
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            status,
+        ));
+    };
+
     if !check_image(&connection, request.image_id).await {
         let r = "Image not found";
         return Ok(warp::reply::with_status(
@@ -1822,12 +5273,11 @@ pub async fn set_pfp(
 pub async fn remove_pfp(
     token: String,
     _request: RemovePFPRequest,
+    state: AppState,
 ) -> Result<impl warp::Reply, warp::Rejection> {
-    let connection = tokio_rusqlite::Connection::open("projekt-db")
-        .await
-        .unwrap();
+    let connection = state.db.get().await;
 
-    let token = match verify_token(token) {
+    let token = match verify_token(&state, token).await {
         Ok(val) => val,
         Err(_) => {
             let r = "Wrong token";
@@ -1838,7 +5288,7 @@ pub async fn remove_pfp(
         }
     };
 
-    if is_limited(&connection, token.claims.uid).await && token.claims.is_admin == 0 {
+    if is_limited(&connection, &state.config, token.claims.uid).await && !has_permission(&token.claims.role, Permission::BypassRateLimit) {
         let r = "Ur too fast";
         return Ok(warp::reply::with_status(
             warp::reply::json(&r),
@@ -1846,6 +5296,14 @@ pub async fn remove_pfp(
         ));
     }
 
+    if let Some((r, status)) = write_restriction(&connection, token.claims.uid).await {
+        info!("User {} not allowed to remove profile picture", token.claims.uid);
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            status,
+        ));
+    };
+
     if !check_user_id(&connection, token.claims.uid).await {
         let r = "User not found";
         return Ok(warp::reply::with_status(
@@ -1867,12 +5325,11 @@ pub async fn remove_pfp(
 pub async fn add_image_to_post(
     token: String,
     request: AddImageToPostRequest,
+    state: AppState,
 ) -> Result<impl warp::Reply, warp::Rejection> {
-    let connection = tokio_rusqlite::Connection::open("projekt-db")
-        .await
-        .unwrap();
+    let connection = state.db.get().await;
 
-    let token = match verify_token(token) {
+    let token = match verify_token(&state, token).await {
         Ok(val) => val,
         Err(_) => {
             let r = "Wrong token";
@@ -1883,7 +5340,7 @@ pub async fn add_image_to_post(
         }
     };
 
-    if is_limited(&connection, token.claims.uid).await && token.claims.is_admin == 0 {
+    if is_limited(&connection, &state.config, token.claims.uid).await && !has_permission(&token.claims.role, Permission::BypassRateLimit) {
         let r = "Ur too fast";
         return Ok(warp::reply::with_status(
             warp::reply::json(&r),
@@ -1891,6 +5348,14 @@ pub async fn add_image_to_post(
         ));
     }
 
+    if let Some((r, status)) = write_restriction(&connection, token.claims.uid).await {
+        info!("User {} not allowed to add images to posts", token.claims.uid);
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&r),
+            status,
+        ));
+    };
+
     if !check_image(&connection, request.image_id).await {
         let r = "Image not found";
         return Ok(warp::reply::with_status(
@@ -1907,9 +5372,8 @@ pub async fn add_image_to_post(
         ));
     }
 
-    if token.claims.uid != get_user_from_post(&connection, request.post_id).await
-        && token.claims.is_admin == 0
-    {
+    let author_id = get_user_from_post(&connection, request.post_id).await;
+    if token.claims.uid != author_id && !has_permission(&token.claims.role, Permission::EditAnyPost) {
         let r = "User not authorized";
         return Ok(warp::reply::with_status(
             warp::reply::json(&r),
@@ -1918,6 +5382,10 @@ pub async fn add_image_to_post(
     }
 
     add_upload_db(&connection, token.claims.uid, 1).await;
+    if token.claims.uid != author_id {
+        let payload = serde_json::json!({ "author_id": author_id, "image_id": request.image_id });
+        add_audit_log_db(&connection, token.claims.uid, "add_image_to_post", "post", request.post_id, payload).await;
+    }
 
     match assign_image_to_post_db(&connection, request.post_id, request.image_id).await {
         Ok(_) => {
@@ -1948,11 +5416,6 @@ pub async fn handle_rejection(
             "Incorrect password",
             warp::http::StatusCode::UNAUTHORIZED,
         ))
-    } else if err.find::<UserBanned>().is_some() {
-        Ok(warp::reply::with_status(
-            "User banned",
-            warp::http::StatusCode::UNAUTHORIZED,
-        ))
     } else if err.find::<UserNotFound>().is_some() {
         Ok(warp::reply::with_status(
             "User not found",
@@ -1978,6 +5441,11 @@ pub async fn handle_rejection(
             "Wrong token",
             warp::http::StatusCode::UNAUTHORIZED,
         ))
+    } else if err.find::<MissingPermission>().is_some() {
+        Ok(warp::reply::with_status(
+            "Missing permission",
+            warp::http::StatusCode::FORBIDDEN,
+        ))
     } else {
         Ok(warp::reply::with_status(
             "Internal server error",
@@ -1986,14 +5454,138 @@ pub async fn handle_rejection(
     }
 }
 
+// wspolny filtr dla wszystkich uprzywilejowanych endpointow: token z ciasteczka + sprawdzenie roli
+pub fn with_permission(
+    state: AppState,
+    permission: Permission,
+) -> impl Filter<Extract = (TokenData<Claims>,), Error = warp::Rejection> + Clone {
+    warp::cookie::<String>("token")
+        .and(with_state(state))
+        .and_then(move |token: String, state: AppState| async move {
+            let token = match verify_token(&state, token).await {
+                Ok(val) => val,
+                Err(_) => return Err(warp::reject::custom(WrongToken)),
+            };
+            if !has_permission(&token.claims.role, permission) {
+                info!("User {} is missing permission {:?}", token.claims.uid, permission);
+                return Err(warp::reject::custom(MissingPermission));
+            }
+            Ok(token)
+        })
+}
+
+pub fn grant_role_json() -> impl Filter<Extract = (RoleGrantRequest,), Error = warp::Rejection> + Clone {
+    warp::body::content_length_limit(1024 * 16).and(warp::body::json())
+}
+
+pub fn revoke_role_json() -> impl Filter<Extract = (RoleRevokeRequest,), Error = warp::Rejection> + Clone {
+    warp::body::content_length_limit(1024 * 16).and(warp::body::json())
+}
+
 pub fn post_json() -> impl Filter<Extract = (PostCreateRequest,), Error = warp::Rejection> + Clone {
     warp::body::content_length_limit(1024 * 16).and(warp::body::json())
 }
 
+pub fn edit_post_json() -> impl Filter<Extract = (PostEditRequest,), Error = warp::Rejection> + Clone {
+    warp::body::content_length_limit(1024 * 16).and(warp::body::json())
+}
+
+pub fn revoke_session_json() -> impl Filter<Extract = (SessionRevokeRequest,), Error = warp::Rejection> + Clone {
+    warp::body::content_length_limit(1024 * 16).and(warp::body::json())
+}
+
+pub fn change_password_json() -> impl Filter<Extract = (PasswordChangeRequest,), Error = warp::Rejection> + Clone {
+    warp::body::content_length_limit(1024 * 16).and(warp::body::json())
+}
+
+pub fn create_password_reset_json() -> impl Filter<Extract = (PasswordResetCreateRequest,), Error = warp::Rejection> + Clone {
+    warp::body::content_length_limit(1024 * 16).and(warp::body::json())
+}
+
+pub fn reset_password_json() -> impl Filter<Extract = (PasswordResetRequest,), Error = warp::Rejection> + Clone {
+    warp::body::content_length_limit(1024 * 16).and(warp::body::json())
+}
+
+pub fn follow_json() -> impl Filter<Extract = (FollowRequest,), Error = warp::Rejection> + Clone {
+    warp::body::content_length_limit(1024 * 16).and(warp::body::json())
+}
+
+pub fn unfollow_json() -> impl Filter<Extract = (UnfollowRequest,), Error = warp::Rejection> + Clone {
+    warp::body::content_length_limit(1024 * 16).and(warp::body::json())
+}
+
 pub fn comment_json() -> impl Filter<Extract = (CommentCreateRequest,), Error = warp::Rejection> + Clone {
     warp::body::content_length_limit(1024 * 16).and(warp::body::json())
 }
 
+pub fn edit_comment_json() -> impl Filter<Extract = (CommentEditRequest,), Error = warp::Rejection> + Clone {
+    warp::body::content_length_limit(1024 * 16).and(warp::body::json())
+}
+
+pub fn delete_comment_json() -> impl Filter<Extract = (CommentDeleteRequest,), Error = warp::Rejection> + Clone {
+    warp::body::content_length_limit(1024 * 16).and(warp::body::json())
+}
+
+pub fn read_notifications_json() -> impl Filter<Extract = (NotificationReadRequest,), Error = warp::Rejection> + Clone {
+    warp::body::content_length_limit(1024 * 16).and(warp::body::json())
+}
+
+pub fn start_conversation_json() -> impl Filter<Extract = (ConversationCreateRequest,), Error = warp::Rejection> + Clone {
+    warp::body::content_length_limit(1024 * 16).and(warp::body::json())
+}
+
+pub fn send_message_json() -> impl Filter<Extract = (MessageSendRequest,), Error = warp::Rejection> + Clone {
+    warp::body::content_length_limit(1024 * 16).and(warp::body::json())
+}
+
+pub fn block_json() -> impl Filter<Extract = (BlockRequest,), Error = warp::Rejection> + Clone {
+    warp::body::content_length_limit(1024 * 16).and(warp::body::json())
+}
+
+pub fn mute_json() -> impl Filter<Extract = (MuteRequest,), Error = warp::Rejection> + Clone {
+    warp::body::content_length_limit(1024 * 16).and(warp::body::json())
+}
+
+pub fn create_collection_json() -> impl Filter<Extract = (CollectionCreateRequest,), Error = warp::Rejection> + Clone {
+    warp::body::content_length_limit(1024 * 16).and(warp::body::json())
+}
+
+pub fn delete_collection_json() -> impl Filter<Extract = (CollectionDeleteRequest,), Error = warp::Rejection> + Clone {
+    warp::body::content_length_limit(1024 * 16).and(warp::body::json())
+}
+
+pub fn add_to_collection_json() -> impl Filter<Extract = (CollectionAddRequest,), Error = warp::Rejection> + Clone {
+    warp::body::content_length_limit(1024 * 16).and(warp::body::json())
+}
+
+pub fn remove_from_collection_json() -> impl Filter<Extract = (CollectionRemoveRequest,), Error = warp::Rejection> + Clone {
+    warp::body::content_length_limit(1024 * 16).and(warp::body::json())
+}
+
+pub fn repost_json() -> impl Filter<Extract = (RepostRequest,), Error = warp::Rejection> + Clone {
+    warp::body::content_length_limit(1024 * 16).and(warp::body::json())
+}
+
+pub fn unrepost_json() -> impl Filter<Extract = (UnrepostRequest,), Error = warp::Rejection> + Clone {
+    warp::body::content_length_limit(1024 * 16).and(warp::body::json())
+}
+
+pub fn vote_poll_json() -> impl Filter<Extract = (PollVoteRequest,), Error = warp::Rejection> + Clone {
+    warp::body::content_length_limit(1024 * 16).and(warp::body::json())
+}
+
+pub fn report_json() -> impl Filter<Extract = (ReportCreateRequest,), Error = warp::Rejection> + Clone {
+    warp::body::content_length_limit(1024 * 16).and(warp::body::json())
+}
+
+pub fn resolve_report_json() -> impl Filter<Extract = (ReportResolveRequest,), Error = warp::Rejection> + Clone {
+    warp::body::content_length_limit(1024 * 16).and(warp::body::json())
+}
+
+pub fn edit_ban_json() -> impl Filter<Extract = (BanEditRequest,), Error = warp::Rejection> + Clone {
+    warp::body::content_length_limit(1024 * 16).and(warp::body::json())
+}
+
 pub fn login_json() -> impl Filter<Extract = (LoginRequest,), Error = warp::Rejection> + Clone {
     warp::body::content_length_limit(1024 * 16).and(warp::body::json())
 }
@@ -2024,11 +5616,11 @@ pub fn unban_json() -> impl Filter<Extract = (UserUnbanRequest,), Error = warp::
     warp::body::content_length_limit(1024 * 16).and(warp::body::json())
 }
 
-pub fn react_json() -> impl Filter<Extract = (LikeRequest,), Error = warp::Rejection> + Clone {
+pub fn react_json() -> impl Filter<Extract = (ReactRequest,), Error = warp::Rejection> + Clone {
     warp::body::content_length_limit(1024 * 16).and(warp::body::json())
 }
 
-pub fn unreact_json() -> impl Filter<Extract = (UnlikeRequest,), Error = warp::Rejection> + Clone {
+pub fn unreact_json() -> impl Filter<Extract = (UnreactRequest,), Error = warp::Rejection> + Clone {
     warp::body::content_length_limit(1024 * 16).and(warp::body::json())
 }
 
@@ -1,15 +1,18 @@
-use argon2::{password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString}, Argon2};
+use argon2::{password_hash::{rand_core::{OsRng, RngCore}, PasswordHash, PasswordHasher, PasswordVerifier, SaltString}, Argon2};
 // argon2 jest wolny generalnie ale nie az tak jak jest teraz, zmiana na release build powinna przyspieszyc
 // https://www.reddit.com/r/rust/comments/1ajkqd7/argon2_slow_is_hashing_password/
 use jsonwebtoken::{decode, encode, errors, EncodingKey, Header, DecodingKey, Validation, Algorithm, TokenData};
 use std::time::SystemTime; 
 use std::fs;
+use sha2::{Digest, Sha256};
 use crate::types::Claims;
+use crate::config::Config;
+use crate::database_functions::check_session;
+use crate::state::AppState;
 
-pub fn get_secret() -> String{
-    let contents = fs::read_to_string("./SECRET")
-        .expect("Should have been able to read the file");
-    contents
+pub fn get_secret(config: &Config) -> String{
+    fs::read_to_string(&config.secret_path)
+        .expect("Should have been able to read the file")
 }
 
 fn get_sys_time_in_secs() -> u64 {
@@ -19,26 +22,67 @@ fn get_sys_time_in_secs() -> u64 {
     }
 }
 
-pub fn get_token(user_id: i64, is_admin_value: i64) -> String {
-    let file_contents = get_secret();
+pub fn get_token(config: &Config, user_id: i64, role: String, session_id: String) -> String {
+    let file_contents = get_secret(config);
     let jwt_secret = file_contents.as_str().trim();
-    let expiration = get_sys_time_in_secs() + 1209600; // wazny przez 10 dni
+    let expiration = get_sys_time_in_secs() + config.access_token_lifetime;
 
     let claims = Claims {
         uid: user_id,
         exp: expiration,
-        is_admin: is_admin_value
+        role,
+        jti: session_id,
     };
     let header = Header::new(Algorithm::HS256);
     let tkn = encode(&header, &claims, &EncodingKey::from_base64_secret(jwt_secret).expect("Nie udalo sie zdekodowac sekretu"));
     tkn.expect("REASON")
 }
 
-pub fn verify_token(token: String) -> Result<TokenData<Claims>, errors::Error>{
-   let file_contents = get_secret();
+fn get_random_hex(length: usize) -> String {
+    let mut bytes = vec![0u8; length];
+    OsRng.fill_bytes(&mut bytes);
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+// refresh tokeny i kody resetu hasla to losowe ciagi, w bazie trzymamy tylko ich hash
+pub fn get_refresh_token() -> String {
+    get_random_hex(32)
+}
+
+pub fn get_reset_code() -> String {
+    get_random_hex(16)
+}
+
+pub fn hash_token(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
+
+// tylko podpis i czas waznosci, bez sprawdzania sesji
+pub fn decode_token(config: &Config, token: &str) -> Result<TokenData<Claims>, errors::Error> {
+   decode_with_validation(config, token, Validation::new(Algorithm::HS256))
+}
+
+// sam podpis, do wylogowania po wygasnieciu tokenu
+pub fn decode_expired_token(config: &Config, token: &str) -> Result<TokenData<Claims>, errors::Error> {
+   let mut validation = Validation::new(Algorithm::HS256);
+   validation.validate_exp = false;
+   decode_with_validation(config, token, validation)
+}
+
+fn decode_with_validation(config: &Config, token: &str, validation: Validation) -> Result<TokenData<Claims>, errors::Error> {
+   let file_contents = get_secret(config);
    let jwt_secret = file_contents.as_str().trim();
    // Claims is a struct that implements Deserialize
-   decode::<Claims>(&token, &DecodingKey::from_base64_secret(jwt_secret).expect("Nie udalo sie zdekodowac sekretu"), &Validation::new(Algorithm::HS256))
+   decode::<Claims>(token, &DecodingKey::from_base64_secret(jwt_secret).expect("Nie udalo sie zdekodowac sekretu"), &validation)
+}
+
+pub async fn verify_token(state: &AppState, token: String) -> Result<TokenData<Claims>, errors::Error>{
+   let token_data = decode_token(&state.config, &token)?;
+   // sam podpis nie wystarczy, sesja mogla zostac odwolana (logout, ban, ...)
+   if !check_session(&state.db.get().await, token_data.claims.jti.clone(), token_data.claims.uid).await {
+       return Err(errors::ErrorKind::InvalidToken.into());
+   }
+   Ok(token_data)
 }
 
 pub fn get_hash(password: String) -> String {
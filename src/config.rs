@@ -0,0 +1,123 @@
+use std::env;
+use std::fs;
+use std::net::SocketAddr;
+use std::str::FromStr;
+
+use serde::Deserialize;
+use tracing::{info, warn};
+
+const DEFAULT_CONFIG_PATH: &str = "./config.toml";
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct Config {
+    pub bind_address: SocketAddr,
+    pub db_path: String,
+    pub db_pool_size: usize,
+    pub media_root: String,
+    pub secret_path: String,
+    pub access_token_lifetime: u64,
+    pub refresh_token_lifetime: i64,
+    pub short_refresh_token_lifetime: i64,
+    pub password_reset_lifetime: i64,
+    pub rate_limit_window: i64,
+    pub rate_limit_max_weight: i64,
+    pub trending_tags_window: i64,
+    pub upload_size_limit: u64,
+    pub cors_origins: Vec<String>,
+    pub reaction_kinds: Vec<String>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            bind_address: SocketAddr::from(([0, 0, 0, 0], 8000)),
+            db_path: "projekt-db".to_string(),
+            db_pool_size: 4,
+            media_root: "./media".to_string(),
+            secret_path: "./SECRET".to_string(),
+            access_token_lifetime: 900,
+            refresh_token_lifetime: 1209600,
+            short_refresh_token_lifetime: 86400,
+            password_reset_lifetime: 86400,
+            rate_limit_window: 60,
+            rate_limit_max_weight: 50,
+            trending_tags_window: 86400,
+            upload_size_limit: 25000000,
+            cors_origins: Vec::new(),
+            reaction_kinds: ["👍", "❤️", "😂", "😮", "😢", "😡"].iter().map(|kind| kind.to_string()).collect(),
+        }
+    }
+}
+
+impl Config {
+    // plik z PROJEKT_CONFIG (albo ./config.toml), potem nadpisania ze zmiennych PROJEKT_*
+    pub fn load() -> Config {
+        let path = env::var("PROJEKT_CONFIG").unwrap_or(DEFAULT_CONFIG_PATH.to_string());
+        let mut config = match fs::read_to_string(&path) {
+            Ok(contents) => toml::from_str(&contents).expect("Invalid config file"),
+            Err(_) => {
+                warn!("Config file {} not found, using defaults", path);
+                Config::default()
+            }
+        };
+
+        env_override("PROJEKT_BIND_ADDRESS", &mut config.bind_address);
+        env_override("PROJEKT_DB_PATH", &mut config.db_path);
+        env_override("PROJEKT_DB_POOL_SIZE", &mut config.db_pool_size);
+        env_override("PROJEKT_MEDIA_ROOT", &mut config.media_root);
+        env_override("PROJEKT_SECRET_PATH", &mut config.secret_path);
+        env_override("PROJEKT_ACCESS_TOKEN_LIFETIME", &mut config.access_token_lifetime);
+        env_override("PROJEKT_REFRESH_TOKEN_LIFETIME", &mut config.refresh_token_lifetime);
+        env_override("PROJEKT_SHORT_REFRESH_TOKEN_LIFETIME", &mut config.short_refresh_token_lifetime);
+        env_override("PROJEKT_PASSWORD_RESET_LIFETIME", &mut config.password_reset_lifetime);
+        env_override("PROJEKT_RATE_LIMIT_WINDOW", &mut config.rate_limit_window);
+        env_override("PROJEKT_RATE_LIMIT_MAX_WEIGHT", &mut config.rate_limit_max_weight);
+        env_override("PROJEKT_TRENDING_TAGS_WINDOW", &mut config.trending_tags_window);
+        env_override("PROJEKT_UPLOAD_SIZE_LIMIT", &mut config.upload_size_limit);
+        if let Ok(val) = env::var("PROJEKT_CORS_ORIGINS") {
+            config.cors_origins = val
+                .split(',')
+                .map(|origin| origin.trim().to_string())
+                .filter(|origin| !origin.is_empty())
+                .collect();
+        }
+        if let Ok(val) = env::var("PROJEKT_REACTION_KINDS") {
+            config.reaction_kinds = val
+                .split(',')
+                .map(|kind| kind.trim().to_string())
+                .filter(|kind| !kind.is_empty())
+                .collect();
+        }
+        // DbPool::get dzieli przez liczbe polaczen
+        if config.db_pool_size < 1 {
+            warn!("db_pool_size must be at least 1, using 1");
+            config.db_pool_size = 1;
+        }
+
+        info!("Loaded config: {:?}", config);
+        config
+    }
+
+    // remember_password decyduje jak dlugo zyje refresh token (a wiec i sesja)
+    pub fn refresh_lifetime(&self, remember: bool) -> i64 {
+        if remember {
+            self.refresh_token_lifetime
+        } else {
+            self.short_refresh_token_lifetime
+        }
+    }
+
+    pub fn images_dir(&self) -> String {
+        format!("{}/images", self.media_root)
+    }
+}
+
+fn env_override<T: FromStr>(name: &str, field: &mut T) {
+    if let Ok(val) = env::var(name) {
+        match val.parse() {
+            Ok(parsed) => *field = parsed,
+            Err(_) => warn!("Ignoring invalid value of {}: {}", name, val),
+        }
+    }
+}
@@ -1,22 +1,149 @@
+use std::collections::HashMap;
 use std::time::SystemTime;
 
-use tokio_rusqlite::{Connection, params};
-use tracing::{debug, info};
+use tokio_rusqlite::{Connection, Row, TransactionBehavior, params};
+use tracing::info;
 
 
-use crate::types::{Post, SignupRequest};
+use crate::types::{AuditLogEntry, AuditLogQuery, Ban, Comment, Message, Poll, PollCreateRequest, PollOption, Post, PostSearchQuery, Profile, QuotedPost, Report, ReportAction, RepostInfo, SearchResult, SignupRequest};
 use crate::auth::*;
+use crate::config::Config;
+use crate::sanctions::Sanction;
+
+// wiersz z "SELECT posts.*, users.user_name, users.display_name, images.image_file,
+// COUNT(comments.comment_id) AS comment_count ..."
+pub fn post_from_row(row: &Row) -> Post {
+    Post {
+        post_id: row.get("post_id").unwrap(),
+        user_id: row.get("user_id").unwrap(),
+        date: row.get("date").unwrap(),
+        body: row.get("body").unwrap(),
+        likes: row.get("likes").unwrap(),
+        reactions: reaction_counts_from_row(row),
+        user_name: row.get("user_name").unwrap(),
+        display_name: row.get("display_name").unwrap(),
+        pfp_image: row.get("image_file").unwrap_or_default(),
+        like_count: row.get("comment_count").unwrap(),
+        edited_at: row.get("edited_at").unwrap(),
+        repost_count: row.get("repost_count").unwrap(),
+        quote_count: row.get("quote_count").unwrap(),
+        quoted_post_id: row.get("quoted_post_id").unwrap(),
+        quoted_post: None,
+        // kolumny reposted_by* sa tylko w zapytaniach laczacych posty z repostami
+        reposted_by: row.get::<_, Option<i64>>("reposted_by").ok().flatten().map(|user_id| RepostInfo {
+            user_id,
+            user_name: row.get("reposted_by_name").unwrap(),
+            display_name: row.get("reposted_by_display_name").unwrap(),
+            date: row.get("activity_date").unwrap(),
+        }),
+        poll: None,
+    }
+}
+
+// wiersz z "SELECT users.user_id, users.user_name, users.display_name, users.description,
+// images.image_file, ... AS follower_count, ... AS following_count ..."
+pub fn profile_from_row(row: &Row) -> Profile {
+    Profile {
+        user_id: row.get("user_id").unwrap(),
+        user_name: row.get("user_name").unwrap(),
+        display_name: row.get("display_name").unwrap(),
+        description: row.get("description").unwrap(),
+        pfp_image: match row.get::<_, String>("image_file") {
+            Ok(val) => format!("pfp_{}", val),
+            Err(_) => "".to_string()
+        },
+        follower_count: row.get("follower_count").unwrap(),
+        following_count: row.get("following_count").unwrap(),
+    }
+}
+
+// wiersz z "SELECT comments.*, users.user_name, users.display_name, images.image_file,
+// ... AS reply_count ...", odpowiedzi doklada sie osobno
+pub fn comment_from_row(row: &Row) -> Comment {
+    Comment {
+        post_id: row.get("post_id").unwrap(),
+        comment_id: row.get("comment_id").unwrap(),
+        user_id: row.get("user_id").unwrap(),
+        body: row.get("body").unwrap(),
+        date: row.get("date").unwrap(),
+        user_name: row.get("user_name").unwrap(),
+        display_name: row.get("display_name").unwrap(),
+        pfp_image: row.get("image_file").unwrap_or_default(),
+        parent_comment_id: row.get("parent_comment_id").unwrap(),
+        edited_at: row.get("edited_at").unwrap(),
+        reply_count: row.get("reply_count").unwrap(),
+        reactions: reaction_counts_from_row(row),
+        replies: Vec::new(),
+    }
+}
+
+// posts.reaction_counts i comments.reaction_counts to JSON {rodzaj: liczba}
+pub fn reaction_counts_from_row(row: &Row) -> HashMap<String, i64> {
+    let counts: String = row.get("reaction_counts").unwrap();
+    serde_json::from_str(&counts).unwrap_or_default()
+}
+
+// zamienia wpisana fraze na bezpieczne zapytanie FTS5: "fraza w cudzyslowie" zostaje fraza,
+// slowo* szuka po prefiksie, reszta slow jest laczona przez AND
+pub fn fts_query(raw: &str) -> Option<String> {
+    let mut terms: Vec<String> = Vec::new();
+    let mut chars = raw.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c.is_whitespace() {
+            continue;
+        }
+        let mut term = String::new();
+        let mut prefix = false;
+        if c == '"' {
+            for c in chars.by_ref() {
+                if c == '"' {
+                    break;
+                }
+                term.push(c);
+            }
+        } else {
+            term.push(c);
+            while let Some(&c) = chars.peek() {
+                if c.is_whitespace() || c == '"' {
+                    break;
+                }
+                term.push(c);
+                chars.next();
+            }
+            if term.ends_with('*') {
+                prefix = true;
+                term = term.trim_end_matches('*').to_string();
+            }
+        }
+        if !term.chars().any(char::is_alphanumeric) {
+            continue;
+        }
+        let quoted = format!("\"{}\"", term.replace('"', "\"\""));
+        terms.push(if prefix { quoted + "*" } else { quoted });
+    }
+    if terms.is_empty() {
+        None
+    } else {
+        Some(terms.join(" "))
+    }
+}
+
+// warunek do WHERE chowajacy autorow zablokowanych i wyciszonych przez ogladajacego,
+// viewer_id = -1 (brak tokena) nic nie chowa
+pub fn hidden_authors_filter(column: &str, viewer_id: i64) -> String {
+    format!(
+        "{} NOT IN (SELECT blocked_id FROM blocks WHERE blocker_id = {}) \
+        AND {} NOT IN (SELECT muted_id FROM mutes WHERE muter_id = {})",
+        column, viewer_id, column, viewer_id
+    )
+}
 
 pub async fn check_user_id(connection: &Connection, id: i64) -> bool {
     let query = "SELECT user_id FROM users WHERE user_id = ?";
     connection.call(move |conn| {
         let mut statement = conn.prepare(query).unwrap();
         let mut rows = statement.query([id]).unwrap();
-        if let Some(_) = rows.next().unwrap() {
-            Ok(true)
-        } else {
-            Ok(false)
-        }
+        Ok(rows.next().unwrap().is_some())
     }).await.unwrap()
 }
 
@@ -25,11 +152,7 @@ pub async fn check_user_name(connection: &Connection, name: String) -> bool {
     connection.call(move |conn| {
         let mut statement = conn.prepare(query).unwrap();
         let mut rows = statement.query([name]).unwrap();
-        if let Some(_) = rows.next().unwrap() {
-            Ok(true)
-        } else {
-            Ok(false)
-        }
+        Ok(rows.next().unwrap().is_some())
     }).await.unwrap()
 }
 
@@ -38,11 +161,7 @@ pub async fn check_post(connection: &Connection, id: i64) -> bool {
     connection.call(move |conn| {
         let mut statement = conn.prepare(query).unwrap();
         let mut rows = statement.query([id]).unwrap();
-        if let Some(_) = rows.next().unwrap() {
-            Ok(true)
-        } else {
-            Ok(false)
-        }
+        Ok(rows.next().unwrap().is_some())
     }).await.unwrap()
 }
 
@@ -51,11 +170,7 @@ pub async fn check_image(connection: &Connection, id: i64) -> bool {
     connection.call(move |conn| {
         let mut statement = conn.prepare(query).unwrap();
         let mut rows = statement.query([id]).unwrap();
-        if let Some(_) = rows.next().unwrap() {
-            Ok(true)
-        } else {
-            Ok(false)
-        }
+        Ok(rows.next().unwrap().is_some())
     }).await.unwrap()
 }
 
@@ -64,33 +179,128 @@ pub async fn check_post_image(connection: &Connection, image_id: i64, post_id: i
     connection.call(move |conn| {
         let mut statement = conn.prepare(query).unwrap();
         let mut rows = statement.query([image_id, post_id]).unwrap();
-        if let Some(_) = rows.next().unwrap() {
-            Ok(true)
-        } else {
-            Ok(false)
-        }
+        Ok(rows.next().unwrap().is_some())
     }).await.unwrap()
 }
 
 
+// bany moga na siebie nachodzic (np. po edycji), liczy sie kazdy aktywny pelny ban,
+// lzejsze kary sprawdza get_sanction
 pub async fn check_banned(connection: &Connection, user_id: i64) -> bool {
-    let query = "SELECT is_active, expires_on FROM bans WHERE user_id = ? ORDER BY given_on DESC LIMIT 1";
-
     if !check_user_id(connection, user_id).await {
         return true;
     }
+    get_active_ban(connection, user_id).await.is_some()
+}
+
+// najmocniejsza aktywna kara, nieistniejacy uzytkownik traktowany jak zbanowany
+pub async fn get_sanction(connection: &Connection, user_id: i64) -> Sanction {
+    if !check_user_id(connection, user_id).await {
+        return Sanction::Ban;
+    }
+    let query = "SELECT level FROM bans WHERE user_id = ? AND is_active = 1 AND expires_on > ?";
     let timestamp = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap().as_secs() as i64;
-    let is_banned = connection.call(move |conn| {
+    let levels = connection.call(move |conn| {
         let mut statement = conn.prepare(query).unwrap();
-        let mut rows = statement.query(params![user_id]).unwrap();
+        let mut rows = statement.query(params![user_id, timestamp]).unwrap();
+        let mut level_vec: Vec<String> = Vec::new();
+        while let Ok(Some(row)) = rows.next() {
+            level_vec.push(row.get(0).unwrap());
+        }
+        Ok(level_vec)
+    }).await.unwrap();
+
+    levels
+        .iter()
+        .filter_map(|level| Sanction::from_level(level))
+        .max()
+        .unwrap_or(Sanction::None)
+}
+
+// warunek do WHERE chowajacy tresci ukaranych autorow: pelny ban dla wszystkich,
+// shadow dla wszystkich poza samym autorem, read_only niczego nie chowa
+pub fn sanctioned_authors_filter(column: &str, viewer_id: i64) -> String {
+    let timestamp = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap().as_secs() as i64;
+    format!(
+        "{} NOT IN (SELECT user_id FROM bans WHERE is_active = 1 AND expires_on > {} \
+        AND (level = 'ban' OR (level = 'shadow' AND user_id != {})))",
+        column, timestamp, viewer_id
+    )
+}
+
+// wiersz z "SELECT bans.*, users.user_name FROM bans LEFT JOIN users ..."
+pub fn ban_from_row(row: &Row) -> Ban {
+    Ban {
+        ban_id: row.get("ban_id").unwrap(),
+        user_id: row.get("user_id").unwrap(),
+        user_name: row.get("user_name").unwrap_or_default(),
+        given_on: row.get("given_on").unwrap(),
+        expires_on: row.get("expires_on").unwrap(),
+        ban_message: row.get("ban_message").unwrap(),
+        level: row.get("level").unwrap(),
+        is_active: row.get::<_, i64>("is_active").unwrap() == 1,
+    }
+}
+
+// aktywny pelny ban ktory trwa najdluzej
+pub async fn get_active_ban(connection: &Connection, user_id: i64) -> Option<Ban> {
+    let query = "
+        SELECT bans.*, users.user_name FROM bans
+        LEFT JOIN users ON users.user_id=bans.user_id
+        WHERE bans.user_id = ? AND bans.is_active = 1 AND bans.expires_on > ? AND bans.level = 'ban'
+        ORDER BY bans.expires_on DESC LIMIT 1";
+    let timestamp = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap().as_secs() as i64;
+    connection.call(move |conn| {
+        let mut statement = conn.prepare(query).unwrap();
+        let mut rows = statement.query(params![user_id, timestamp]).unwrap();
+        if let Ok(Some(row)) = rows.next() {
+            Ok(Some(ban_from_row(row)))
+        } else {
+            Ok(None)
+        }
+    }).await.unwrap()
+}
+
+pub async fn get_ban_db(connection: &Connection, ban_id: i64) -> Option<Ban> {
+    let query = "
+        SELECT bans.*, users.user_name FROM bans
+        LEFT JOIN users ON users.user_id=bans.user_id
+        WHERE bans.ban_id = ?";
+    connection.call(move |conn| {
+        let mut statement = conn.prepare(query).unwrap();
+        let mut rows = statement.query(params![ban_id]).unwrap();
         if let Ok(Some(row)) = rows.next() {
-            Ok(row.get::<_, i64>(0).unwrap() == 1 && row.get::<_, i64>(1).unwrap() > timestamp)
+            Ok(Some(ban_from_row(row)))
         } else {
-            Ok(false)
+            Ok(None)
         }
+    }).await.unwrap()
+}
+
+pub async fn add_ban_db(connection: &Connection, user_id: i64, ban_length: i64, ban_message: String, level: String) -> i64 {
+    let query = "INSERT INTO bans (user_id, given_on, expires_on, ban_message, level, is_active) VALUES (?, ?, ?, ?, ?, 1)";
+    let timestamp = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap().as_secs() as i64;
+    let expiration = timestamp + ban_length;
+    let log_level = level.clone();
+    let ban_id = connection.call(move |conn| {
+        let mut statement = conn.prepare(query).unwrap();
+        statement.execute(params![user_id, timestamp, expiration, ban_message, level]).unwrap();
+        Ok(conn.last_insert_rowid())
+    }).await.unwrap();
+
+    info!("User {} sanctioned ({}) until {}", user_id, log_level, expiration);
+    ban_id
+}
+
+pub async fn edit_ban_db(connection: &Connection, ban_id: i64, expires_on: i64, ban_message: String) {
+    let query = "UPDATE bans SET expires_on = ?, ban_message = ? WHERE ban_id = ?";
+    connection.call(move |conn| {
+        let mut statement = conn.prepare(query).unwrap();
+        statement.execute(params![expires_on, ban_message, ban_id]).unwrap();
+        Ok(0)
     }).await.unwrap();
 
-    is_banned
+    info!("Ban {} now expires on {}", ban_id, expires_on);
 }
 
 pub async fn purge_data(connection: &Connection, user_id: i64) {
@@ -105,53 +315,140 @@ pub async fn purge_data(connection: &Connection, user_id: i64) {
         Ok(post_id_vec)
     }).await.unwrap();
 
-    let post_tag_delete_query = "DELETE FROM posts_tags WHERE post_id = ?";
-    let comment_delete_query = "DELETE FROM comments WHERE post_id = ?";
     for post_id in post_ids.iter() {
-        let post_id = *post_id;
-        connection.call(move |conn| {
-            let mut statement = conn.prepare(post_tag_delete_query).unwrap();
-            statement.execute(params![post_id]).unwrap();
-            Ok(0)
-        }).await.unwrap();
-        connection.call(move |conn| {
-            let mut statement = conn.prepare(comment_delete_query).unwrap();
-            statement.execute(params![post_id]).unwrap();
-            Ok(0)
-        }).await.unwrap();
+        delete_post_db(connection, *post_id).await;
     }
     
-    let find_posts_query = "SELECT post_id FROM likes WHERE user_id = ?";
-    let like_post_ids = connection.call(move |conn| {
-        let mut statement = conn.prepare(find_posts_query).unwrap();
-        let mut rows = statement.query(params![user_id]).unwrap();
-        let mut post_id_vec: Vec<i64> = Vec::new();
-        while let Ok(Some(row)) = rows.next() {
-            post_id_vec.push(row.get(0).unwrap());
+    let find_reactions_query = "SELECT DISTINCT target_type, target_id FROM reactions WHERE user_id = ?";
+    connection.call(move |conn| {
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate).unwrap();
+        let targets: Vec<(String, i64)> = {
+            let mut statement = tx.prepare(find_reactions_query).unwrap();
+            let mut rows = statement.query(params![user_id]).unwrap();
+            let mut target_vec = Vec::new();
+            while let Ok(Some(row)) = rows.next() {
+                target_vec.push((row.get(0).unwrap(), row.get(1).unwrap()));
+            }
+            target_vec
+        };
+        tx.execute("DELETE FROM reactions WHERE user_id = ?", params![user_id]).unwrap();
+        for (target_type, target_id) in targets.iter() {
+            tx.execute(&reaction_counts_update(target_type), params![target_id]).unwrap();
         }
-        Ok(post_id_vec)
+        tx.commit().unwrap();
+        Ok(0)
     }).await.unwrap();
 
-    let post_like_delete_query = "UPDATE posts SET likes=likes-1 WHERE post_id = ?";
-    for post_id in like_post_ids.iter() {
-        let post_id = *post_id;
-        connection.call(move |conn| {
-            let mut statement = conn.prepare(post_like_delete_query).unwrap();
-            statement.execute(params![post_id]).unwrap();
-            Ok(0)
-        }).await.unwrap();
-    };
+    let post_delete_query = "DELETE FROM posts WHERE user_id = ?";
+    connection.call(move |conn| {
+        let mut statement = conn.prepare(post_delete_query).unwrap();
+        statement.execute(params![user_id]).unwrap();
+        Ok(0)
+    }).await.unwrap();
     
-    let likes_delete_query = "DELETE FROM likes WHERE user_id = ?";
+    let refresh_delete_query = "DELETE FROM refresh_tokens WHERE user_id = ?";
     connection.call(move |conn| {
-        let mut statement = conn.prepare(likes_delete_query).unwrap();
+        let mut statement = conn.prepare(refresh_delete_query).unwrap();
         statement.execute(params![user_id]).unwrap();
         Ok(0)
     }).await.unwrap();
 
-    let post_delete_query = "DELETE FROM posts WHERE user_id = ?";
+    let follows_delete_query = "DELETE FROM follows WHERE follower_id = ? OR followed_id = ?";
     connection.call(move |conn| {
-        let mut statement = conn.prepare(post_delete_query).unwrap();
+        let mut statement = conn.prepare(follows_delete_query).unwrap();
+        statement.execute(params![user_id, user_id]).unwrap();
+        Ok(0)
+    }).await.unwrap();
+
+    let blocks_delete_query = "DELETE FROM blocks WHERE blocker_id = ? OR blocked_id = ?";
+    connection.call(move |conn| {
+        let mut statement = conn.prepare(blocks_delete_query).unwrap();
+        statement.execute(params![user_id, user_id]).unwrap();
+        Ok(0)
+    }).await.unwrap();
+
+    let mutes_delete_query = "DELETE FROM mutes WHERE muter_id = ? OR muted_id = ?";
+    connection.call(move |conn| {
+        let mut statement = conn.prepare(mutes_delete_query).unwrap();
+        statement.execute(params![user_id, user_id]).unwrap();
+        Ok(0)
+    }).await.unwrap();
+
+    connection.call(move |conn| {
+        let tx = conn.transaction().unwrap();
+        tx.execute("
+            UPDATE posts SET repost_count = repost_count - 1 WHERE post_id IN
+            (SELECT post_id FROM reposts WHERE user_id = ?)", params![user_id]).unwrap();
+        tx.execute("DELETE FROM reposts WHERE user_id = ?", params![user_id]).unwrap();
+        tx.commit().unwrap();
+        Ok(0)
+    }).await.unwrap();
+
+    connection.call(move |conn| {
+        let tx = conn.transaction().unwrap();
+        tx.execute("
+            UPDATE poll_options SET vote_count = vote_count - 1 WHERE option_id IN
+            (SELECT option_id FROM poll_votes WHERE user_id = ?)", params![user_id]).unwrap();
+        tx.execute("DELETE FROM poll_votes WHERE user_id = ?", params![user_id]).unwrap();
+        tx.commit().unwrap();
+        Ok(0)
+    }).await.unwrap();
+
+    connection.call(move |conn| {
+        let tx = conn.transaction().unwrap();
+        tx.execute("
+            DELETE FROM collections_posts WHERE collection_id IN
+            (SELECT collection_id FROM collections WHERE user_id = ?)", params![user_id]).unwrap();
+        tx.execute("DELETE FROM collections WHERE user_id = ?", params![user_id]).unwrap();
+        tx.commit().unwrap();
+        Ok(0)
+    }).await.unwrap();
+
+    let resets_delete_query = "DELETE FROM password_resets WHERE user_id = ?";
+    connection.call(move |conn| {
+        let mut statement = conn.prepare(resets_delete_query).unwrap();
+        statement.execute(params![user_id]).unwrap();
+        Ok(0)
+    }).await.unwrap();
+
+    let mentions_delete_query = "DELETE FROM mentions WHERE user_id = ? OR author_id = ?";
+    connection.call(move |conn| {
+        let mut statement = conn.prepare(mentions_delete_query).unwrap();
+        statement.execute(params![user_id, user_id]).unwrap();
+        Ok(0)
+    }).await.unwrap();
+
+    // zbiorcze powiadomienia innych zostaja, o ile zostal w nich jeszcze ktos poza tym userem
+    connection.call(move |conn| {
+        let tx = conn.transaction().unwrap();
+        tx.execute("
+            DELETE FROM notification_actors WHERE actor_id = ? OR notification_id IN
+            (SELECT notification_id FROM notifications WHERE user_id = ?)", params![user_id, user_id]).unwrap();
+        tx.execute("
+            DELETE FROM notifications WHERE notification_id NOT IN
+            (SELECT notification_id FROM notification_actors)", []).unwrap();
+        tx.commit().unwrap();
+        Ok(0)
+    }).await.unwrap();
+
+    // rozmowy w ktorych nikt nie zostal znikaja razem z wiadomosciami
+    connection.call(move |conn| {
+        let tx = conn.transaction().unwrap();
+        tx.execute("DELETE FROM messages WHERE user_id = ?", params![user_id]).unwrap();
+        tx.execute("DELETE FROM conversation_members WHERE user_id = ?", params![user_id]).unwrap();
+        tx.execute("
+            DELETE FROM messages WHERE conversation_id NOT IN
+            (SELECT conversation_id FROM conversation_members)", []).unwrap();
+        tx.execute("
+            DELETE FROM conversations WHERE conversation_id NOT IN
+            (SELECT conversation_id FROM conversation_members)", []).unwrap();
+        tx.commit().unwrap();
+        Ok(0)
+    }).await.unwrap();
+
+    let sessions_delete_query = "DELETE FROM sessions WHERE user_id = ?";
+    connection.call(move |conn| {
+        let mut statement = conn.prepare(sessions_delete_query).unwrap();
         statement.execute(params![user_id]).unwrap();
         Ok(0)
     }).await.unwrap();
@@ -164,36 +461,49 @@ pub async fn purge_data(connection: &Connection, user_id: i64) {
     }).await.unwrap();
 }
 
-pub async fn get_next_post_id(connection: &Connection) -> Result<i64, &str> {
-    let query = "SELECT MAX(post_id) FROM posts";
+pub async fn delete_post_db(connection: &Connection, post_id: i64) {
     connection.call(move |conn| {
-        let mut statement = conn.prepare(query).unwrap();
-        let mut rows = statement.query([]).unwrap();
-        if let Some(val) = rows.next().unwrap() {
-            let ret = match val.get::<_, i64>(0) {
-                Ok(val) => {val + 1},
-                Err(_) => {0}
-            };
-            Ok(Ok(ret))
-        } else {
-            Ok(Err("Failed to count posts"))
-        }
-    }).await.unwrap()
+        let tx = conn.transaction().unwrap();
+        tx.execute("DELETE FROM posts_tags WHERE post_id = ?", params![post_id]).unwrap();
+        tx.execute("DELETE FROM posts_images WHERE post_id = ?", params![post_id]).unwrap();
+        tx.execute("
+            DELETE FROM reactions WHERE target_type = 'comment' AND target_id IN
+            (SELECT comment_id FROM comments WHERE post_id = ?)", params![post_id]).unwrap();
+        tx.execute("DELETE FROM comments WHERE post_id = ?", params![post_id]).unwrap();
+        tx.execute("DELETE FROM reactions WHERE target_type = 'post' AND target_id = ?", params![post_id]).unwrap();
+        tx.execute("DELETE FROM collections_posts WHERE post_id = ?", params![post_id]).unwrap();
+        tx.execute("DELETE FROM reposts WHERE post_id = ?", params![post_id]).unwrap();
+        tx.execute("DELETE FROM poll_votes WHERE post_id = ?", params![post_id]).unwrap();
+        tx.execute("DELETE FROM poll_options WHERE post_id = ?", params![post_id]).unwrap();
+        tx.execute("DELETE FROM polls WHERE post_id = ?", params![post_id]).unwrap();
+        // cytaty usunietego posta zostaja, pokazuja go jako "deleted"
+        tx.execute("
+            UPDATE posts SET quote_count = quote_count - 1 WHERE post_id =
+            (SELECT quoted_post_id FROM posts WHERE post_id = ?)", params![post_id]).unwrap();
+        tx.execute("DELETE FROM post_revisions WHERE post_id = ?", params![post_id]).unwrap();
+        tx.execute("DELETE FROM mentions WHERE post_id = ?", params![post_id]).unwrap();
+        tx.execute("
+            DELETE FROM notification_actors WHERE notification_id IN
+            (SELECT notification_id FROM notifications WHERE post_id = ?)", params![post_id]).unwrap();
+        tx.execute("DELETE FROM notifications WHERE post_id = ?", params![post_id]).unwrap();
+        tx.execute("DELETE FROM posts WHERE post_id = ?", params![post_id]).unwrap();
+        tx.commit().unwrap();
+        Ok(0)
+    }).await.unwrap();
+
+    info!("Deleted post {}", post_id);
 }
 
-pub async fn get_next_comment_id(connection: &Connection, post_id: i64) -> Result<i64, &str> {
-    let query = "SELECT COUNT(comment_id) FROM comments WHERE post_id = ?";
+// (post_id, user_id) komentarza albo -1 jesli nie istnieje
+pub async fn get_comment_post_user(connection: &Connection, comment_id: i64) -> (i64, i64) {
+    let query = "SELECT post_id, user_id FROM comments WHERE comment_id = ?";
     connection.call(move |conn| {
         let mut statement = conn.prepare(query).unwrap();
-        let mut rows = statement.query(params![post_id]).unwrap();
+        let mut rows = statement.query([comment_id]).unwrap();
         if let Some(val) = rows.next().unwrap() {
-            let ret = match val.get::<_, i64>(0) {
-                Ok(val) => {val + 1},
-                Err(_) => {0}
-            };
-            Ok(Ok(ret))
+            Ok((val.get(0).unwrap(), val.get(1).unwrap()))
         } else {
-            Ok(Err("Failed to count posts"))
+            Ok((-1, -1))
         }
     }).await.unwrap()
 }
@@ -263,6 +573,18 @@ pub async fn get_tag_by_name(connection: &Connection, name: String) -> Result<i6
     }).await.unwrap()
 }
 
+pub async fn get_reaction_counts(connection: &Connection, target_type: String, target_id: i64) -> HashMap<String, i64> {
+    let query = match target_type.as_str() {
+        "post" => "SELECT reaction_counts FROM posts WHERE post_id = ?",
+        _ => "SELECT reaction_counts FROM comments WHERE comment_id = ?",
+    };
+    connection.call(move |conn| {
+        let mut statement = conn.prepare(query).unwrap();
+        let mut rows = statement.query(params![target_id]).unwrap();
+        Ok(rows.next().unwrap().map(reaction_counts_from_row).unwrap_or_default())
+    }).await.unwrap()
+}
+
 pub async fn get_user_from_post(connection: &Connection, id: i64) -> i64 {
     let query = "SELECT user_id FROM posts WHERE post_id = ?";
     connection.call(move |conn| {
@@ -296,64 +618,333 @@ pub async fn add_tag_db(connection: &Connection, name: String) -> i64 {
     tag_count
 }
 
-pub async fn add_post_db(connection: &Connection, post: Post, tags: Vec<String>) {
+// "#Rust  Lang " -> "rust_lang", None jesli nic nie zostalo
+pub fn normalize_tag(tag: &str) -> Option<String> {
+    let tag = tag.trim().trim_start_matches('#').to_lowercase();
+    let tag: String = tag
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join("_")
+        .chars()
+        .take(64)
+        .collect();
+    if tag.is_empty() {
+        None
+    } else {
+        Some(tag)
+    }
+}
+
+// "#tag" liczy sie tylko na poczatku slowa, zeby nie lapac np. "C#"
+pub fn extract_hashtags(body: &str) -> Vec<String> {
+    let chars: Vec<char> = body.chars().collect();
+    let mut hashtags: Vec<String> = Vec::new();
+    for i in 0..chars.len() {
+        if chars[i] != '#' || (i > 0 && !chars[i - 1].is_whitespace()) {
+            continue;
+        }
+        let tag: String = chars[i + 1..]
+            .iter()
+            .take_while(|c| c.is_alphanumeric() || **c == '_')
+            .collect();
+        if let Some(tag) = normalize_tag(&tag) {
+            if !hashtags.contains(&tag) {
+                hashtags.push(tag);
+            }
+        }
+    }
+    hashtags
+}
+
+// user_name moze miec wszystko oprocz spacji, wiec bierzemy cale slowo bez interpunkcji na koncu
+pub fn extract_mentions(body: &str) -> Vec<String> {
+    let mut mentions: Vec<String> = Vec::new();
+    for word in body.split_whitespace() {
+        if let Some(name) = word.strip_prefix('@') {
+            let name = name.trim_end_matches(['.', ',', '!', '?', ':', ';', ')', '"', '\'']);
+            if !name.is_empty() && !mentions.iter().any(|m| m == name) {
+                mentions.push(name.to_string());
+            }
+        }
+    }
+    mentions
+}
+
+// nadpisuje wzmianki z danego posta (comment_id = None) albo komentarza,
+// zwraca userow wspomnianych tu po raz pierwszy (do powiadomien)
+pub async fn set_mentions_db(
+    connection: &Connection,
+    author_id: i64,
+    post_id: i64,
+    comment_id: Option<i64>,
+    body: &str
+) -> Vec<i64> {
     let time_since_epoch: i64 = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap().as_secs() as i64;
+    let names = extract_mentions(body);
 
-    let query = "INSERT INTO posts VALUES (?, ?, ?, ?, ?)";
+    let previous_query = "SELECT user_id FROM mentions WHERE post_id = ? AND comment_id IS ?";
+    let delete_query = "DELETE FROM mentions WHERE post_id = ? AND comment_id IS ?";
+    // kto zablokowal autora, ten nie dostaje od niego wzmianek
+    let user_query = "
+        SELECT user_id FROM users WHERE user_name = ?
+        AND user_id NOT IN (SELECT blocker_id FROM blocks WHERE blocked_id = ?)";
+    let insert_query = "
+        INSERT INTO mentions (user_id, author_id, post_id, comment_id, date)
+        VALUES (?, ?, ?, ?, ?)";
     connection.call(move |conn| {
-        let mut statement = conn.prepare(query).unwrap();
-        statement.execute(params![post.post_id, post.user_id, time_since_epoch, post.body, 0]).unwrap();
-        Ok(0)
-    }).await.unwrap();
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate).unwrap();
+        let previous: Vec<i64> = {
+            let mut statement = tx.prepare(previous_query).unwrap();
+            let mut rows = statement.query(params![post_id, comment_id]).unwrap();
+            let mut user_vec: Vec<i64> = Vec::new();
+            while let Ok(Some(row)) = rows.next() {
+                user_vec.push(row.get(0).unwrap());
+            }
+            user_vec
+        };
+        tx.execute(delete_query, params![post_id, comment_id]).unwrap();
+        let mut mentioned: Vec<i64> = Vec::new();
+        for name in names.iter() {
+            let user_id: Option<i64> = {
+                let mut statement = tx.prepare(user_query).unwrap();
+                let mut rows = statement.query(params![name, author_id]).unwrap();
+                rows.next().unwrap().map(|row| row.get(0).unwrap())
+            };
+            if let Some(user_id) = user_id {
+                if user_id != author_id && !mentioned.contains(&user_id) {
+                    mentioned.push(user_id);
+                }
+            }
+        }
+        for user_id in mentioned.iter() {
+            tx.execute(insert_query, params![user_id, author_id, post_id, comment_id, time_since_epoch]).unwrap();
+        }
+        tx.commit().unwrap();
+        mentioned.retain(|user_id| !previous.contains(user_id));
+        Ok(mentioned)
+    }).await.unwrap()
+}
 
-    for tag in tags.iter() {
+pub async fn add_post_tags_db(connection: &Connection, post_id: i64, tags: Vec<String>) {
+    let mut normalized: Vec<String> = Vec::new();
+    for tag in tags.iter().filter_map(|tag| normalize_tag(tag)) {
+        if !normalized.contains(&tag) {
+            normalized.push(tag);
+        }
+    }
+    for tag in normalized.iter() {
         match get_tag_by_name(connection, tag.clone()).await {
             Ok(id) => {
-                add_post_tag_db(connection, post.post_id, id).await;
+                add_post_tag_db(connection, post_id, id).await;
             },
             Err(_) => {
                 let id = add_tag_db(connection, tag.clone()).await; 
-                add_post_tag_db(connection, post.post_id, id).await;
+                add_post_tag_db(connection, post_id, id).await;
             }
         }
     }
+}
+
+// zwraca (post_id, nowo wspomniani userzy), post.post_id jest ignorowane -
+// id nadaje SQLite (AUTOINCREMENT), wiec id usunietego posta nigdy nie wraca
+pub async fn add_post_db(connection: &Connection, post: Post, mut tags: Vec<String>) -> (i64, Vec<i64>) {
+    let time_since_epoch: i64 = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap().as_secs() as i64;
+
+    let body = post.body.clone();
+    let user_id = post.user_id;
+    let query = "INSERT INTO posts (user_id, date, body, likes, quoted_post_id) VALUES (?, ?, ?, ?, ?)";
+    let quote_count_query = "UPDATE posts SET quote_count = quote_count + 1 WHERE post_id = ?";
+    let post_id = connection.call(move |conn| {
+        let tx = conn.transaction().unwrap();
+        tx.execute(query, params![post.user_id, time_since_epoch, post.body, 0, post.quoted_post_id]).unwrap();
+        let post_id = tx.last_insert_rowid();
+        if let Some(quoted_post_id) = post.quoted_post_id {
+            tx.execute(quote_count_query, params![quoted_post_id]).unwrap();
+        }
+        tx.commit().unwrap();
+        Ok(post_id)
+    }).await.unwrap();
+
+    tags.extend(extract_hashtags(&body));
+    add_post_tags_db(connection, post_id, tags).await;
+    let new_mentions = set_mentions_db(connection, user_id, post_id, None, &body).await;
 
     info!(
         "Added post {} for user {}", 
-        post.post_id, 
-        post.user_id,
+        post_id, 
+        user_id,
     );
+    (post_id, new_mentions)
+}
+
+// poprzednia wersja (tresc + tagi) laduje w post_revisions, potem nadpisujemy post
+pub async fn edit_post_db(connection: &Connection, post_id: i64, body: String, mut tags: Vec<String>) -> Vec<i64> {
+    let time_since_epoch: i64 = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap().as_secs() as i64;
+
+    let old_tags_query = "
+        SELECT tags.tag_name
+        FROM posts_tags
+        JOIN tags ON tags.tag_id=posts_tags.tag_id
+        WHERE posts_tags.post_id = ?";
+    let revision_query = "
+        INSERT INTO post_revisions (post_id, body, tags, date)
+        SELECT post_id, body, ?, ? FROM posts WHERE post_id = ?";
+    let update_query = "UPDATE posts SET body = ?, edited_at = ? WHERE post_id = ?";
+    let tags_delete_query = "DELETE FROM posts_tags WHERE post_id = ?";
+
+    tags.extend(extract_hashtags(&body));
+    let author_id = get_user_from_post(connection, post_id).await;
+    let post_body = body.clone();
+
+    connection.call(move |conn| {
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate).unwrap();
+        let old_tags = {
+            let mut statement = tx.prepare(old_tags_query).unwrap();
+            let mut rows = statement.query(params![post_id]).unwrap();
+            let mut tag_vec: Vec<String> = Vec::new();
+            while let Ok(Some(row)) = rows.next() {
+                tag_vec.push(row.get(0).unwrap());
+            }
+            serde_json::to_string(&tag_vec).unwrap()
+        };
+        tx.execute(revision_query, params![old_tags, time_since_epoch, post_id]).unwrap();
+        tx.execute(update_query, params![body, time_since_epoch, post_id]).unwrap();
+        tx.execute(tags_delete_query, params![post_id]).unwrap();
+        tx.commit().unwrap();
+        Ok(0)
+    }).await.unwrap();
+
+    add_post_tags_db(connection, post_id, tags).await;
+    let new_mentions = set_mentions_db(connection, author_id, post_id, None, &post_body).await;
+
+    info!("Edited post {}", post_id);
+    new_mentions
 }
 
+// id nadaje sqlite, zwraca (comment_id, nowo oznaczeni uzytkownicy)
 pub async fn add_comment_db(
     connection: &Connection, 
     post_id: i64,
-    comment_id: i64,
     user_id: i64,
-    body: String
-) {
+    body: String,
+    parent_comment_id: Option<i64>
+) -> (i64, Vec<i64>) {
     let time_since_epoch: i64 = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap().as_secs() as i64;
 
-    let query = "INSERT INTO comments VALUES (?, ?, ?, ?, ?)";
-    connection.call(move |conn| {
+    let query = "INSERT INTO comments (post_id, user_id, body, date, parent_comment_id) VALUES (?, ?, ?, ?, ?)";
+    let comment_body = body.clone();
+    let comment_id = connection.call(move |conn| {
         let mut statement = conn.prepare(query).unwrap();
-        statement.execute(params![post_id, comment_id, user_id, body, time_since_epoch]).unwrap();
-        Ok(0)
+        statement.execute(params![post_id, user_id, body, time_since_epoch, parent_comment_id]).unwrap();
+        Ok(conn.last_insert_rowid())
     }).await.unwrap();
+    // hashtagi z komentarzy nie sa tagami posta, tagi ustala tylko autor
+    let new_mentions = set_mentions_db(connection, user_id, post_id, Some(comment_id), &comment_body).await;
     info!(
         "Added comment {} for post {} for user {}", 
         comment_id,
         post_id, 
         user_id,
     );
+    (comment_id, new_mentions)
+}
+
+pub async fn edit_comment_db(connection: &Connection, comment_id: i64, body: String) -> Vec<i64> {
+    let time_since_epoch: i64 = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap().as_secs() as i64;
+
+    let query = "UPDATE comments SET body = ?, edited_at = ? WHERE comment_id = ?";
+    let comment_body = body.clone();
+    connection.call(move |conn| {
+        let mut statement = conn.prepare(query).unwrap();
+        statement.execute(params![body, time_since_epoch, comment_id]).unwrap();
+        Ok(0)
+    }).await.unwrap();
+
+    let (post_id, user_id) = get_comment_post_user(connection, comment_id).await;
+    let new_mentions = set_mentions_db(connection, user_id, post_id, Some(comment_id), &comment_body).await;
+
+    info!("Edited comment {}", comment_id);
+    new_mentions
+}
+
+// usuwa komentarz razem z cala galezia odpowiedzi pod nim
+pub async fn delete_comment_db(connection: &Connection, comment_id: i64) {
+    let subtree = "
+        WITH RECURSIVE subtree(comment_id) AS (
+            SELECT ?
+            UNION ALL
+            SELECT comments.comment_id FROM comments
+            JOIN subtree ON comments.parent_comment_id=subtree.comment_id
+        )";
+    let mentions_query = format!("{} DELETE FROM mentions WHERE comment_id IN (SELECT comment_id FROM subtree)", subtree);
+    // pojedyncze powiadomienia (reply, mention) leca razem z komentarzem, zbiorcze sa czyszczone nizej
+    let single_notifications = format!(
+        "SELECT notification_id FROM notifications WHERE comment_id IN (SELECT comment_id FROM subtree) AND kind NOT IN ({})",
+        AGGREGATED_NOTIFICATIONS.map(|kind| format!("'{}'", kind)).join(", ")
+    );
+    let actors_query = format!("{} DELETE FROM notification_actors WHERE notification_id IN ({})", subtree, single_notifications);
+    let notifications_query = format!("{} DELETE FROM notifications WHERE notification_id IN ({})", subtree, single_notifications);
+    let reactions_query = format!("
+        {} DELETE FROM reactions WHERE target_type = 'comment'
+        AND target_id IN (SELECT comment_id FROM subtree)", subtree);
+    let comments_query = format!("{} DELETE FROM comments WHERE comment_id IN (SELECT comment_id FROM subtree)", subtree);
+    let post_query = "SELECT post_id FROM comments WHERE comment_id = ?";
+    // z powiadomien zbiorczych znikaja tylko osoby, ktore nie maja juz komentarza / reakcji pod tym postem
+    let comment_actors_query = "
+        DELETE FROM notification_actors WHERE notification_id IN
+        (SELECT notification_id FROM notifications WHERE post_id = ?1 AND kind = 'comment')
+        AND NOT EXISTS (SELECT 1 FROM comments WHERE comments.post_id = ?1 AND comments.user_id = notification_actors.actor_id)";
+    let reaction_actors_query = "
+        DELETE FROM notification_actors WHERE notification_id IN
+        (SELECT notification_id FROM notifications WHERE post_id = ?1 AND kind = 'reaction')
+        AND NOT EXISTS (
+            SELECT 1 FROM reactions
+            JOIN notifications ON notifications.notification_id = notification_actors.notification_id
+            WHERE reactions.user_id = notification_actors.actor_id
+            AND ((reactions.target_type = 'post' AND reactions.target_id = ?1
+                AND (SELECT user_id FROM posts WHERE post_id = ?1) = notifications.user_id)
+            OR (reactions.target_type = 'comment' AND reactions.target_id IN
+                (SELECT comment_id FROM comments WHERE comments.post_id = ?1 AND comments.user_id = notifications.user_id)))
+        )";
+    let comment_repoint_query = "
+        UPDATE notifications SET comment_id =
+        (SELECT MAX(comments.comment_id) FROM comments WHERE comments.post_id = ?1 AND comments.user_id IN
+            (SELECT actor_id FROM notification_actors WHERE notification_actors.notification_id = notifications.notification_id))
+        WHERE post_id = ?1 AND kind = 'comment' AND comment_id NOT IN (SELECT comment_id FROM comments)";
+    let reaction_repoint_query = "
+        UPDATE notifications SET comment_id = NULL
+        WHERE post_id = ?1 AND kind = 'reaction' AND comment_id NOT IN (SELECT comment_id FROM comments)";
+    let empty_notifications_query = "
+        DELETE FROM notifications WHERE post_id = ?1 AND kind IN ('comment', 'reaction')
+        AND notification_id NOT IN (SELECT notification_id FROM notification_actors)";
+    connection.call(move |conn| {
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate).unwrap();
+        let post_id: Option<i64> = tx.query_row(post_query, params![comment_id], |row| row.get(0)).ok();
+        tx.execute(&mentions_query, params![comment_id]).unwrap();
+        tx.execute(&reactions_query, params![comment_id]).unwrap();
+        tx.execute(&actors_query, params![comment_id]).unwrap();
+        tx.execute(&notifications_query, params![comment_id]).unwrap();
+        tx.execute(&comments_query, params![comment_id]).unwrap();
+        if let Some(post_id) = post_id {
+            tx.execute(comment_actors_query, params![post_id]).unwrap();
+            tx.execute(reaction_actors_query, params![post_id]).unwrap();
+            tx.execute(comment_repoint_query, params![post_id]).unwrap();
+            tx.execute(reaction_repoint_query, params![post_id]).unwrap();
+            tx.execute(empty_notifications_query, params![post_id]).unwrap();
+        }
+        tx.commit().unwrap();
+        Ok(0)
+    }).await.unwrap();
+
+    info!("Deleted comment {}", comment_id);
 }
 
-pub async fn add_user_db(connection: &Connection, request: SignupRequest) -> String {
+pub async fn add_user_db(connection: &Connection, request: SignupRequest) -> i64 {
     let user_id = max_user_id(connection).await.unwrap();
     let user_name = request.user_name.clone();
     let password = request.passwd.clone();
     let password_hash = get_hash(password);
-    let signup_query = "INSERT INTO users VALUES (:user_id, :user_name, :user_name, '', :passwd, 0, '')";
+    let signup_query = "INSERT INTO users VALUES (:user_id, :user_name, :user_name, '', :passwd, 'user', '')";
     connection.call(move |conn| {
         let mut statement = conn.prepare(signup_query).unwrap();
         statement.execute(params![user_id, request.user_name, password_hash]).unwrap();
@@ -361,95 +952,776 @@ pub async fn add_user_db(connection: &Connection, request: SignupRequest) -> Str
     }).await.unwrap();
 
     info!("User {} created with id {}", user_name, user_id);
-    get_token(user_id, 0)
+    user_id
 }
 
-pub async fn get_id_passwd_adm(connection: &Connection, user: String) -> Result<(i64, String, i64), String> {
-    let query = "SELECT passwd, user_id, is_admin FROM users WHERE user_name = ?";
+// nowa sesja = nowa rodzina refresh tokenow, zwraca (session_id, refresh_token)
+pub async fn add_session_db(connection: &Connection, config: &Config, user_id: i64, remember: bool) -> (String, String) {
+    let time_since_epoch: i64 = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap().as_secs() as i64;
+    let expiration = time_since_epoch + config.refresh_lifetime(remember);
+    let session_id = uuid::Uuid::new_v4().to_string();
+    let refresh_token = get_refresh_token();
+    let refresh_hash = hash_token(&refresh_token);
+    let session_id_cpy = session_id.clone();
+    let session_query = "INSERT INTO sessions VALUES (?, ?, ?, ?, 1, ?)";
+    let refresh_query = "INSERT INTO refresh_tokens VALUES (?, ?, ?, ?, ?, 0)";
+    connection.call(move |conn| {
+        let tx = conn.transaction().unwrap();
+        tx.execute(session_query, params![session_id_cpy, user_id, time_since_epoch, expiration, remember as i64]).unwrap();
+        tx.execute(refresh_query, params![refresh_hash, session_id_cpy, user_id, time_since_epoch, expiration]).unwrap();
+        tx.commit().unwrap();
+        Ok(0)
+    }).await.unwrap();
 
-    let ret = connection.call(move |conn| {
-        let mut statement = conn.prepare(query).unwrap();
-        let mut rows = statement.query_map(params![user], |row| { Ok((row.get(1).unwrap(), row.get(0).unwrap(), row.get(2).unwrap())) }).unwrap();
-        if let Some(row) = rows.next() {
-            Ok(row.unwrap())
-        } else {
-            Ok((-1, "".to_string(), -1))
-        }
-    }).await;
-    match ret {
-        Ok(val) => {
-            if val.0 == -1 {
-                Err("User not found".to_string())
-            } else {
-                Ok(val)
-            }
-        },
-        Err(_) => Err("Database error".to_string())
-    }
-    
+    info!("Session created for user {}", user_id);
+    (session_id, refresh_token)
 }
 
-pub async fn check_like(connection: &Connection, user_id: i64, post_id: i64) -> bool {
-    let query = "SELECT post_id FROM likes WHERE post_id = ? AND user_id = ?";
+// zuzywa refresh token i wydaje nastepny z tej samej rodziny
+// ponowne uzycie zuzytego tokena = ktos go ukradl, wiec ubijamy cala sesje
+pub async fn rotate_refresh_token_db(
+    connection: &Connection,
+    config: &Config,
+    refresh_token: String
+) -> Result<(i64, String, String, String, bool), &'static str> {
+    let time_since_epoch: i64 = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap().as_secs() as i64;
+    let refresh_hash = hash_token(&refresh_token);
+    let new_refresh_token = get_refresh_token();
+    let new_refresh_hash = hash_token(&new_refresh_token);
+    let refresh_lifetime = config.refresh_token_lifetime;
+    let short_refresh_lifetime = config.short_refresh_token_lifetime;
+    let find_query = "
+        SELECT refresh_tokens.session_id, refresh_tokens.user_id, refresh_tokens.expires_on,
+        refresh_tokens.is_used, sessions.is_active, sessions.remember, users.role
+        FROM refresh_tokens
+        JOIN sessions ON sessions.session_id=refresh_tokens.session_id
+        JOIN users ON users.user_id=refresh_tokens.user_id
+        WHERE refresh_tokens.token_hash = ?";
+    let revoke_query = "UPDATE sessions SET is_active = 0 WHERE session_id = ?";
+    let use_query = "UPDATE refresh_tokens SET is_used = 1 WHERE token_hash = ?";
+    let refresh_query = "INSERT INTO refresh_tokens VALUES (?, ?, ?, ?, ?, 0)";
+    let session_query = "UPDATE sessions SET expires_on = ? WHERE session_id = ?";
 
+    // IMMEDIATE bo najpierw czytamy a potem piszemy - przy DEFERRED rownolegly zapis
+    // konczy sie od razu SQLITE_BUSY zamiast czekania na busy_timeout
+    connection.call(move |conn| {
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate).unwrap();
+        let found = {
+            let mut statement = tx.prepare(find_query).unwrap();
+            let mut rows = statement.query(params![refresh_hash]).unwrap();
+            rows.next().unwrap().map(|row| (
+                row.get::<_, String>(0).unwrap(),
+                row.get::<_, i64>(1).unwrap(),
+                row.get::<_, i64>(2).unwrap(),
+                row.get::<_, i64>(3).unwrap() == 1,
+                row.get::<_, i64>(4).unwrap() == 1,
+                row.get::<_, i64>(5).unwrap() == 1,
+                row.get::<_, String>(6).unwrap(),
+            ))
+        };
+        let (session_id, user_id, expires_on, is_used, is_active, remember, role) = match found {
+            Some(val) => val,
+            None => return Ok(Err("Refresh token not found"))
+        };
+        if is_used {
+            tx.execute(revoke_query, params![session_id]).unwrap();
+            tx.commit().unwrap();
+            return Ok(Err("Refresh token reused"));
+        }
+        if !is_active || expires_on <= time_since_epoch {
+            return Ok(Err("Session expired"));
+        }
+
+        let expiration = time_since_epoch + if remember { refresh_lifetime } else { short_refresh_lifetime };
+        tx.execute(use_query, params![refresh_hash]).unwrap();
+        tx.execute(refresh_query, params![new_refresh_hash, session_id, user_id, time_since_epoch, expiration]).unwrap();
+        tx.execute(session_query, params![expiration, session_id]).unwrap();
+        tx.commit().unwrap();
+        Ok(Ok((user_id, role, session_id, new_refresh_token, remember)))
+    }).await.unwrap()
+}
+
+pub async fn check_session(connection: &Connection, session_id: String, user_id: i64) -> bool {
+    let time_since_epoch: i64 = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap().as_secs() as i64;
+    let query = "SELECT session_id FROM sessions WHERE session_id = ? AND user_id = ? AND is_active = 1 AND expires_on > ?";
+    connection.call(move |conn| {
+        let mut statement = conn.prepare(query).unwrap();
+        let mut rows = statement.query(params![session_id, user_id, time_since_epoch]).unwrap();
+        Ok(rows.next().unwrap().is_some())
+    }).await.unwrap()
+}
+
+// zwraca false jesli sesja nie istnieje albo nie nalezy do usera
+pub async fn revoke_session_db(connection: &Connection, session_id: String, user_id: i64) -> bool {
+    let query = "UPDATE sessions SET is_active = 0 WHERE session_id = ? AND user_id = ? AND is_active = 1";
+    let changed = connection.call(move |conn| {
+        let mut statement = conn.prepare(query).unwrap();
+        Ok(statement.execute(params![session_id, user_id]).unwrap())
+    }).await.unwrap();
+    changed > 0
+}
+
+// sesja, do ktorej nalezy refresh token (takze juz zuzyty)
+pub async fn revoke_session_by_refresh_db(connection: &Connection, refresh_token: String) -> bool {
+    let refresh_hash = hash_token(&refresh_token);
+    let query = "
+        UPDATE sessions SET is_active = 0 WHERE is_active = 1 AND session_id =
+        (SELECT session_id FROM refresh_tokens WHERE token_hash = ?)";
+    let changed = connection.call(move |conn| {
+        let mut statement = conn.prepare(query).unwrap();
+        Ok(statement.execute(params![refresh_hash]).unwrap())
+    }).await.unwrap();
+    changed > 0
+}
+
+pub async fn get_user_role(connection: &Connection, user_id: i64) -> Option<String> {
+    let query = "SELECT role FROM users WHERE user_id = ?";
     connection.call(move |conn| {
         let mut statement = conn.prepare(query).unwrap();
-        let mut rows = statement.query(params![post_id, user_id]).unwrap();
-        if let Ok(Some(_)) = rows.next() {
-            Ok(true)
+        let mut rows = statement.query(params![user_id]).unwrap();
+        if let Ok(Some(row)) = rows.next() {
+            Ok(Some(row.get(0).unwrap()))
         } else {
-            Ok(false)
+            Ok(None)
         }
     }).await.unwrap()
 }
 
-pub async fn add_like_db(connection: &Connection, user_id: i64, post_id: i64) -> bool {
-    let query = "INSERT INTO likes VALUES (?, ?)"; 
-    let update_query = "UPDATE posts SET likes=likes+1 WHERE post_id = ?";
+pub async fn set_user_role_db(connection: &Connection, user_id: i64, role: String) {
+    let query = "UPDATE users SET role = ? WHERE user_id = ?";
+    info!("User {} now has role {}", user_id, role);
+    connection.call(move |conn| {
+        let mut statement = conn.prepare(query).unwrap();
+        statement.execute(params![role, user_id]).unwrap();
+        Ok(0)
+    }).await.unwrap();
+}
+
+pub async fn revoke_user_sessions_db(connection: &Connection, user_id: i64) {
+    let query = "UPDATE sessions SET is_active = 0 WHERE user_id = ? AND is_active = 1";
+    connection.call(move |conn| {
+        let mut statement = conn.prepare(query).unwrap();
+        statement.execute(params![user_id]).unwrap();
+        Ok(0)
+    }).await.unwrap();
+
+    info!("Revoked all sessions of user {}", user_id);
+}
+
+pub async fn get_passwd(connection: &Connection, user_id: i64) -> Result<String, &str> {
+    let query = "SELECT passwd FROM users WHERE user_id = ?";
+    connection.call(move |conn| {
+        let mut statement = conn.prepare(query).unwrap();
+        let mut rows = statement.query(params![user_id]).unwrap();
+        if let Some(row) = rows.next().unwrap() {
+            Ok(Ok(row.get(0).unwrap()))
+        } else {
+            Ok(Err("User not found"))
+        }
+    }).await.unwrap()
+}
+
+pub async fn set_passwd_db(connection: &Connection, user_id: i64, password: String) {
+    let password_hash = get_hash(password);
+    let query = "UPDATE users SET passwd = ? WHERE user_id = ?";
+    connection.call(move |conn| {
+        let mut statement = conn.prepare(query).unwrap();
+        statement.execute(params![password_hash, user_id]).unwrap();
+        Ok(0)
+    }).await.unwrap();
+
+    info!("Password changed for user {}", user_id);
+}
+
+pub async fn add_password_reset_db(connection: &Connection, config: &Config, user_id: i64) -> String {
+    let time_since_epoch: i64 = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap().as_secs() as i64;
+    let expiration = time_since_epoch + config.password_reset_lifetime;
+    let reset_code = get_reset_code();
+    let reset_hash = hash_token(&reset_code);
+    // stare, niewykorzystane kody przestaja dzialac
+    let invalidate_query = "UPDATE password_resets SET is_used = 1 WHERE user_id = ? AND is_used = 0";
+    let query = "INSERT INTO password_resets VALUES (?, ?, ?, ?, 0)";
+    connection.call(move |conn| {
+        let tx = conn.transaction().unwrap();
+        tx.execute(invalidate_query, params![user_id]).unwrap();
+        tx.execute(query, params![reset_hash, user_id, time_since_epoch, expiration]).unwrap();
+        tx.commit().unwrap();
+        Ok(0)
+    }).await.unwrap();
+
+    reset_code
+}
+
+// zwraca id usera jesli kod jest dobry, kod od razu oznaczany jako zuzyty
+pub async fn use_password_reset_db(connection: &Connection, user_name: String, reset_code: String) -> Result<i64, &str> {
+    let time_since_epoch: i64 = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap().as_secs() as i64;
+    let reset_hash = hash_token(&reset_code);
+    let find_query = "
+        SELECT password_resets.user_id
+        FROM password_resets
+        JOIN users ON users.user_id=password_resets.user_id
+        WHERE password_resets.code_hash = ? AND users.user_name = ?
+        AND password_resets.is_used = 0 AND password_resets.expires_on > ?";
+    let use_query = "UPDATE password_resets SET is_used = 1 WHERE code_hash = ?";
+    connection.call(move |conn| {
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate).unwrap();
+        let user_id = {
+            let mut statement = tx.prepare(find_query).unwrap();
+            let mut rows = statement.query(params![reset_hash, user_name, time_since_epoch]).unwrap();
+            rows.next().unwrap().map(|row| row.get::<_, i64>(0).unwrap())
+        };
+        match user_id {
+            Some(user_id) => {
+                tx.execute(use_query, params![reset_hash]).unwrap();
+                tx.commit().unwrap();
+                Ok(Ok(user_id))
+            },
+            None => Ok(Err("Invalid reset code"))
+        }
+    }).await.unwrap()
+}
+
+pub async fn get_id_passwd_role(connection: &Connection, user: String) -> Result<(i64, String, String), String> {
+    let query = "SELECT passwd, user_id, role FROM users WHERE user_name = ?";
+
+    let ret = connection.call(move |conn| {
+        let mut statement = conn.prepare(query).unwrap();
+        let mut rows = statement.query_map(params![user], |row| { Ok((row.get(1).unwrap(), row.get(0).unwrap(), row.get(2).unwrap())) }).unwrap();
+        if let Some(row) = rows.next() {
+            Ok(row.unwrap())
+        } else {
+            Ok((-1, "".to_string(), "".to_string()))
+        }
+    }).await;
+    match ret {
+        Ok(val) => {
+            if val.0 == -1 {
+                Err("User not found".to_string())
+            } else {
+                Ok(val)
+            }
+        },
+        Err(_) => Err("Database error".to_string())
+    }
+    
+}
+
+// liczniki celu sa przeliczane od nowa przy kazdej zmianie, posts.likes to suma wszystkich reakcji
+fn reaction_counts_update(target_type: &str) -> String {
+    let counts = format!("
+        (SELECT json_group_object(kind, reaction_count) FROM
+        (SELECT kind, COUNT(*) AS reaction_count FROM reactions
+        WHERE target_type = '{}' AND target_id = ?1 GROUP BY kind))", target_type);
+    match target_type {
+        "post" => format!("
+            UPDATE posts SET reaction_counts = {},
+            likes = (SELECT COUNT(*) FROM reactions WHERE target_type = 'post' AND target_id = ?1)
+            WHERE post_id = ?1", counts),
+        _ => format!("UPDATE comments SET reaction_counts = {} WHERE comment_id = ?1", counts),
+    }
+}
+
+pub async fn add_reaction_db(connection: &Connection, user_id: i64, target_type: String, target_id: i64, kind: String) -> bool {
+    // reakcja jest kluczem, przy dwoch rownoczesnych zadaniach (double click) drugie nic nie wstawi
+    let query = "INSERT OR IGNORE INTO reactions (user_id, target_type, target_id, kind, date) VALUES (?, ?, ?, ?, ?)";
+    let update_query = reaction_counts_update(&target_type);
+    let timestamp = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap().as_secs() as i64;
+
+    let log_kind = kind.clone();
+    let log_target_type = target_type.clone();
+    let changed = connection.call(move |conn| {
+        let tx = conn.transaction().unwrap();
+        let changed = tx.execute(query, params![user_id, target_type, target_id, kind, timestamp]).unwrap();
+        if changed > 0 {
+            tx.execute(&update_query, params![target_id]).unwrap();
+        }
+        tx.commit().unwrap();
+        Ok(changed)
+    }).await.unwrap();
+
+    if changed == 0 {
+        info!("Reaction already exists");
+        return true;
+    }
+
+    info!("Reaction {} added for {} {} by user {}", log_kind, log_target_type, target_id, user_id);
+    false
+}
+
+pub async fn remove_reaction_db(connection: &Connection, user_id: i64, target_type: String, target_id: i64, kind: String) -> bool {
+    let query = "DELETE FROM reactions WHERE user_id = ? AND target_type = ? AND target_id = ? AND kind = ?";
+    let update_query = reaction_counts_update(&target_type);
+
+    let log_kind = kind.clone();
+    let log_target_type = target_type.clone();
+    let changed = connection.call(move |conn| {
+        let tx = conn.transaction().unwrap();
+        let changed = tx.execute(query, params![user_id, target_type, target_id, kind]).unwrap();
+        if changed > 0 {
+            tx.execute(&update_query, params![target_id]).unwrap();
+        }
+        tx.commit().unwrap();
+        Ok(changed)
+    }).await.unwrap();
+
+    if changed == 0 {
+        info!("Reaction doesn't exists");
+        return true;
+    }
+
+    info!("Reaction {} removed from {} {} by user {}", log_kind, log_target_type, target_id, user_id);
+    false
+}
+
+pub async fn check_follow(connection: &Connection, follower_id: i64, followed_id: i64) -> bool {
+    let query = "SELECT follower_id FROM follows WHERE follower_id = ? AND followed_id = ?";
+
+    connection.call(move |conn| {
+        let mut statement = conn.prepare(query).unwrap();
+        let mut rows = statement.query(params![follower_id, followed_id]).unwrap();
+        Ok(rows.next().unwrap().is_some())
+    }).await.unwrap()
+}
+
+pub async fn add_follow_db(connection: &Connection, follower_id: i64, followed_id: i64) -> bool {
+    let time_since_epoch: i64 = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap().as_secs() as i64;
+    // para jest kluczem, przy dwoch rownoczesnych zadaniach drugie nic nie wstawi
+    let query = "INSERT OR IGNORE INTO follows VALUES (?, ?, ?)";
+
+    let changed = connection.call(move |conn| {
+        let mut statement = conn.prepare(query).unwrap();
+        Ok(statement.execute(params![follower_id, followed_id, time_since_epoch]).unwrap())
+    }).await.unwrap();
+
+    if changed == 0 {
+        info!("Follow already exists");
+        return true;
+    }
+
+    info!("User {} followed user {}", follower_id, followed_id);
+    false
+}
+
+pub async fn remove_follow_db(connection: &Connection, follower_id: i64, followed_id: i64) -> bool {
+    let query = "DELETE FROM follows WHERE follower_id = ? AND followed_id = ?";
 
-    if check_like(connection, user_id, post_id).await {
-        info!("Like already exists");
+    if !check_follow(connection, follower_id, followed_id).await {
+        info!("Follow doesn't exist");
         return true;
     }
 
     connection.call(move |conn| {
         let mut statement = conn.prepare(query).unwrap();
-        statement.execute(params![user_id, post_id]).unwrap();
+        statement.execute(params![follower_id, followed_id]).unwrap();
         Ok(0)
     }).await.unwrap();
-    
+
+    info!("User {} unfollowed user {}", follower_id, followed_id);
+    false
+}
+
+pub async fn add_repost_db(connection: &Connection, user_id: i64, post_id: i64) -> bool {
+    let time_since_epoch: i64 = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap().as_secs() as i64;
+
+    // licznik rosnie tylko gdy wiersz faktycznie wszedl, dwa rownoczesne reposty licza sie raz
+    let changed = connection.call(move |conn| {
+        let tx = conn.transaction().unwrap();
+        let changed = tx.execute("INSERT OR IGNORE INTO reposts VALUES (?, ?, ?)", params![user_id, post_id, time_since_epoch]).unwrap();
+        if changed > 0 {
+            tx.execute("UPDATE posts SET repost_count = repost_count + 1 WHERE post_id = ?", params![post_id]).unwrap();
+        }
+        tx.commit().unwrap();
+        Ok(changed)
+    }).await.unwrap();
+
+    if changed == 0 {
+        info!("Repost already exists");
+        return true;
+    }
+
+    info!("User {} reposted post {}", user_id, post_id);
+    false
+}
+
+pub async fn remove_repost_db(connection: &Connection, user_id: i64, post_id: i64) -> bool {
+    let changed = connection.call(move |conn| {
+        let tx = conn.transaction().unwrap();
+        let changed = tx.execute("DELETE FROM reposts WHERE user_id = ? AND post_id = ?", params![user_id, post_id]).unwrap();
+        if changed > 0 {
+            tx.execute("UPDATE posts SET repost_count = repost_count - 1 WHERE post_id = ?", params![post_id]).unwrap();
+        }
+        tx.commit().unwrap();
+        Ok(changed)
+    }).await.unwrap();
+
+    if changed == 0 {
+        info!("Repost doesn't exist");
+        return true;
+    }
+
+    info!("User {} removed repost of post {}", user_id, post_id);
+    false
+}
+
+// dokleja cytowane posty, z tymi samymi filtrami co listy postow; cytat w cytacie
+// zostaje tylko jako quoted_post_id
+pub async fn attach_quoted_posts(connection: &Connection, posts: &mut [Post], viewer_id: i64) {
+    let query = format!(
+        "
+        SELECT posts.*, users.user_name, users.display_name, images.image_file,
+        (SELECT COUNT(*) FROM comments WHERE comments.post_id=posts.post_id) AS comment_count
+        FROM posts
+        JOIN users ON users.user_id=posts.user_id
+        LEFT JOIN images ON users.pfp_id=images.image_id
+        WHERE posts.post_id = ?
+        AND {}
+        AND {}",
+        sanctioned_authors_filter("posts.user_id", viewer_id),
+        hidden_authors_filter("posts.user_id", viewer_id)
+    );
+
+    let mut quoted: HashMap<i64, QuotedPost> = HashMap::new();
+    for post in posts.iter_mut() {
+        let Some(quoted_post_id) = post.quoted_post_id else {
+            continue;
+        };
+        if let Some(cached) = quoted.get(&quoted_post_id) {
+            post.quoted_post = Some(cached.clone());
+            continue;
+        }
+        let query = query.clone();
+        let mut quoted_post = connection.call(move |conn| {
+            let mut statement = conn.prepare(&query).unwrap();
+            let mut rows = statement.query(params![quoted_post_id]).unwrap();
+            Ok(rows.next().unwrap().map(post_from_row))
+        }).await.unwrap();
+        if let Some(quoted_post) = quoted_post.as_mut() {
+            attach_polls(connection, std::slice::from_mut(quoted_post), viewer_id).await;
+        }
+        let status = match quoted_post {
+            Some(_) => "ok",
+            None if check_post(connection, quoted_post_id).await => "unavailable",
+            None => "deleted",
+        };
+        let quoted_post = QuotedPost {
+            post_id: quoted_post_id,
+            status: status.to_string(),
+            post: quoted_post.map(Box::new),
+        };
+        post.quoted_post = Some(quoted_post.clone());
+        quoted.insert(quoted_post_id, quoted_post);
+    }
+}
+
+pub async fn add_poll_db(connection: &Connection, post_id: i64, poll: PollCreateRequest) {
+    let poll_query = "INSERT INTO polls (post_id, multiple_choice, closes_on) VALUES (?, ?, ?)";
+    let option_query = "INSERT INTO poll_options (post_id, position, text) VALUES (?, ?, ?)";
     connection.call(move |conn| {
-        let mut statement = conn.prepare(update_query).unwrap();
-        statement.execute(params![post_id]).unwrap();
+        let tx = conn.transaction().unwrap();
+        tx.execute(poll_query, params![post_id, poll.multiple_choice as i64, poll.closes_on]).unwrap();
+        for (position, text) in poll.options.iter().enumerate() {
+            tx.execute(option_query, params![post_id, position as i64, text]).unwrap();
+        }
+        tx.commit().unwrap();
         Ok(0)
     }).await.unwrap();
 
-    info!("Like added for post {} by user {}", post_id, user_id);
+    info!("Added poll to post {}", post_id);
+}
+
+// ankieta posta widziana przez viewer_id, None jesli post jej nie ma
+pub async fn get_poll(connection: &Connection, post_id: i64, viewer_id: i64) -> Option<Poll> {
+    let poll_query = "SELECT multiple_choice, closes_on FROM polls WHERE post_id = ?";
+    let options_query = "
+        SELECT poll_options.option_id, poll_options.text, poll_options.vote_count,
+        EXISTS (SELECT 1 FROM poll_votes WHERE poll_votes.option_id=poll_options.option_id AND poll_votes.user_id = ?) AS voted
+        FROM poll_options
+        WHERE poll_options.post_id = ?
+        ORDER BY poll_options.position ASC";
+    let voters_query = "SELECT COUNT(DISTINCT user_id) FROM poll_votes WHERE post_id = ?";
+    let timestamp = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap().as_secs() as i64;
+
+    connection.call(move |conn| {
+        let (multiple_choice, closes_on): (i64, Option<i64>) = {
+            let mut statement = conn.prepare(poll_query).unwrap();
+            let mut rows = statement.query(params![post_id]).unwrap();
+            match rows.next().unwrap() {
+                Some(row) => (row.get(0).unwrap(), row.get(1).unwrap()),
+                None => return Ok(None),
+            }
+        };
+        let options: Vec<(PollOption, i64)> = {
+            let mut statement = conn.prepare(options_query).unwrap();
+            let mut rows = statement.query(params![viewer_id, post_id]).unwrap();
+            let mut option_vec = Vec::new();
+            while let Ok(Some(row)) = rows.next() {
+                option_vec.push((PollOption {
+                    option_id: row.get(0).unwrap(),
+                    text: row.get(1).unwrap(),
+                    votes: None,
+                    voted: row.get::<_, i64>(3).unwrap() == 1,
+                }, row.get(2).unwrap()));
+            }
+            option_vec
+        };
+        let voter_count: i64 = conn.query_row(voters_query, params![post_id], |row| row.get(0)).unwrap();
+
+        let is_closed = closes_on.is_some_and(|closes_on| closes_on <= timestamp);
+        let has_voted = options.iter().any(|(option, _)| option.voted);
+        let show_results = is_closed || has_voted;
+        Ok(Some(Poll {
+            multiple_choice: multiple_choice == 1,
+            closes_on,
+            is_closed,
+            has_voted,
+            voter_count: show_results.then_some(voter_count),
+            options: options
+                .into_iter()
+                .map(|(option, votes)| PollOption { votes: show_results.then_some(votes), ..option })
+                .collect(),
+        }))
+    }).await.unwrap()
+}
+
+// true jesli user juz glosowal, wtedy nic nie zapisuje
+pub async fn add_poll_vote_db(connection: &Connection, post_id: i64, user_id: i64, option_ids: Vec<i64>) -> bool {
+    let check_query = "SELECT 1 FROM poll_votes WHERE post_id = ? AND user_id = ?";
+    let vote_query = "INSERT INTO poll_votes (post_id, user_id, option_id, date) VALUES (?, ?, ?, ?)";
+    let count_query = "UPDATE poll_options SET vote_count = vote_count + 1 WHERE option_id = ?";
+    let time_since_epoch: i64 = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap().as_secs() as i64;
+
+    let existed = connection.call(move |conn| {
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate).unwrap();
+        let existed = tx.prepare(check_query).unwrap().exists(params![post_id, user_id]).unwrap();
+        if !existed {
+            for option_id in option_ids.iter() {
+                tx.execute(vote_query, params![post_id, user_id, option_id, time_since_epoch]).unwrap();
+                tx.execute(count_query, params![option_id]).unwrap();
+            }
+        }
+        tx.commit().unwrap();
+        Ok(existed)
+    }).await.unwrap();
+
+    if existed {
+        info!("User {} already voted in poll {}", user_id, post_id);
+    } else {
+        info!("User {} voted in poll {}", user_id, post_id);
+    }
+    existed
+}
+
+// dokleja ankiety do postow, ktore je maja
+pub async fn attach_polls(connection: &Connection, posts: &mut [Post], viewer_id: i64) {
+    if posts.is_empty() {
+        return;
+    }
+    let post_ids: Vec<String> = posts.iter().map(|post| post.post_id.to_string()).collect();
+    let query = format!("SELECT post_id FROM polls WHERE post_id IN ({})", post_ids.join(", "));
+    let poll_post_ids = connection.call(move |conn| {
+        let mut statement = conn.prepare(&query).unwrap();
+        let mut rows = statement.query([]).unwrap();
+        let mut post_id_vec: Vec<i64> = Vec::new();
+        while let Ok(Some(row)) = rows.next() {
+            post_id_vec.push(row.get(0).unwrap());
+        }
+        Ok(post_id_vec)
+    }).await.unwrap();
+
+    for post in posts.iter_mut() {
+        if poll_post_ids.contains(&post.post_id) {
+            post.poll = get_poll(connection, post.post_id, viewer_id).await;
+        }
+    }
+}
+
+// wlasciciel kolekcji, -1 jesli nie istnieje
+pub async fn get_collection_owner(connection: &Connection, collection_id: i64) -> i64 {
+    let query = "SELECT user_id FROM collections WHERE collection_id = ?";
+    connection.call(move |conn| {
+        let mut statement = conn.prepare(query).unwrap();
+        let mut rows = statement.query(params![collection_id]).unwrap();
+        Ok(rows.next().unwrap().map(|row| row.get(0).unwrap()).unwrap_or(-1))
+    }).await.unwrap()
+}
+
+pub async fn check_collection_name(connection: &Connection, user_id: i64, name: String) -> bool {
+    let query = "SELECT collection_id FROM collections WHERE user_id = ? AND name = ?";
+    connection.call(move |conn| {
+        let mut statement = conn.prepare(query).unwrap();
+        let mut rows = statement.query(params![user_id, name]).unwrap();
+        Ok(rows.next().unwrap().is_some())
+    }).await.unwrap()
+}
+
+pub async fn add_collection_db(connection: &Connection, user_id: i64, name: String) -> i64 {
+    let query = "INSERT INTO collections (user_id, name, date) VALUES (?, ?, ?)";
+    let time_since_epoch: i64 = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap().as_secs() as i64;
+    let collection_id = connection.call(move |conn| {
+        let mut statement = conn.prepare(query).unwrap();
+        statement.execute(params![user_id, name, time_since_epoch]).unwrap();
+        Ok(conn.last_insert_rowid())
+    }).await.unwrap();
+
+    info!("User {} created collection {}", user_id, collection_id);
+    collection_id
+}
+
+pub async fn delete_collection_db(connection: &Connection, collection_id: i64) {
+    connection.call(move |conn| {
+        let tx = conn.transaction().unwrap();
+        tx.execute("DELETE FROM collections_posts WHERE collection_id = ?", params![collection_id]).unwrap();
+        tx.execute("DELETE FROM collections WHERE collection_id = ?", params![collection_id]).unwrap();
+        tx.commit().unwrap();
+        Ok(0)
+    }).await.unwrap();
+
+    info!("Deleted collection {}", collection_id);
+}
+
+pub async fn add_collection_post_db(connection: &Connection, collection_id: i64, post_id: i64) -> bool {
+    // para jest kluczem, przy dwoch rownoczesnych zadaniach drugie nic nie wstawi
+    let query = "INSERT OR IGNORE INTO collections_posts VALUES (?, ?, ?)";
+    let time_since_epoch: i64 = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap().as_secs() as i64;
+
+    let changed = connection.call(move |conn| {
+        let mut statement = conn.prepare(query).unwrap();
+        Ok(statement.execute(params![collection_id, post_id, time_since_epoch]).unwrap())
+    }).await.unwrap();
+
+    if changed == 0 {
+        info!("Post already in collection");
+        return true;
+    }
+
+    info!("Post {} added to collection {}", post_id, collection_id);
     false
 }
 
-pub async fn remove_like_db(connection: &Connection, user_id: i64, post_id: i64) -> bool {
-    let query = "DELETE FROM likes WHERE user_id=? AND post_id=?"; 
-    let update_query = "UPDATE posts SET likes=likes-1 WHERE post_id = ?";
+pub async fn remove_collection_post_db(connection: &Connection, collection_id: i64, post_id: i64) -> bool {
+    let query = "DELETE FROM collections_posts WHERE collection_id = ? AND post_id = ?";
 
-    if !check_like(connection, user_id, post_id).await {
-        info!("Like doesn't exists");
+    let changed = connection.call(move |conn| {
+        let mut statement = conn.prepare(query).unwrap();
+        Ok(statement.execute(params![collection_id, post_id]).unwrap())
+    }).await.unwrap();
+
+    if changed == 0 {
+        info!("Post not in collection");
         return true;
     }
 
+    info!("Post {} removed from collection {}", post_id, collection_id);
+    false
+}
+
+// blocker_id zablokowal blocked_id
+pub async fn check_block(connection: &Connection, blocker_id: i64, blocked_id: i64) -> bool {
+    let query = "SELECT blocker_id FROM blocks WHERE blocker_id = ? AND blocked_id = ?";
+
     connection.call(move |conn| {
         let mut statement = conn.prepare(query).unwrap();
-        statement.execute(params![user_id, post_id]).unwrap();
+        let mut rows = statement.query(params![blocker_id, blocked_id]).unwrap();
+        Ok(rows.next().unwrap().is_some())
+    }).await.unwrap()
+}
+
+// blokada zrywa tez obserwowanie w obie strony
+pub async fn add_block_db(connection: &Connection, blocker_id: i64, blocked_id: i64) -> bool {
+    let time_since_epoch: i64 = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap().as_secs() as i64;
+
+    let changed = connection.call(move |conn| {
+        let tx = conn.transaction().unwrap();
+        let changed = tx.execute("INSERT OR IGNORE INTO blocks VALUES (?, ?, ?)", params![blocker_id, blocked_id, time_since_epoch]).unwrap();
+        if changed > 0 {
+            tx.execute("
+                DELETE FROM follows WHERE (follower_id = ? AND followed_id = ?)
+                OR (follower_id = ? AND followed_id = ?)", params![blocker_id, blocked_id, blocked_id, blocker_id]).unwrap();
+        }
+        tx.commit().unwrap();
+        Ok(changed)
+    }).await.unwrap();
+
+    if changed == 0 {
+        info!("Block already exists");
+        return true;
+    }
+
+    info!("User {} blocked user {}", blocker_id, blocked_id);
+    false
+}
+
+pub async fn remove_block_db(connection: &Connection, blocker_id: i64, blocked_id: i64) -> bool {
+    let query = "DELETE FROM blocks WHERE blocker_id = ? AND blocked_id = ?";
+
+    if !check_block(connection, blocker_id, blocked_id).await {
+        info!("Block doesn't exist");
+        return true;
+    }
+
+    connection.call(move |conn| {
+        let mut statement = conn.prepare(query).unwrap();
+        statement.execute(params![blocker_id, blocked_id]).unwrap();
         Ok(0)
     }).await.unwrap();
-    
+
+    info!("User {} unblocked user {}", blocker_id, blocked_id);
+    false
+}
+
+pub async fn check_mute(connection: &Connection, muter_id: i64, muted_id: i64) -> bool {
+    let query = "SELECT muter_id FROM mutes WHERE muter_id = ? AND muted_id = ?";
+
     connection.call(move |conn| {
-        let mut statement = conn.prepare(update_query).unwrap();
-        statement.execute(params![post_id]).unwrap();
+        let mut statement = conn.prepare(query).unwrap();
+        let mut rows = statement.query(params![muter_id, muted_id]).unwrap();
+        Ok(rows.next().unwrap().is_some())
+    }).await.unwrap()
+}
+
+pub async fn add_mute_db(connection: &Connection, muter_id: i64, muted_id: i64) -> bool {
+    let time_since_epoch: i64 = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap().as_secs() as i64;
+    let query = "INSERT OR IGNORE INTO mutes VALUES (?, ?, ?)";
+
+    let changed = connection.call(move |conn| {
+        let mut statement = conn.prepare(query).unwrap();
+        Ok(statement.execute(params![muter_id, muted_id, time_since_epoch]).unwrap())
+    }).await.unwrap();
+
+    if changed == 0 {
+        info!("Mute already exists");
+        return true;
+    }
+
+    info!("User {} muted user {}", muter_id, muted_id);
+    false
+}
+
+pub async fn remove_mute_db(connection: &Connection, muter_id: i64, muted_id: i64) -> bool {
+    let query = "DELETE FROM mutes WHERE muter_id = ? AND muted_id = ?";
+
+    if !check_mute(connection, muter_id, muted_id).await {
+        info!("Mute doesn't exist");
+        return true;
+    }
+
+    connection.call(move |conn| {
+        let mut statement = conn.prepare(query).unwrap();
+        statement.execute(params![muter_id, muted_id]).unwrap();
         Ok(0)
     }).await.unwrap();
 
-    info!("Like added for post {} by user {}", post_id, user_id);
+    info!("User {} unmuted user {}", muter_id, muted_id);
+    false
+}
+
+// czy ktorys z userow zablokowal author_id
+pub async fn check_blocked_by_any(connection: &Connection, user_ids: Vec<i64>, author_id: i64) -> bool {
+    for user_id in user_ids {
+        if user_id != author_id && check_block(connection, user_id, author_id).await {
+            return true;
+        }
+    }
     false
 }
 
@@ -533,20 +1805,16 @@ pub async fn add_upload_db(connection: &Connection, user_id: i64, weight: i16) {
     }).await.unwrap();
 }
 
-pub async fn get_upload(connection: &Connection, user_id: i64) -> i64 {
+pub async fn get_upload(connection: &Connection, user_id: i64, window: i64) -> i64 {
     let mut time_since_epoch: i64 = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap().as_secs() as i64;
-    time_since_epoch -= 60;
+    time_since_epoch -= window;
     let query = "SELECT SUM(weight) FROM uploads WHERE user_id = ? AND date > ?";
     
     connection.call(move |conn| {
         let mut statement = conn.prepare(query).unwrap();
         let mut rows = statement.query(params![user_id, time_since_epoch]).unwrap();
         if let Some(val) = rows.next().unwrap() {
-            let ret = match val.get::<_, i64>(0) {
-                Ok(val) => {val},
-                Err(_) => {0}
-            };
-            Ok(ret)
+            Ok(val.get::<_, i64>(0).unwrap_or_default())
         } else {
             Ok(0)
         }
@@ -554,6 +1822,531 @@ pub async fn get_upload(connection: &Connection, user_id: i64) -> i64 {
     }).await.unwrap()
 }
 
-pub async fn is_limited(connection: &Connection, user_id: i64) -> bool {
-    get_upload(connection, user_id).await > 50   
+pub async fn is_limited(connection: &Connection, config: &Config, user_id: i64) -> bool {
+    get_upload(connection, user_id, config.rate_limit_window).await > config.rate_limit_max_weight
+}
+
+const SNIPPET_MATCH_START: char = '\u{2}';
+const SNIPPET_MATCH_END: char = '\u{3}';
+
+// snippet() dostaje surowa tresc posta, wiec najpierw escapujemy calosc,
+// a dopiero potem znaczniki trafien zamieniamy na <b></b>
+fn snippet_to_html(snippet: &str) -> String {
+    let mut html = String::with_capacity(snippet.len());
+    for c in snippet.chars() {
+        match c {
+            '&' => html.push_str("&amp;"),
+            '<' => html.push_str("&lt;"),
+            '>' => html.push_str("&gt;"),
+            '"' => html.push_str("&quot;"),
+            '\'' => html.push_str("&#39;"),
+            SNIPPET_MATCH_START => html.push_str("<b>"),
+            SNIPPET_MATCH_END => html.push_str("</b>"),
+            _ => html.push(c),
+        }
+    }
+    html
+}
+
+// match to juz wynik fts_query
+pub async fn search_posts_db(
+    connection: &Connection,
+    match_query: String,
+    search: PostSearchQuery,
+    viewer_id: i64
+) -> Vec<SearchResult> {
+    let query = format!(
+        "
+        SELECT posts.*, users.user_name, users.display_name, images.image_file,
+        (SELECT COUNT(*) FROM comments WHERE comments.post_id=posts.post_id) AS comment_count,
+        snippet(posts_fts, 0, char(2), char(3), '...', 16) AS snippet,
+        bm25(posts_fts) AS rank
+        FROM posts_fts
+        JOIN posts ON posts.post_id=posts_fts.rowid
+        JOIN users ON users.user_id=posts.user_id
+        LEFT JOIN images ON users.pfp_id=images.image_id
+        WHERE posts_fts MATCH ?1
+        AND {}
+        AND {}
+        AND (?2 IS NULL OR posts.post_id IN (
+            SELECT posts_tags.post_id FROM posts_tags
+            JOIN tags ON tags.tag_id=posts_tags.tag_id
+            WHERE tags.tag_name = ?2
+        ))
+        AND (?3 IS NULL OR users.user_name = ?3)
+        AND (?4 IS NULL OR posts.date >= ?4)
+        AND (?5 IS NULL OR posts.date <= ?5)
+        ORDER BY rank
+        LIMIT ?6 OFFSET ?7
+    ",
+        sanctioned_authors_filter("posts.user_id", viewer_id),
+        hidden_authors_filter("posts.user_id", viewer_id)
+    );
+    let mut result_list = connection.call(move |conn| {
+        let mut statement = conn.prepare(&query).unwrap();
+        let mut rows = statement.query(params![
+            match_query,
+            search.tag,
+            search.author,
+            search.from,
+            search.to,
+            search.limit.unwrap_or(20),
+            search.offset.unwrap_or(0),
+        ]).unwrap();
+        let mut result_vec: Vec<SearchResult> = Vec::new();
+        while let Ok(Some(row)) = rows.next() {
+            result_vec.push(SearchResult {
+                post: post_from_row(row),
+                snippet: snippet_to_html(&row.get::<_, String>("snippet").unwrap()),
+                rank: row.get("rank").unwrap(),
+            });
+        }
+        Ok(result_vec)
+    }).await.unwrap();
+
+    for result in result_list.iter_mut() {
+        attach_quoted_posts(connection, std::slice::from_mut(&mut result.post), viewer_id).await;
+        attach_polls(connection, std::slice::from_mut(&mut result.post), viewer_id).await;
+    }
+    result_list
+}
+
+// te rodzaje zbijaja sie w jedno nieprzeczytane powiadomienie ("N osob zareagowalo na twoj post")
+const AGGREGATED_NOTIFICATIONS: [&str; 4] = ["reaction", "comment", "follow", "repost"];
+
+pub async fn add_notification_db(
+    connection: &Connection,
+    user_id: i64,
+    kind: &'static str,
+    actor_id: i64,
+    post_id: Option<i64>,
+    comment_id: Option<i64>
+) -> Option<i64> {
+    if user_id == actor_id {
+        return None;
+    }
+    let time_since_epoch: i64 = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap().as_secs() as i64;
+
+    let find_query = "
+        SELECT notification_id FROM notifications
+        WHERE user_id = ? AND kind = ? AND post_id IS ? AND is_read = 0";
+    let insert_query = "
+        INSERT INTO notifications (user_id, kind, post_id, comment_id, date, is_read)
+        VALUES (?, ?, ?, ?, ?, 0)";
+    let update_query = "UPDATE notifications SET date = ? WHERE notification_id = ?";
+    let actor_delete_query = "DELETE FROM notification_actors WHERE notification_id = ? AND actor_id = ?";
+    let actor_insert_query = "INSERT INTO notification_actors VALUES (?, ?, ?)";
+
+    let notification_id = connection.call(move |conn| {
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate).unwrap();
+        let existing: Option<i64> = if AGGREGATED_NOTIFICATIONS.contains(&kind) {
+            let mut statement = tx.prepare(find_query).unwrap();
+            let mut rows = statement.query(params![user_id, kind, post_id]).unwrap();
+            rows.next().unwrap().map(|row| row.get(0).unwrap())
+        } else {
+            None
+        };
+        let notification_id = match existing {
+            Some(id) => {
+                tx.execute(update_query, params![time_since_epoch, id]).unwrap();
+                tx.execute(actor_delete_query, params![id, actor_id]).unwrap();
+                id
+            }
+            None => {
+                tx.execute(insert_query, params![user_id, kind, post_id, comment_id, time_since_epoch]).unwrap();
+                tx.last_insert_rowid()
+            }
+        };
+        tx.execute(actor_insert_query, params![notification_id, actor_id, time_since_epoch]).unwrap();
+        tx.commit().unwrap();
+        Ok(notification_id)
+    }).await.unwrap();
+
+    info!("Notification {} for user {} from user {}", kind, user_id, actor_id);
+    Some(notification_id)
+}
+
+pub async fn count_unread_notifications(connection: &Connection, user_id: i64) -> i64 {
+    let query = "SELECT COUNT(*) FROM notifications WHERE user_id = ? AND is_read = 0";
+    connection.call(move |conn| {
+        let mut statement = conn.prepare(query).unwrap();
+        let mut rows = statement.query(params![user_id]).unwrap();
+        Ok(rows.next().unwrap().unwrap().get(0).unwrap())
+    }).await.unwrap()
+}
+
+// zwraca ile powiadomien faktycznie zmienilo stan
+pub async fn read_notifications_db(connection: &Connection, user_id: i64, notification_ids: Vec<i64>) -> usize {
+    let query = "UPDATE notifications SET is_read = 1 WHERE notification_id = ? AND user_id = ? AND is_read = 0";
+    connection.call(move |conn| {
+        let tx = conn.transaction().unwrap();
+        let mut changed = 0;
+        for notification_id in notification_ids.iter() {
+            changed += tx.execute(query, params![notification_id, user_id]).unwrap();
+        }
+        tx.commit().unwrap();
+        Ok(changed)
+    }).await.unwrap()
+}
+
+pub async fn read_all_notifications_db(connection: &Connection, user_id: i64) -> usize {
+    let query = "UPDATE notifications SET is_read = 1 WHERE user_id = ? AND is_read = 0";
+    connection.call(move |conn| {
+        let mut statement = conn.prepare(query).unwrap();
+        Ok(statement.execute(params![user_id]).unwrap())
+    }).await.unwrap()
+}
+
+// wiersz z "SELECT messages.*, users.user_name, users.display_name, images.image_file ..."
+pub fn message_from_row(row: &Row) -> Message {
+    Message {
+        message_id: row.get("message_id").unwrap(),
+        conversation_id: row.get("conversation_id").unwrap(),
+        user_id: row.get("user_id").unwrap(),
+        user_name: row.get("user_name").unwrap(),
+        display_name: row.get("display_name").unwrap(),
+        pfp_image: row.get("image_file").unwrap_or_default(),
+        body: row.get("body").unwrap(),
+        date: row.get("date").unwrap(),
+    }
+}
+
+pub async fn check_conversation_member(connection: &Connection, conversation_id: i64, user_id: i64) -> bool {
+    let query = "SELECT user_id FROM conversation_members WHERE conversation_id = ? AND user_id = ?";
+    connection.call(move |conn| {
+        let mut statement = conn.prepare(query).unwrap();
+        let mut rows = statement.query(params![conversation_id, user_id]).unwrap();
+        Ok(rows.next().unwrap().is_some())
+    }).await.unwrap()
+}
+
+pub async fn get_conversation_members(connection: &Connection, conversation_id: i64) -> Vec<i64> {
+    let query = "SELECT user_id FROM conversation_members WHERE conversation_id = ?";
+    connection.call(move |conn| {
+        let mut statement = conn.prepare(query).unwrap();
+        let mut rows = statement.query(params![conversation_id]).unwrap();
+        let mut user_vec: Vec<i64> = Vec::new();
+        while let Ok(Some(row)) = rows.next() {
+            user_vec.push(row.get(0).unwrap());
+        }
+        Ok(user_vec)
+    }).await.unwrap()
+}
+
+// istniejaca rozmowa 1:1 miedzy dwoma userami, zeby nie zakladac drugiej
+pub async fn get_direct_conversation(connection: &Connection, user_a: i64, user_b: i64) -> Option<i64> {
+    let query = "
+        SELECT conversations.conversation_id FROM conversations
+        JOIN conversation_members AS a ON a.conversation_id=conversations.conversation_id
+        JOIN conversation_members AS b ON b.conversation_id=conversations.conversation_id
+        WHERE conversations.is_group = 0 AND a.user_id = ? AND b.user_id = ?";
+    connection.call(move |conn| {
+        let mut statement = conn.prepare(query).unwrap();
+        let mut rows = statement.query(params![user_a, user_b]).unwrap();
+        Ok(rows.next().unwrap().map(|row| row.get(0).unwrap()))
+    }).await.unwrap()
+}
+
+// members bez tworcy, tworca dochodzi sam
+pub async fn add_conversation_db(
+    connection: &Connection,
+    created_by: i64,
+    members: Vec<i64>,
+    is_group: bool,
+    title: Option<String>
+) -> i64 {
+    let time_since_epoch: i64 = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap().as_secs() as i64;
+
+    let conversation_query = "
+        INSERT INTO conversations (created_by, created_on, is_group, title)
+        VALUES (?, ?, ?, ?)";
+    let member_query = "INSERT INTO conversation_members VALUES (?, ?, ?, 0)";
+    let conversation_id = connection.call(move |conn| {
+        let tx = conn.transaction().unwrap();
+        tx.execute(conversation_query, params![created_by, time_since_epoch, is_group as i64, title]).unwrap();
+        let conversation_id = tx.last_insert_rowid();
+        tx.execute(member_query, params![conversation_id, created_by, time_since_epoch]).unwrap();
+        for user_id in members.iter() {
+            tx.execute(member_query, params![conversation_id, user_id, time_since_epoch]).unwrap();
+        }
+        tx.commit().unwrap();
+        Ok(conversation_id)
+    }).await.unwrap();
+
+    info!("User {} started conversation {}", created_by, conversation_id);
+    conversation_id
+}
+
+pub async fn add_message_db(connection: &Connection, conversation_id: i64, user_id: i64, body: String) -> i64 {
+    let time_since_epoch: i64 = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap().as_secs() as i64;
+
+    let message_query = "INSERT INTO messages (conversation_id, user_id, body, date) VALUES (?, ?, ?, ?)";
+    let read_query = "
+        UPDATE conversation_members SET last_read_message_id = ?
+        WHERE conversation_id = ? AND user_id = ?";
+    let message_id = connection.call(move |conn| {
+        let tx = conn.transaction().unwrap();
+        tx.execute(message_query, params![conversation_id, user_id, body, time_since_epoch]).unwrap();
+        let message_id = tx.last_insert_rowid();
+        tx.execute(read_query, params![message_id, conversation_id, user_id]).unwrap();
+        tx.commit().unwrap();
+        Ok(message_id)
+    }).await.unwrap();
+
+    info!("User {} sent message {} to conversation {}", user_id, message_id, conversation_id);
+    message_id
+}
+
+pub async fn mark_conversation_read_db(connection: &Connection, conversation_id: i64, user_id: i64, message_id: i64) {
+    let query = "
+        UPDATE conversation_members SET last_read_message_id = ?
+        WHERE conversation_id = ? AND user_id = ? AND last_read_message_id < ?";
+    connection.call(move |conn| {
+        let mut statement = conn.prepare(query).unwrap();
+        statement.execute(params![message_id, conversation_id, user_id, message_id]).unwrap();
+        Ok(0)
+    }).await.unwrap();
+}
+
+// wiersz z "SELECT reports.*, users.user_name ... LEFT JOIN users ON users.user_id=reports.reporter_id"
+pub fn report_from_row(row: &Row) -> Report {
+    Report {
+        report_id: row.get("report_id").unwrap(),
+        reporter_id: row.get("reporter_id").unwrap(),
+        reporter_name: row.get("user_name").unwrap_or_default(),
+        target_type: row.get("target_type").unwrap(),
+        target_id: row.get("target_id").unwrap(),
+        author_id: row.get("author_id").unwrap(),
+        reason: row.get("reason").unwrap(),
+        details: row.get("details").unwrap(),
+        date: row.get("date").unwrap(),
+        status: row.get("status").unwrap(),
+    }
+}
+
+pub fn report_action_from_row(row: &Row) -> ReportAction {
+    ReportAction {
+        action_id: row.get("action_id").unwrap(),
+        report_id: row.get("report_id").unwrap(),
+        admin_id: row.get("admin_id").unwrap(),
+        admin_name: row.get("user_name").unwrap_or_default(),
+        action: row.get("action").unwrap(),
+        note: row.get("note").unwrap(),
+        date: row.get("date").unwrap(),
+    }
+}
+
+// None jesli ten user ma juz otwarte zgloszenie na ten sam cel
+pub async fn add_report_db(
+    connection: &Connection,
+    reporter_id: i64,
+    target_type: String,
+    target_id: i64,
+    author_id: i64,
+    reason: String,
+    details: String
+) -> Option<i64> {
+    let time_since_epoch: i64 = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap().as_secs() as i64;
+
+    let target = format!("{} {}", target_type, target_id);
+    let check_query = "
+        SELECT report_id FROM reports
+        WHERE reporter_id = ? AND target_type = ? AND target_id = ? AND status = 'open'";
+    let insert_query = "
+        INSERT INTO reports (reporter_id, target_type, target_id, author_id, reason, details, date, status)
+        VALUES (?, ?, ?, ?, ?, ?, ?, 'open')";
+    let report_id = connection.call(move |conn| {
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate).unwrap();
+        let exists = tx.prepare(check_query).unwrap()
+            .query(params![reporter_id, target_type, target_id]).unwrap()
+            .next().unwrap().is_some();
+        if exists {
+            return Ok(None);
+        }
+        tx.execute(insert_query, params![reporter_id, target_type, target_id, author_id, reason, details, time_since_epoch]).unwrap();
+        let report_id = tx.last_insert_rowid();
+        tx.commit().unwrap();
+        Ok(Some(report_id))
+    }).await.unwrap();
+
+    match report_id {
+        Some(report_id) => info!("User {} reported {} (report {})", reporter_id, target, report_id),
+        None => info!("Report already exists"),
+    }
+    report_id
+}
+
+pub async fn get_report_db(connection: &Connection, report_id: i64) -> Option<Report> {
+    let query = "
+        SELECT reports.*, users.user_name FROM reports
+        LEFT JOIN users ON users.user_id=reports.reporter_id
+        WHERE reports.report_id = ?";
+    connection.call(move |conn| {
+        let mut statement = conn.prepare(query).unwrap();
+        let mut rows = statement.query(params![report_id]).unwrap();
+        if let Ok(Some(row)) = rows.next() {
+            Ok(Some(report_from_row(row)))
+        } else {
+            Ok(None)
+        }
+    }).await.unwrap()
+}
+
+pub async fn count_open_reports(connection: &Connection, target_type: String, target_id: i64) -> i64 {
+    let query = "SELECT COUNT(*) FROM reports WHERE target_type = ? AND target_id = ? AND status = 'open'";
+    connection.call(move |conn| {
+        let mut statement = conn.prepare(query).unwrap();
+        let mut rows = statement.query(params![target_type, target_id]).unwrap();
+        Ok(rows.next().unwrap().unwrap().get(0).unwrap())
+    }).await.unwrap()
+}
+
+// zamyka wszystkie otwarte zgloszenia na dany cel, kazde dostaje wpis w report_actions
+pub async fn resolve_reports_db(
+    connection: &Connection,
+    target_type: String,
+    target_id: i64,
+    admin_id: i64,
+    action: String,
+    note: String
+) -> usize {
+    let time_since_epoch: i64 = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap().as_secs() as i64;
+    let status = if action == "dismiss" { "dismissed" } else { "resolved" };
+    let target = format!("{} {}", target_type, target_id);
+
+    let actions_query = "
+        INSERT INTO report_actions (report_id, admin_id, action, note, date)
+        SELECT report_id, ?, ?, ?, ? FROM reports
+        WHERE target_type = ? AND target_id = ? AND status = 'open'";
+    let status_query = "
+        UPDATE reports SET status = ?
+        WHERE target_type = ? AND target_id = ? AND status = 'open'";
+    let resolved = connection.call(move |conn| {
+        let tx = conn.transaction().unwrap();
+        tx.execute(actions_query, params![admin_id, action, note, time_since_epoch, target_type, target_id]).unwrap();
+        let resolved = tx.execute(status_query, params![status, target_type, target_id]).unwrap();
+        tx.commit().unwrap();
+        Ok(resolved)
+    }).await.unwrap();
+
+    info!("Admin {} resolved {} reports on {}", admin_id, resolved, target);
+    resolved
+}
+
+// kazda akcja admina zostawia slad, payload to dowolny json z parametrami akcji
+pub async fn add_audit_log_db(
+    connection: &Connection,
+    actor_id: i64,
+    action: &str,
+    target_type: &str,
+    target_id: i64,
+    payload: serde_json::Value
+) {
+    let time_since_epoch: i64 = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap().as_secs() as i64;
+
+    let query = "
+        INSERT INTO audit_log (actor_id, action, target_type, target_id, payload, date)
+        VALUES (?, ?, ?, ?, ?, ?)";
+    let action = action.to_string();
+    let target_type = target_type.to_string();
+    info!("User {} did {} on {} {}", actor_id, action, target_type, target_id);
+    connection.call(move |conn| {
+        let mut statement = conn.prepare(query).unwrap();
+        statement.execute(params![actor_id, action, target_type, target_id, payload.to_string(), time_since_epoch]).unwrap();
+        Ok(0)
+    }).await.unwrap();
+}
+
+pub async fn get_audit_log_db(connection: &Connection, filter: AuditLogQuery) -> Vec<AuditLogEntry> {
+    let query = "
+        SELECT audit_log.*, users.user_name FROM audit_log
+        LEFT JOIN users ON users.user_id=audit_log.actor_id
+        WHERE (?1 IS NULL OR audit_log.actor_id = ?1)
+        AND (?2 IS NULL OR audit_log.action = ?2)
+        AND (?3 IS NULL OR audit_log.target_type = ?3)
+        AND (?4 IS NULL OR audit_log.target_id = ?4)
+        AND (?5 IS NULL OR audit_log.date >= ?5)
+        AND (?6 IS NULL OR audit_log.date <= ?6)
+        ORDER BY audit_log.log_id DESC
+        LIMIT ?7 OFFSET ?8
+    ";
+    connection.call(move |conn| {
+        let mut statement = conn.prepare(query).unwrap();
+        let mut rows = statement.query(params![
+            filter.actor_id,
+            filter.action,
+            filter.target_type,
+            filter.target_id,
+            filter.from,
+            filter.to,
+            filter.limit.unwrap_or(50),
+            filter.offset.unwrap_or(0),
+        ]).unwrap();
+        let mut entry_vec: Vec<AuditLogEntry> = Vec::new();
+        while let Ok(Some(row)) = rows.next() {
+            entry_vec.push(AuditLogEntry {
+                log_id: row.get("log_id").unwrap(),
+                actor_id: row.get("actor_id").unwrap(),
+                actor_name: row.get("user_name").unwrap_or_default(),
+                action: row.get("action").unwrap(),
+                target_type: row.get("target_type").unwrap(),
+                target_id: row.get("target_id").unwrap(),
+                payload: serde_json::from_str(&row.get::<_, String>("payload").unwrap()).unwrap_or_default(),
+                date: row.get("date").unwrap(),
+            });
+        }
+        Ok(entry_vec)
+    }).await.unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fts_query_quotes_every_term() {
+        assert_eq!(fts_query("hello world").as_deref(), Some("\"hello\" \"world\""));
+        assert_eq!(fts_query("\"hello world\" foo").as_deref(), Some("\"hello world\" \"foo\""));
+    }
+
+    #[test]
+    fn fts_query_escapes_quotes_inside_terms() {
+        assert_eq!(fts_query("ab\"c").as_deref(), Some("\"ab\" \"c\""));
+        assert_eq!(fts_query("it's").as_deref(), Some("\"it's\""));
+    }
+
+    #[test]
+    fn fts_query_keeps_prefix_star() {
+        assert_eq!(fts_query("hel*").as_deref(), Some("\"hel\"*"));
+        assert_eq!(fts_query("hel** world").as_deref(), Some("\"hel\"* \"world\""));
+    }
+
+    #[test]
+    fn fts_query_neutralizes_operators() {
+        assert_eq!(fts_query("a OR b").as_deref(), Some("\"a\" \"OR\" \"b\""));
+        assert_eq!(fts_query("NEAR(a b)").as_deref(), Some("\"NEAR(a\" \"b)\""));
+        assert_eq!(fts_query("body:x").as_deref(), Some("\"body:x\""));
+    }
+
+    #[test]
+    fn fts_query_without_words_is_none() {
+        assert_eq!(fts_query(""), None);
+        assert_eq!(fts_query("   "), None);
+        assert_eq!(fts_query("* - ^ ( ) \"\" :"), None);
+    }
+
+    #[test]
+    fn snippet_to_html_escapes_body_and_marks_matches() {
+        let snippet = format!("x <script>alert('1')</script> & {}hello{}", SNIPPET_MATCH_START, SNIPPET_MATCH_END);
+        assert_eq!(
+            snippet_to_html(&snippet),
+            "x &lt;script&gt;alert(&#39;1&#39;)&lt;/script&gt; &amp; <b>hello</b>"
+        );
+    }
+
+    #[test]
+    fn snippet_to_html_escapes_markup_inside_matches() {
+        let snippet = format!("{}<b>\"x\"</b>{}", SNIPPET_MATCH_START, SNIPPET_MATCH_END);
+        assert_eq!(snippet_to_html(&snippet), "<b>&lt;b&gt;&quot;x&quot;&lt;/b&gt;</b>");
+    }
 }
@@ -0,0 +1,129 @@
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::future::Future;
+use std::time::Duration;
+
+use futures::Stream;
+use serde::Serialize;
+use tokio::sync::broadcast;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::time::MissedTickBehavior;
+
+// ile eventow moze czekac na wolnego subskrybenta zanim zacznie je gubic
+const EVENT_BUS_CAPACITY: usize = 256;
+// co ile sprawdzamy sesje, kiedy nic nie przychodzi
+const SESSION_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Event {
+    NewPost {
+        post_id: i64,
+        user_id: i64,
+    },
+    NewComment {
+        post_id: i64,
+        comment_id: i64,
+        user_id: i64,
+        parent_comment_id: Option<i64>,
+    },
+    ReactionCount {
+        target_type: String,
+        target_id: i64,
+        reactions: HashMap<String, i64>,
+    },
+    NewMessage {
+        conversation_id: i64,
+        message_id: i64,
+        user_id: i64,
+        #[serde(skip)]
+        member_ids: Vec<i64>,
+    },
+    Notification {
+        user_id: i64,
+        notification_id: i64,
+        kind: String,
+        unread_count: i64,
+    },
+}
+
+impl Event {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Event::NewPost { .. } => "new_post",
+            Event::NewComment { .. } => "new_comment",
+            Event::ReactionCount { .. } => "reaction_count",
+            Event::NewMessage { .. } => "new_message",
+            Event::Notification { .. } => "notification",
+        }
+    }
+
+    // powiadomienia ida tylko do adresata, wiadomosci do czlonkow rozmowy, reszta do wszystkich
+    pub fn is_visible_to(&self, user_id: i64) -> bool {
+        match self {
+            Event::NewMessage { member_ids, .. } => member_ids.contains(&user_id),
+            Event::Notification { user_id: recipient, .. } => *recipient == user_id,
+            _ => true,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct EventBus {
+    sender: broadcast::Sender<Event>,
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        EventBus::new()
+    }
+}
+
+impl EventBus {
+    pub fn new() -> EventBus {
+        let (sender, _) = broadcast::channel(EVENT_BUS_CAPACITY);
+        EventBus { sender }
+    }
+
+    pub fn publish(&self, event: Event) {
+        // Err znaczy tylko tyle, ze nikt teraz nie slucha
+        let _ = self.sender.send(event);
+    }
+
+    // session_alive jest sprawdzane przed kazdym eventem i co SESSION_CHECK_INTERVAL,
+    // po wylogowaniu, banie albo odwolaniu sesji strumien sie konczy
+    pub fn subscribe<F, Fut>(&self, user_id: i64, session_alive: F) -> impl Stream<Item = Result<warp::sse::Event, Infallible>>
+    where
+        F: Fn() -> Fut + Send + 'static,
+        Fut: Future<Output = bool> + Send,
+    {
+        let mut interval = tokio::time::interval(SESSION_CHECK_INTERVAL);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        let state = (self.sender.subscribe(), interval, session_alive);
+        futures::stream::unfold(state, move |(mut receiver, mut interval, session_alive)| async move {
+            loop {
+                tokio::select! {
+                    received = receiver.recv() => match received {
+                        Ok(event) if event.is_visible_to(user_id) => {
+                            if !session_alive().await {
+                                return None;
+                            }
+                            let sse_event = warp::sse::Event::default()
+                                .event(event.name())
+                                .json_data(&event)
+                                .unwrap();
+                            return Some((Ok(sse_event), (receiver, interval, session_alive)));
+                        }
+                        Ok(_) | Err(RecvError::Lagged(_)) => continue,
+                        Err(RecvError::Closed) => return None,
+                    },
+                    _ = interval.tick() => {
+                        if !session_alive().await {
+                            return None;
+                        }
+                    }
+                }
+            }
+        })
+    }
+}
@@ -1,194 +1,621 @@
 #![recursion_limit = "256"]
 use warp::Filter;
 pub mod auth;
+pub mod config;
+pub mod events;
 pub mod api_calls;
 pub mod types;
 pub mod database_functions;
 pub mod image_processing;
+pub mod state;
+pub mod permissions;
+pub mod sanctions;
 use crate::api_calls::*;
+use crate::config::Config;
+use crate::permissions::Permission;
+use crate::state::{with_state, AppState};
+use crate::types::{AuditLogQuery, PostSearchQuery};
 
 
-pub fn routes() -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+pub fn routes(state: AppState) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
     let get_posts_by_user = warp::get()
         .and(warp::path!("api" / "get" / "posts" / "by-user" / i64 / i64 / i64))
+        .and(warp::cookie::optional::<String>("token"))
+        .and(with_state(state.clone()))
         .and_then(get_posts_by_user);
     
     let get_post_by_id = warp::get()
         .and(warp::path!("api" / "get" / "posts" / "by-id" / i64))
+        .and(warp::cookie::optional::<String>("token"))
+        .and(with_state(state.clone()))
         .and_then(get_post_by_id);
 
     let get_posts = warp::get()
         .and(warp::path!("api" / "get" / "posts" / "new" / i64 / i64))
+        .and(warp::cookie::optional::<String>("token"))
+        .and(with_state(state.clone()))
         .and_then(get_posts);
     
     let get_posts_top = warp::get()
         .and(warp::path!("api" / "get" / "posts" / "top" / i64 / i64 / i64))
+        .and(warp::cookie::optional::<String>("token"))
+        .and(with_state(state.clone()))
         .and_then(get_posts_top);
     
     let get_posts_bottom = warp::get()
         .and(warp::path!("api" / "get" / "posts" / "bottom" / i64 / i64 / i64))
+        .and(warp::cookie::optional::<String>("token"))
+        .and(with_state(state.clone()))
         .and_then(get_posts_bottom);
     
     let get_posts_trending = warp::get()
         .and(warp::path!("api" / "get" / "posts" / "trending" / i64 / i64 / i64))
+        .and(warp::cookie::optional::<String>("token"))
+        .and(with_state(state.clone()))
         .and_then(get_posts_trending);
 
+    let get_post_revisions = warp::get()
+        .and(warp::path!("api" / "get" / "posts" / "revisions" / i64))
+        .and(warp::cookie::optional::<String>("token"))
+        .and(with_state(state.clone()))
+        .and_then(get_post_revisions);
+
+    let get_feed = warp::get()
+        .and(warp::path!("api" / "get" / "posts" / "feed" / i64 / String))
+        .and(warp::cookie::<String>("token"))
+        .and(with_state(state.clone()))
+        .and_then(get_feed);
+
+    let get_followers = warp::get()
+        .and(warp::path!("api" / "get" / "followers" / i64 / i64 / i64))
+        .and(with_state(state.clone()))
+        .and_then(get_followers);
+
+    let get_following = warp::get()
+        .and(warp::path!("api" / "get" / "following" / i64 / i64 / i64))
+        .and(with_state(state.clone()))
+        .and_then(get_following);
+
     let get_tags_from_post = warp::get()
         .and(warp::path!("api" / "get" / "tags" / "from-post" / i64))
+        .and(with_state(state.clone()))
         .and_then(get_tags_from_post);
+
+    let get_posts_by_tag = warp::get()
+        .and(warp::path!("api" / "get" / "posts" / "by-tag" / String / String / i64 / i64))
+        .and(warp::cookie::optional::<String>("token"))
+        .and(with_state(state.clone()))
+        .and_then(get_posts_by_tag);
+
+    let get_tags_autocomplete = warp::get()
+        .and(warp::path!("api" / "get" / "tags" / "autocomplete" / String / i64))
+        .and(with_state(state.clone()))
+        .and_then(get_tags_autocomplete);
+
+    let get_tags_trending = warp::get()
+        .and(warp::path!("api" / "get" / "tags" / "trending" / i64))
+        .and(with_state(state.clone()))
+        .and_then(get_tags_trending);
     
     let get_user_name = warp::get()
         .and(warp::path!("api" / "get" / "user" / "name" / i64))
+        .and(with_state(state.clone()))
         .and_then(get_user_name);
     
     let get_user_id = warp::get()
         .and(warp::path!("api" / "get" / "user" / "id" / String))
+        .and(with_state(state.clone()))
         .and_then(get_user_id);
 
     let get_profile_by_id = warp::get()
         .and(warp::path!("api" / "get" / "profile" / "by-id" / i64))
+        .and(with_state(state.clone()))
         .and_then(get_profile_by_id);
 
     let get_images_from_post = warp::get()
         .and(warp::path!("api" / "get" / "images" / "from-post" / i64))
+        .and(with_state(state.clone()))
         .and_then(get_images_from_post);
 
     let get_image = warp::get()
         .and(warp::path!("api" / "get" / "image" / ..))
-        .and(warp::fs::dir("./media/images"));
+        .and(warp::fs::dir(state.config.images_dir()));
     
     let get_like_from_post_by_user = warp::get()
         .and(warp::path!("api" / "get" / "like" / i64 / i64))
+        .and(with_state(state.clone()))
         .and_then(get_like_from_post_by_user);
+
+    let get_reactions = warp::get()
+        .and(warp::path!("api" / "get" / "reactions" / String / i64 / i64 / i64))
+        .and(warp::cookie::optional::<String>("token"))
+        .and(with_state(state.clone()))
+        .and_then(get_reactions);
+
+    let get_reaction_kinds = warp::get()
+        .and(warp::path!("api" / "get" / "reaction-kinds"))
+        .and(with_state(state.clone()))
+        .and_then(get_reaction_kinds);
     
     let get_comments_from_post = warp::get()
         .and(warp::path!("api" / "get" / "comments" / i64))
+        .and(warp::cookie::optional::<String>("token"))
+        .and(with_state(state.clone()))
         .and_then(get_comments_from_post);
 
+    let get_comment_tree_from_post = warp::get()
+        .and(warp::path!("api" / "get" / "comments" / "tree" / i64 / i64 / i64))
+        .and(warp::cookie::optional::<String>("token"))
+        .and(with_state(state.clone()))
+        .and_then(get_comment_tree_from_post);
+
     let validate_cookie = warp::get()
         .and(warp::path!("api" / "get" / "cookie"))
         .and(warp::cookie::optional::<String>("token"))
+        .and(with_state(state.clone()))
         .and_then(validate_token);
 
     let get_posts_from_search = warp::get()
         .and(warp::path!("api" / "get" / "posts" / "from-search" / String / i64 / i64 / i64))
+        .and(warp::cookie::optional::<String>("token"))
+        .and(with_state(state.clone()))
         .and_then(get_posts_from_search);
 
+    let search_posts = warp::get()
+        .and(warp::path!("api" / "get" / "posts" / "search"))
+        .and(warp::query::<PostSearchQuery>())
+        .and(warp::cookie::optional::<String>("token"))
+        .and(with_state(state.clone()))
+        .and_then(search_posts);
+
     let get_users_from_search = warp::get()
         .and(warp::path!("api" / "get" / "users" / "from-search" / String / i64 / i64))
+        .and(with_state(state.clone()))
         .and_then(get_users_from_search);
 
     let post = warp::post()
         .and(warp::path!("api" / "post" / "add-post"))
         .and(warp::cookie::<String>("token"))
         .and(post_json())
+        .and(with_state(state.clone()))
         .and_then(post);
     
+    let edit_post = warp::post()
+        .and(warp::path!("api" / "post" / "edit-post"))
+        .and(warp::cookie::<String>("token"))
+        .and(edit_post_json())
+        .and(with_state(state.clone()))
+        .and_then(edit_post);
+
     let comment = warp::post()
         .and(warp::path!("api" / "post" / "comment"))
         .and(warp::cookie::<String>("token"))
         .and(comment_json())
+        .and(with_state(state.clone()))
         .and_then(comment);
 
+    let edit_comment = warp::post()
+        .and(warp::path!("api" / "post" / "edit-comment"))
+        .and(warp::cookie::<String>("token"))
+        .and(edit_comment_json())
+        .and(with_state(state.clone()))
+        .and_then(edit_comment);
+
+    let delete_comment = warp::post()
+        .and(warp::path!("api" / "post" / "delete-comment"))
+        .and(warp::cookie::<String>("token"))
+        .and(delete_comment_json())
+        .and(with_state(state.clone()))
+        .and_then(delete_comment);
+
     let react = warp::post()
         .and(warp::path!("api" / "post" / "react"))
         .and(warp::cookie::<String>("token"))
         .and(react_json())
+        .and(with_state(state.clone()))
         .and_then(react);
     
     let unreact = warp::post()
         .and(warp::path!("api" / "post" / "unreact"))
         .and(warp::cookie::<String>("token"))
         .and(unreact_json())
+        .and(with_state(state.clone()))
         .and_then(unreact);
 
+    let follow = warp::post()
+        .and(warp::path!("api" / "post" / "follow"))
+        .and(warp::cookie::<String>("token"))
+        .and(follow_json())
+        .and(with_state(state.clone()))
+        .and_then(follow);
+
+    let unfollow = warp::post()
+        .and(warp::path!("api" / "post" / "unfollow"))
+        .and(warp::cookie::<String>("token"))
+        .and(unfollow_json())
+        .and(with_state(state.clone()))
+        .and_then(unfollow);
+
     let login = warp::post()
         .and(warp::path!("api" / "post" / "login")) 
         .and(login_json())
+        .and(with_state(state.clone()))
         .and_then(login);
     
+    // refresh_token ma Path=/api/post/refresh, wiec przegladarka wysle go tylko pod ta druga sciezka
     let logout = warp::post()
-        .and(warp::path!("api" / "post" / "logout")) 
-        .and(warp::cookie::<String>("token"))
+        .and(warp::path!("api" / "post" / "logout").or(warp::path!("api" / "post" / "refresh" / "logout")).unify())
+        .and(warp::cookie::optional::<String>("token"))
+        .and(warp::cookie::optional::<String>("refresh_token"))
+        .and(with_state(state.clone()))
         .and_then(logout);
 
+    let refresh = warp::post()
+        .and(warp::path!("api" / "post" / "refresh"))
+        .and(warp::cookie::<String>("refresh_token"))
+        .and(with_state(state.clone()))
+        .and_then(refresh);
+
+    let get_mentions = warp::get()
+        .and(warp::path!("api" / "get" / "mentions" / i64 / i64))
+        .and(warp::cookie::<String>("token"))
+        .and(with_state(state.clone()))
+        .and_then(get_mentions);
+
+    let start_conversation = warp::post()
+        .and(warp::path!("api" / "post" / "start-conversation"))
+        .and(warp::cookie::<String>("token"))
+        .and(start_conversation_json())
+        .and(with_state(state.clone()))
+        .and_then(start_conversation);
+
+    let send_message = warp::post()
+        .and(warp::path!("api" / "post" / "send-message"))
+        .and(warp::cookie::<String>("token"))
+        .and(send_message_json())
+        .and(with_state(state.clone()))
+        .and_then(send_message);
+
+    let get_conversations = warp::get()
+        .and(warp::path!("api" / "get" / "conversations" / i64 / i64))
+        .and(warp::cookie::<String>("token"))
+        .and(with_state(state.clone()))
+        .and_then(get_conversations);
+
+    let get_messages = warp::get()
+        .and(warp::path!("api" / "get" / "messages" / i64 / i64 / i64))
+        .and(warp::cookie::<String>("token"))
+        .and(with_state(state.clone()))
+        .and_then(get_messages);
+
+    let get_events = warp::get()
+        .and(warp::path!("api" / "get" / "events"))
+        .and(warp::cookie::<String>("token"))
+        .and(with_state(state.clone()))
+        .and_then(get_events);
+
+    let block = warp::post()
+        .and(warp::path!("api" / "post" / "block"))
+        .and(warp::cookie::<String>("token"))
+        .and(block_json())
+        .and(with_state(state.clone()))
+        .and_then(block);
+
+    let unblock = warp::post()
+        .and(warp::path!("api" / "post" / "unblock"))
+        .and(warp::cookie::<String>("token"))
+        .and(block_json())
+        .and(with_state(state.clone()))
+        .and_then(unblock);
+
+    let mute = warp::post()
+        .and(warp::path!("api" / "post" / "mute"))
+        .and(warp::cookie::<String>("token"))
+        .and(mute_json())
+        .and(with_state(state.clone()))
+        .and_then(mute);
+
+    let unmute = warp::post()
+        .and(warp::path!("api" / "post" / "unmute"))
+        .and(warp::cookie::<String>("token"))
+        .and(mute_json())
+        .and(with_state(state.clone()))
+        .and_then(unmute);
+
+    let get_blocked = warp::get()
+        .and(warp::path!("api" / "get" / "blocked"))
+        .and(warp::cookie::<String>("token"))
+        .and(with_state(state.clone()))
+        .and_then(get_blocked);
+
+    let create_collection = warp::post()
+        .and(warp::path!("api" / "post" / "create-collection"))
+        .and(warp::cookie::<String>("token"))
+        .and(create_collection_json())
+        .and(with_state(state.clone()))
+        .and_then(create_collection);
+
+    let delete_collection = warp::post()
+        .and(warp::path!("api" / "post" / "delete-collection"))
+        .and(warp::cookie::<String>("token"))
+        .and(delete_collection_json())
+        .and(with_state(state.clone()))
+        .and_then(delete_collection);
+
+    let add_to_collection = warp::post()
+        .and(warp::path!("api" / "post" / "add-to-collection"))
+        .and(warp::cookie::<String>("token"))
+        .and(add_to_collection_json())
+        .and(with_state(state.clone()))
+        .and_then(add_to_collection);
+
+    let remove_from_collection = warp::post()
+        .and(warp::path!("api" / "post" / "remove-from-collection"))
+        .and(warp::cookie::<String>("token"))
+        .and(remove_from_collection_json())
+        .and(with_state(state.clone()))
+        .and_then(remove_from_collection);
+
+    let repost = warp::post()
+        .and(warp::path!("api" / "post" / "repost"))
+        .and(warp::cookie::<String>("token"))
+        .and(repost_json())
+        .and(with_state(state.clone()))
+        .and_then(repost);
+
+    let unrepost = warp::post()
+        .and(warp::path!("api" / "post" / "unrepost"))
+        .and(warp::cookie::<String>("token"))
+        .and(unrepost_json())
+        .and(with_state(state.clone()))
+        .and_then(unrepost);
+
+    let get_reposts = warp::get()
+        .and(warp::path!("api" / "get" / "reposts" / i64 / i64 / i64))
+        .and(warp::cookie::optional::<String>("token"))
+        .and(with_state(state.clone()))
+        .and_then(get_reposts);
+
+    let vote_poll = warp::post()
+        .and(warp::path!("api" / "post" / "vote-poll"))
+        .and(warp::cookie::<String>("token"))
+        .and(vote_poll_json())
+        .and(with_state(state.clone()))
+        .and_then(vote_poll);
+
+    let get_collections = warp::get()
+        .and(warp::path!("api" / "get" / "collections"))
+        .and(warp::cookie::<String>("token"))
+        .and(with_state(state.clone()))
+        .and_then(get_collections);
+
+    let get_collection_posts = warp::get()
+        .and(warp::path!("api" / "get" / "collection" / i64 / i64 / i64))
+        .and(warp::cookie::<String>("token"))
+        .and(with_state(state.clone()))
+        .and_then(get_collection_posts);
+
+    let report = warp::post()
+        .and(warp::path!("api" / "post" / "report"))
+        .and(warp::cookie::<String>("token"))
+        .and(report_json())
+        .and(with_state(state.clone()))
+        .and_then(report);
+
+    let get_reports = warp::get()
+        .and(warp::path!("api" / "admin" / "get" / "reports" / i64 / i64))
+        .and(with_permission(state.clone(), Permission::ResolveReports))
+        .and(with_state(state.clone()))
+        .and_then(get_reports);
+
+    let get_report_history = warp::get()
+        .and(warp::path!("api" / "admin" / "get" / "report" / i64))
+        .and(with_permission(state.clone(), Permission::ResolveReports))
+        .and(with_state(state.clone()))
+        .and_then(get_report_history);
+
+    let resolve_report = warp::post()
+        .and(warp::path!("api" / "admin" / "post" / "resolve-report"))
+        .and(with_permission(state.clone(), Permission::ResolveReports))
+        .and(resolve_report_json())
+        .and(with_state(state.clone()))
+        .and_then(resolve_report);
+
+    let get_audit_log = warp::get()
+        .and(warp::path!("api" / "admin" / "get" / "audit-log"))
+        .and(warp::query::<AuditLogQuery>())
+        .and(with_permission(state.clone(), Permission::ViewAudit))
+        .and(with_state(state.clone()))
+        .and_then(get_audit_log);
+
+    let get_muted = warp::get()
+        .and(warp::path!("api" / "get" / "muted"))
+        .and(warp::cookie::<String>("token"))
+        .and(with_state(state.clone()))
+        .and_then(get_muted);
+
+    let get_notifications = warp::get()
+        .and(warp::path!("api" / "get" / "notifications" / i64 / i64))
+        .and(warp::cookie::<String>("token"))
+        .and(with_state(state.clone()))
+        .and_then(get_notifications);
+
+    let get_unread_notification_count = warp::get()
+        .and(warp::path!("api" / "get" / "notifications" / "unread-count"))
+        .and(warp::cookie::<String>("token"))
+        .and(with_state(state.clone()))
+        .and_then(get_unread_notification_count);
+
+    let read_notifications = warp::post()
+        .and(warp::path!("api" / "post" / "read-notifications"))
+        .and(warp::cookie::<String>("token"))
+        .and(read_notifications_json())
+        .and(with_state(state.clone()))
+        .and_then(read_notifications);
+
+    let read_all_notifications = warp::post()
+        .and(warp::path!("api" / "post" / "read-all-notifications"))
+        .and(warp::cookie::<String>("token"))
+        .and(with_state(state.clone()))
+        .and_then(read_all_notifications);
+
+    let get_sessions = warp::get()
+        .and(warp::path!("api" / "get" / "sessions"))
+        .and(warp::cookie::<String>("token"))
+        .and(with_state(state.clone()))
+        .and_then(get_sessions);
+
+    let revoke_session = warp::post()
+        .and(warp::path!("api" / "post" / "revoke-session"))
+        .and(warp::cookie::<String>("token"))
+        .and(revoke_session_json())
+        .and(with_state(state.clone()))
+        .and_then(revoke_session);
+
     let signup = warp::post()
         .and(warp::path!("api" / "post" / "signup"))
         .and(signup_json())
+        .and(with_state(state.clone()))
         .and_then(signup);
     
     let delete_user = warp::post()
         .and(warp::path!("api" / "post" / "delete-user"))
         .and(warp::cookie::<String>("token"))
         .and(delete_json())
+        .and(with_state(state.clone()))
         .and_then(delete_user);
 
     let delete_post = warp::post()
         .and(warp::path!("api" / "post" / "delete-post"))
         .and(warp::cookie::<String>("token"))
         .and(delete_post_json())
+        .and(with_state(state.clone()))
         .and_then(delete_post);
 
     let upgrade = warp::post()
         .and(warp::path!("api" / "admin" / "post" / "upgrade-user"))
-        .and(warp::cookie::<String>("token"))
+        .and(with_permission(state.clone(), Permission::ManageRoles))
         .and(upgrade_json())
+        .and(with_state(state.clone()))
         .and_then(upgrade_user);
 
+    let grant_role = warp::post()
+        .and(warp::path!("api" / "admin" / "post" / "grant-role"))
+        .and(with_permission(state.clone(), Permission::ManageRoles))
+        .and(grant_role_json())
+        .and(with_state(state.clone()))
+        .and_then(grant_role);
+
+    let revoke_role = warp::post()
+        .and(warp::path!("api" / "admin" / "post" / "revoke-role"))
+        .and(with_permission(state.clone(), Permission::ManageRoles))
+        .and(revoke_role_json())
+        .and(with_state(state.clone()))
+        .and_then(revoke_role);
+
     let ban = warp::post()
         .and(warp::path!("api" / "admin" / "post" / "ban-user"))
-        .and(warp::cookie::<String>("token"))
+        .and(with_permission(state.clone(), Permission::Ban))
         .and(ban_json())
+        .and(with_state(state.clone()))
         .and_then(ban_user);
 
+    let get_bans = warp::get()
+        .and(warp::path!("api" / "admin" / "get" / "bans" / i64 / i64))
+        .and(with_permission(state.clone(), Permission::Ban))
+        .and(with_state(state.clone()))
+        .and_then(get_bans);
+
+    let get_user_bans = warp::get()
+        .and(warp::path!("api" / "admin" / "get" / "bans" / "by-user" / i64))
+        .and(with_permission(state.clone(), Permission::Ban))
+        .and(with_state(state.clone()))
+        .and_then(get_user_bans);
+
+    let edit_ban = warp::post()
+        .and(warp::path!("api" / "admin" / "post" / "edit-ban"))
+        .and(with_permission(state.clone(), Permission::Ban))
+        .and(edit_ban_json())
+        .and(with_state(state.clone()))
+        .and_then(edit_ban);
+
     let unban = warp::post()
         .and(warp::path!("api" / "admin" / "post" / "unban-user"))
-        .and(warp::cookie::<String>("token"))
+        .and(with_permission(state.clone(), Permission::Ban))
         .and(unban_json())
+        .and(with_state(state.clone()))
         .and_then(unban_user);
 
     let change_display_name = warp::post()
         .and(warp::path!("api" / "post" / "change" / "display-name"))
         .and(warp::cookie::<String>("token"))
         .and(display_name_change_json())
+        .and(with_state(state.clone()))
         .and_then(change_display_name);
     
     let change_user_name = warp::post()
         .and(warp::path!("api" / "post" / "change" / "user-name"))
         .and(warp::cookie::<String>("token"))
         .and(user_name_change_json())
+        .and(with_state(state.clone()))
         .and_then(change_user_name);
 
     let change_description = warp::post()
         .and(warp::path!("api" / "post" / "change" / "description"))
         .and(warp::cookie::<String>("token"))
         .and(description_change_json())
+        .and(with_state(state.clone()))
         .and_then(change_description);
 
+    let change_password = warp::post()
+        .and(warp::path!("api" / "post" / "change" / "password"))
+        .and(warp::cookie::<String>("token"))
+        .and(change_password_json())
+        .and(with_state(state.clone()))
+        .and_then(change_password);
+
+    let create_password_reset = warp::post()
+        .and(warp::path!("api" / "admin" / "post" / "reset-password"))
+        .and(with_permission(state.clone(), Permission::ResetPasswords))
+        .and(create_password_reset_json())
+        .and(with_state(state.clone()))
+        .and_then(create_password_reset);
+
+    let reset_password = warp::post()
+        .and(warp::path!("api" / "post" / "reset-password"))
+        .and(reset_password_json())
+        .and(with_state(state.clone()))
+        .and_then(reset_password);
+
     let upload_image = warp::post()
         .and(warp::path!("api" / "post" / "upload" / "image"))
         .and(warp::cookie::<String>("token"))
-        .and(warp::multipart::form().max_length(25000000))
+        .and(warp::multipart::form().max_length(state.config.upload_size_limit))
+        .and(with_state(state.clone()))
         .and_then(upload_image);
     
     let add_image_to_post = warp::post()
         .and(warp::path!("api" / "post" / "add-image-to-post"))
         .and(warp::cookie::<String>("token"))
         .and(image_to_post_add_json())
+        .and(with_state(state.clone()))
         .and_then(add_image_to_post);
     
     let set_pfp = warp::post()
         .and(warp::path!("api" / "post" / "set-pfp"))
         .and(warp::cookie::<String>("token"))
         .and(set_pfp_json())
+        .and(with_state(state.clone()))
         .and_then(set_pfp);
     
     let remove_pfp = warp::post()
         .and(warp::path!("api" / "post" / "remove-pfp"))
         .and(warp::cookie::<String>("token"))
         .and(remove_pfp_json())
+        .and(with_state(state.clone()))
         .and_then(remove_pfp);
 
+    // boxed() co kilkanascie tras, inaczej zagniezdzone futures z .or() przepelniaja stos w debug buildzie
     get_posts_by_user
         .or(post)
         .or(get_posts)
@@ -210,6 +637,7 @@ pub fn routes() -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejecti
         .or(get_profile_by_id)
         .or(get_images_from_post)
         .or(get_like_from_post_by_user)
+        .boxed()
         .or(change_display_name)
         .or(change_user_name)
         .or(change_description)
@@ -225,16 +653,89 @@ pub fn routes() -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejecti
         .or(get_posts_from_search)
         .or(get_users_from_search)
         .or(remove_pfp)
+        .boxed()
+        .or(edit_post)
+        .or(get_post_revisions)
+        .or(get_sessions)
+        .or(revoke_session)
+        .or(refresh)
+        .or(change_password)
+        .or(create_password_reset)
+        .or(reset_password)
+        .or(get_feed)
+        .boxed()
+        .or(get_followers)
+        .or(get_following)
+        .or(follow)
+        .or(unfollow)
+        .or(get_comment_tree_from_post)
+        .or(edit_comment)
+        .or(delete_comment)
+        .or(search_posts)
+        .or(get_posts_by_tag)
+        .or(get_tags_autocomplete)
+        .or(get_tags_trending)
+        .or(get_mentions)
+        .or(get_notifications)
+        .or(get_unread_notification_count)
+        .boxed()
+        .or(read_notifications)
+        .or(read_all_notifications)
+        .or(get_events)
+        .or(start_conversation)
+        .or(send_message)
+        .or(get_conversations)
+        .or(get_messages)
+        .or(block)
+        .or(unblock)
+        .or(mute)
+        .or(unmute)
+        .or(get_blocked)
+        .or(get_muted)
+        .boxed()
+        .or(report)
+        .or(get_reports)
+        .or(get_report_history)
+        .or(resolve_report)
+        .or(get_audit_log)
+        .or(grant_role)
+        .or(revoke_role)
+        .or(get_bans)
+        .or(get_user_bans)
+        .or(edit_ban)
+        .boxed()
+        .or(get_reactions)
+        .or(get_reaction_kinds)
+        .or(create_collection)
+        .or(delete_collection)
+        .or(add_to_collection)
+        .or(remove_from_collection)
+        .or(get_collections)
+        .or(get_collection_posts)
+        .or(repost)
+        .or(unrepost)
+        .or(get_reposts)
+        .or(vote_poll)
 }
 
 #[tokio::main]
 async fn main() {
     tracing_subscriber::fmt::init();
-    let cors = warp::cors().allow_any_origin()
+    let config = Config::load();
+    let bind_address = config.bind_address;
+
+    let cors = warp::cors()
         .allow_methods(vec!["GET", "POST", "DELETE", "OPTIONS"])
         .allow_headers(vec!["content-type", "Access-Control-Allow-Origin"])
         .allow_credentials(true);
+    let cors = if config.cors_origins.is_empty() {
+        cors.allow_any_origin()
+    } else {
+        cors.allow_origins(config.cors_origins.iter().map(|origin| origin.as_str()))
+    };
+
+    let state = AppState::new(config).await;
 
-    let routes = routes().recover(handle_rejection).with(cors); // change back to do error handling .recover(handle_rejection)
-    warp::serve(routes).run(([0, 0, 0, 0], 8000)).await;
+    let routes = routes(state).recover(handle_rejection).with(cors); // change back to do error handling .recover(handle_rejection)
+    warp::serve(routes).run(bind_address).await;
 }
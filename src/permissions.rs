@@ -0,0 +1,38 @@
+// uprawnienia wynikaja tylko z roli, rola siedzi w users.role i w claimsach tokena
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Permission {
+    Ban,
+    DeleteAnyPost,
+    EditAnyPost,
+    ResolveReports,
+    ResetPasswords,
+    ManageRoles,
+    ViewAudit,
+    BypassRateLimit,
+}
+
+// od najslabszej do najmocniejszej
+pub const ROLES: [&str; 3] = ["user", "moderator", "admin"];
+
+const MODERATOR_PERMISSIONS: [Permission; 4] = [
+    Permission::Ban,
+    Permission::DeleteAnyPost,
+    Permission::ResolveReports,
+    Permission::BypassRateLimit,
+];
+
+pub fn has_permission(role: &str, permission: Permission) -> bool {
+    match role {
+        "admin" => true,
+        "moderator" => MODERATOR_PERMISSIONS.contains(&permission),
+        _ => false,
+    }
+}
+
+pub fn is_role(role: &str) -> bool {
+    ROLES.contains(&role)
+}
+
+pub fn role_rank(role: &str) -> usize {
+    ROLES.iter().position(|r| *r == role).unwrap_or(0)
+}
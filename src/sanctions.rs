@@ -0,0 +1,28 @@
+// stopien kary siedzi w bans.level, przy kilku aktywnych liczy sie najmocniejsza
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Sanction {
+    None,
+    // tresci widzi tylko autor
+    Shadow,
+    // moze sie logowac i przegladac, ale nic nie pisze
+    ReadOnly,
+    Ban,
+}
+
+// od najslabszej do najmocniejszej, tak jak w enumie
+pub const SANCTION_LEVELS: [&str; 3] = ["shadow", "read_only", "ban"];
+
+impl Sanction {
+    pub fn from_level(level: &str) -> Option<Sanction> {
+        match level {
+            "shadow" => Some(Sanction::Shadow),
+            "read_only" => Some(Sanction::ReadOnly),
+            "ban" => Some(Sanction::Ban),
+            _ => None,
+        }
+    }
+}
+
+pub fn is_sanction_level(level: &str) -> bool {
+    SANCTION_LEVELS.contains(&level)
+}
@@ -0,0 +1,86 @@
+use std::convert::Infallible;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tokio_rusqlite::Connection;
+use tracing::{info, warn};
+use warp::Filter;
+
+use crate::config::Config;
+use crate::events::EventBus;
+
+const DB_BUSY_TIMEOUT_MS: u64 = 5000;
+
+#[derive(Clone)]
+pub struct AppState {
+    pub db: DbPool,
+    pub config: Arc<Config>,
+    pub events: EventBus,
+}
+
+impl AppState {
+    pub async fn new(config: Config) -> AppState {
+        AppState {
+            db: DbPool::open(&config.db_path, config.db_pool_size).await,
+            config: Arc::new(config),
+            events: EventBus::new(),
+        }
+    }
+}
+
+// tokio_rusqlite::Connection to jest handle do watku z polaczeniem, wiec pula
+// to po prostu kilka takich watkow rozdawanych po kolei
+#[derive(Clone)]
+pub struct DbPool {
+    path: Arc<String>,
+    connections: Arc<Vec<Mutex<Connection>>>,
+    next: Arc<AtomicUsize>,
+}
+
+impl DbPool {
+    pub async fn open(path: &str, size: usize) -> DbPool {
+        let mut connections = Vec::with_capacity(size);
+        for _ in 0..size {
+            connections.push(Mutex::new(open_connection(path).await.unwrap()));
+        }
+        info!("Opened {} connections to {}", size, path);
+
+        DbPool {
+            path: Arc::new(path.to_string()),
+            connections: Arc::new(connections),
+            next: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    // panic w closure zabija watek polaczenia na zawsze (tokio-rusqlite nie lapie panikow),
+    // wiec martwe polaczenie otwieramy od nowa zamiast oddawac je dalej
+    pub async fn get(&self) -> Connection {
+        let i = self.next.fetch_add(1, Ordering::Relaxed) % self.connections.len();
+        let connection = self.connections[i].lock().unwrap().clone();
+        match connection.call(|_| Ok(())).await {
+            Err(tokio_rusqlite::Error::ConnectionClosed) => {
+                warn!("Connection {} to {} closed, reopening", i, self.path);
+                let connection = open_connection(&self.path).await.unwrap();
+                *self.connections[i].lock().unwrap() = connection.clone();
+                connection
+            }
+            _ => connection,
+        }
+    }
+}
+
+async fn open_connection(path: &str) -> Result<Connection, tokio_rusqlite::Error> {
+    let connection = Connection::open(path).await?;
+    connection.call(|conn| {
+        conn.pragma_update_and_check(None, "journal_mode", "WAL", |row| row.get::<_, String>(0))?;
+        conn.pragma_update(None, "synchronous", "NORMAL")?;
+        conn.busy_timeout(Duration::from_millis(DB_BUSY_TIMEOUT_MS))?;
+        Ok(())
+    }).await?;
+    Ok(connection)
+}
+
+pub fn with_state(state: AppState) -> impl Filter<Extract = (AppState,), Error = Infallible> + Clone {
+    warp::any().map(move || state.clone())
+}
@@ -1,23 +1,86 @@
 use serde::{Serialize, Deserialize};
+use std::collections::HashMap;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Claims {
    pub uid: i64,
    pub exp: u64,
-   pub is_admin: i64,
+   pub role: String,
+   pub jti: String,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct Session {
+    pub session_id: String,
+    pub created_on: i64,
+    pub expires_on: i64,
+    pub is_current: bool,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
 pub struct Post {
     pub post_id: i64,
     pub user_id: i64,
     pub date: i64,
     pub body: String,
     pub likes: i64,
+    pub reactions: HashMap<String, i64>,
     pub user_name: String,
     pub display_name: String,
     pub pfp_image: String,
-    pub like_count: i64
+    pub like_count: i64,
+    pub edited_at: Option<i64>,
+    pub repost_count: i64,
+    pub quote_count: i64,
+    pub quoted_post_id: Option<i64>,
+    pub quoted_post: Option<QuotedPost>,
+    pub reposted_by: Option<RepostInfo>,
+    pub poll: Option<Poll>,
+}
+
+// status: "ok", "deleted" albo "unavailable" (autor zbanowany, ukryty albo zablokowany)
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+pub struct QuotedPost {
+    pub post_id: i64,
+    pub status: String,
+    pub post: Option<Box<Post>>,
+}
+
+// kto udostepnil post, tylko w wynikach get_posts_by_user i get_feed
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+pub struct RepostInfo {
+    pub user_id: i64,
+    pub user_name: String,
+    pub display_name: String,
+    pub date: i64,
+}
+
+// wyniki (votes, voter_count) sa None dopoki pytajacy nie zaglosowal albo ankieta sie nie zamknela
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+pub struct Poll {
+    pub multiple_choice: bool,
+    pub closes_on: Option<i64>,
+    pub is_closed: bool,
+    pub has_voted: bool,
+    pub voter_count: Option<i64>,
+    pub options: Vec<PollOption>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+pub struct PollOption {
+    pub option_id: i64,
+    pub text: String,
+    pub votes: Option<i64>,
+    pub voted: bool,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct PostRevision {
+    pub revision_id: i64,
+    pub post_id: i64,
+    pub body: String,
+    pub tags: Vec<String>,
+    pub date: i64,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
@@ -30,15 +93,22 @@ pub struct Comment {
     pub user_name: String,
     pub display_name: String,
     pub pfp_image: String,
+    pub parent_comment_id: Option<i64>,
+    pub edited_at: Option<i64>,
+    pub reply_count: i64,
+    pub reactions: HashMap<String, i64>,
+    pub replies: Vec<Comment>,
 }
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
 pub struct Profile {
     pub user_id: i64,
     pub user_name: String,
     pub display_name: String,
     pub description: String,
-    pub pfp_image: String
+    pub pfp_image: String,
+    pub follower_count: i64,
+    pub following_count: i64,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
@@ -46,16 +116,262 @@ pub struct PostList {
     pub post_list: Vec<Post>
 }
 
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct SearchResult {
+    pub post: Post,
+    pub snippet: String,
+    pub rank: f64,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct SearchResultList {
+    pub result_list: Vec<SearchResult>
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+pub struct PostSearchQuery {
+    #[serde(default)]
+    pub q: String,
+    pub tag: Option<String>,
+    pub author: Option<String>,
+    pub from: Option<i64>,
+    pub to: Option<i64>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct FeedPage {
+    pub post_list: Vec<Post>,
+    pub next_cursor: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct RevisionList {
+    pub revision_list: Vec<PostRevision>
+}
+
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct ProfileList {
     pub profile_list: Vec<Profile>
 }
 
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct SessionList {
+    pub session_list: Vec<Session>
+}
+
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct TagList {
     pub tag_list: Vec<String>
 }
 
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct Mention {
+    pub mention_id: i64,
+    pub post_id: i64,
+    pub comment_id: Option<i64>,
+    pub author_id: i64,
+    pub user_name: String,
+    pub display_name: String,
+    pub pfp_image: String,
+    pub body: String,
+    pub date: i64,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct MentionList {
+    pub mention_list: Vec<Mention>
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct Notification {
+    pub notification_id: i64,
+    pub kind: String,
+    pub post_id: Option<i64>,
+    pub comment_id: Option<i64>,
+    pub date: i64,
+    pub is_read: bool,
+    pub actor_count: i64,
+    pub actor_id: i64,
+    pub user_name: String,
+    pub display_name: String,
+    pub pfp_image: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct NotificationList {
+    pub notification_list: Vec<Notification>,
+    pub unread_count: i64,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct NotificationReadRequest {
+    pub notification_ids: Vec<i64>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct Message {
+    pub message_id: i64,
+    pub conversation_id: i64,
+    pub user_id: i64,
+    pub user_name: String,
+    pub display_name: String,
+    pub pfp_image: String,
+    pub body: String,
+    pub date: i64,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct MessageList {
+    pub message_list: Vec<Message>
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct ConversationMember {
+    pub user_id: i64,
+    pub user_name: String,
+    pub display_name: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct Conversation {
+    pub conversation_id: i64,
+    pub is_group: bool,
+    pub title: Option<String>,
+    pub members: Vec<ConversationMember>,
+    pub last_message: Option<Message>,
+    pub unread_count: i64,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct ConversationList {
+    pub conversation_list: Vec<Conversation>
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct ConversationCreateRequest {
+    pub user_ids: Vec<i64>,
+    #[serde(default)]
+    pub title: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct MessageSendRequest {
+    pub conversation_id: i64,
+    pub body: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct Report {
+    pub report_id: i64,
+    pub reporter_id: i64,
+    pub reporter_name: String,
+    pub target_type: String,
+    pub target_id: i64,
+    pub author_id: i64,
+    pub reason: String,
+    pub details: String,
+    pub date: i64,
+    pub status: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct ReportTarget {
+    pub target_type: String,
+    pub target_id: i64,
+    pub author_id: i64,
+    pub report_count: i64,
+    pub last_reported: i64,
+    pub reports: Vec<Report>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct ReportTargetList {
+    pub target_list: Vec<ReportTarget>
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct ReportAction {
+    pub action_id: i64,
+    pub report_id: i64,
+    pub admin_id: i64,
+    pub admin_name: String,
+    pub action: String,
+    pub note: String,
+    pub date: i64,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct ReportHistory {
+    pub report: Report,
+    pub action_list: Vec<ReportAction>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct AuditLogEntry {
+    pub log_id: i64,
+    pub actor_id: i64,
+    pub actor_name: String,
+    pub action: String,
+    pub target_type: String,
+    pub target_id: i64,
+    pub payload: serde_json::Value,
+    pub date: i64,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct AuditLogList {
+    pub entry_list: Vec<AuditLogEntry>
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+pub struct AuditLogQuery {
+    pub actor_id: Option<i64>,
+    pub action: Option<String>,
+    pub target_type: Option<String>,
+    pub target_id: Option<i64>,
+    pub from: Option<i64>,
+    pub to: Option<i64>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct ReportCreateRequest {
+    pub target_type: String,
+    pub target_id: i64,
+    pub reason: String,
+    #[serde(default)]
+    pub details: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct ReportResolveRequest {
+    pub target_type: String,
+    pub target_id: i64,
+    pub action: String,
+    #[serde(default)]
+    pub note: String,
+    #[serde(default)]
+    pub ban_length: i64,
+    #[serde(default)]
+    pub ban_message: String,
+    #[serde(default)]
+    pub ban_level: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct TagCount {
+    pub tag_name: String,
+    pub post_count: i64,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct TagCountList {
+    pub tag_list: Vec<TagCount>
+}
+
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct CommentList {
     pub comment_list: Vec<Comment>
@@ -67,8 +383,18 @@ pub struct ImageList {
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
-pub struct LikeCount {
-    pub like_count: i64
+pub struct Reaction {
+    pub user_id: i64,
+    pub user_name: String,
+    pub display_name: String,
+    pub pfp_image: String,
+    pub kind: String,
+    pub date: i64,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct ReactionList {
+    pub reaction_list: Vec<Reaction>
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
@@ -85,26 +411,139 @@ pub struct SignupRequest {
     pub remember_password: bool 
 }
 
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct SessionRevokeRequest {
+    pub session_id: String,
+}
+
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct PostCreateRequest {
     pub body: String,
     pub tags: Vec<String>,
+    #[serde(default)]
+    pub quoted_post_id: Option<i64>,
+    #[serde(default)]
+    pub poll: Option<PollCreateRequest>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct PollCreateRequest {
+    pub options: Vec<String>,
+    #[serde(default)]
+    pub closes_on: Option<i64>,
+    #[serde(default)]
+    pub multiple_choice: bool,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct PostEditRequest {
+    pub post_id: i64,
+    pub body: String,
+    pub tags: Vec<String>,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct CommentCreateRequest {
     pub post_id: i64,
     pub body: String,
+    #[serde(default)]
+    pub parent_comment_id: Option<i64>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct CommentEditRequest {
+    pub comment_id: i64,
+    pub body: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct CommentDeleteRequest {
+    pub comment_id: i64,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct ReactRequest {
+    pub target_type: String,
+    pub target_id: i64,
+    pub kind: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct UnreactRequest {
+    pub target_type: String,
+    pub target_id: i64,
+    pub kind: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct FollowRequest {
+    pub user_id: i64,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct Collection {
+    pub collection_id: i64,
+    pub name: String,
+    pub date: i64,
+    pub post_count: i64,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct CollectionList {
+    pub collection_list: Vec<Collection>
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct CollectionCreateRequest {
+    pub name: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct CollectionDeleteRequest {
+    pub collection_id: i64,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct CollectionAddRequest {
+    pub collection_id: i64,
+    pub post_id: i64,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct CollectionRemoveRequest {
+    pub collection_id: i64,
+    pub post_id: i64,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct RepostRequest {
+    pub post_id: i64,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
-pub struct LikeRequest {
+pub struct UnrepostRequest {
     pub post_id: i64,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
-pub struct UnlikeRequest {
+pub struct PollVoteRequest {
     pub post_id: i64,
+    pub option_ids: Vec<i64>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct BlockRequest {
+    pub user_id: i64,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct MuteRequest {
+    pub user_id: i64,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct UnfollowRequest {
+    pub user_id: i64,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
@@ -122,11 +561,57 @@ pub struct UserUpgradeRequest {
     pub user_id: i64,
 }
 
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct RoleGrantRequest {
+    pub user_id: i64,
+    pub role: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct RoleRevokeRequest {
+    pub user_id: i64,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct Ban {
+    pub ban_id: i64,
+    pub user_id: i64,
+    pub user_name: String,
+    pub given_on: i64,
+    pub expires_on: i64,
+    pub ban_message: String,
+    pub level: String,
+    pub is_active: bool,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct BanList {
+    pub ban_list: Vec<Ban>
+}
+
+// to widzi sam zbanowany user przy logowaniu
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct BanInfo {
+    pub ban_message: String,
+    pub expires_on: i64,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct BanEditRequest {
+    pub ban_id: i64,
+    #[serde(default)]
+    pub expires_on: Option<i64>,
+    #[serde(default)]
+    pub ban_message: Option<String>,
+}
+
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct UserBanRequest {
     pub user_id: i64,
     pub ban_length: i64,
     pub ban_message: String,
+    #[serde(default)]
+    pub level: Option<String>,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
@@ -149,6 +634,24 @@ pub struct DescriptionChangeRequest {
     pub new_description: String,
 }
 
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct PasswordChangeRequest {
+    pub passwd: String,
+    pub new_passwd: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct PasswordResetCreateRequest {
+    pub user_id: i64,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct PasswordResetRequest {
+    pub user_name: String,
+    pub reset_code: String,
+    pub new_passwd: String,
+}
+
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct AddImageToPostRequest {
     pub image_id: i64,